@@ -0,0 +1,151 @@
+use crate::workspace::Workspace;
+use regex::Regex;
+use std::path::Path;
+
+const ATTRIBUTES_FILE: &str = ".gitattributes";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum State {
+    Set,
+    Unset,
+    Value(String),
+}
+
+#[derive(Clone, Debug)]
+struct Rule {
+    pattern: Regex,
+    basename: bool,
+    attrs: Vec<(String, State)>,
+}
+
+/// The rules from the top-level `.gitattributes`, where later lines win.
+#[derive(Clone, Debug, Default)]
+pub struct Attributes {
+    rules: Vec<Rule>,
+}
+
+impl Attributes {
+    pub fn load(workspace: &Workspace) -> Self {
+        match workspace.read_file(ATTRIBUTES_FILE) {
+            Ok(data) => Attributes::parse(&String::from_utf8_lossy(&data)),
+            Err(_) => Attributes::default(),
+        }
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let mut rules = vec![];
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let glob = parts.next().unwrap();
+            let mut attrs = vec![];
+            for attr in parts {
+                expand(attr, &mut attrs);
+            }
+            let basename = !glob.contains('/');
+            if let Ok(pattern) = glob_to_regex(glob.trim_start_matches('/')) {
+                rules.push(Rule {
+                    pattern,
+                    basename,
+                    attrs,
+                });
+            }
+        }
+        Self { rules }
+    }
+
+    pub fn get<P: AsRef<Path>>(&self, path: P, attr: &str) -> Option<State> {
+        let path = path.as_ref();
+        let full = path.to_str().unwrap_or("");
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or(full);
+        self.rules
+            .iter()
+            .rev()
+            .filter(|r| r.pattern.is_match(if r.basename { name } else { full }))
+            .filter_map(|r| r.attrs.iter().rev().find(|(a, _)| a == attr))
+            .map(|(_, state)| state.clone())
+            .next()
+    }
+
+    /// Whether the `diff` attribute forces a path to be treated as binary
+    /// (`-diff`, or the `binary` macro) or as text (`diff`).
+    pub fn is_binary<P: AsRef<Path>>(&self, path: P) -> Option<bool> {
+        match self.get(path, "diff") {
+            Some(State::Unset) => Some(true),
+            Some(State::Set) => Some(false),
+            _ => None,
+        }
+    }
+}
+
+fn expand(attr: &str, attrs: &mut Vec<(String, State)>) {
+    if attr == "binary" {
+        for name in &["diff", "merge", "text"] {
+            attrs.push((String::from(*name), State::Unset));
+        }
+        attrs.push((String::from("binary"), State::Set));
+    } else if let Some(name) = attr.strip_prefix('-') {
+        attrs.push((String::from(name), State::Unset));
+    } else if let Some(pos) = attr.find('=') {
+        let (name, value) = attr.split_at(pos);
+        attrs.push((String::from(name), State::Value(value[1..].to_owned())));
+    } else if !attr.starts_with('!') {
+        attrs.push((String::from(attr), State::Set));
+    }
+}
+
+fn glob_to_regex(glob: &str) -> Result<Regex, regex::Error> {
+    let mut re = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    re.push_str("(?:.*/)?");
+                } else {
+                    re.push_str(".*");
+                }
+            }
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    Regex::new(&re)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Attributes, State};
+
+    #[test]
+    fn test_binary_macro() {
+        let attrs = Attributes::parse("*.png binary\n*.txt diff\n");
+        assert_eq!(attrs.is_binary("img/logo.png"), Some(true));
+        assert_eq!(attrs.is_binary("notes.txt"), Some(false));
+        assert_eq!(attrs.is_binary("main.rs"), None);
+    }
+
+    #[test]
+    fn test_later_rules_win() {
+        let attrs = Attributes::parse("*.dat -diff\ndocs/*.dat diff\n");
+        assert_eq!(attrs.is_binary("x/a.dat"), Some(true));
+        assert_eq!(attrs.is_binary("docs/a.dat"), Some(false));
+        assert_eq!(attrs.get("docs/a.dat", "merge"), None);
+    }
+
+    #[test]
+    fn test_values() {
+        let attrs = Attributes::parse("**/*.c diff=cpp\n");
+        assert_eq!(
+            attrs.get("src/lib/x.c", "diff"),
+            Some(State::Value(String::from("cpp")))
+        );
+    }
+}
@@ -1,60 +1,126 @@
 use chrono::prelude::*;
+use failure::format_err;
+use std::borrow::Cow;
 use std::convert::TryFrom;
 
-#[derive(Debug)]
+/// An identity line as git writes it: `name <email> timestamp timezone`.
+/// The name and email are kept as the bytes written, which need not be
+/// UTF-8, and the timezone exactly as written, so the line round-trips.
+#[derive(Clone, Debug, PartialEq)]
 pub struct Author {
-    name: String,
-    email: String,
-    time: DateTime<Local>,
+    name: Vec<u8>,
+    email: Vec<u8>,
+    timestamp: i64,
+    timezone: String,
 }
 
 impl Author {
     pub fn new(name: String, email: String, time: DateTime<Local>) -> Self {
-        Author { name, email, time }
+        Author {
+            name: name.into_bytes(),
+            email: email.into_bytes(),
+            timestamp: time.timestamp(),
+            timezone: time.format("%z").to_string(),
+        }
+    }
+
+    /// The identity recorded for ref updates: the committer if one is set,
+    /// otherwise the author.
+    pub fn committer() -> Self {
+        let var = |names: &[&str]| {
+            names
+                .iter()
+                .filter_map(|n| std::env::var(n).ok())
+                .next()
+                .unwrap_or_else(|| String::from("unknown"))
+        };
+        let name = var(&["GIT_COMMITTER_NAME", "GIT_AUTHOR_NAME"]);
+        let email = var(&["GIT_COMMITTER_EMAIL", "GIT_AUTHOR_EMAIL"]);
+        Author::new(name, email, Local::now())
+    }
+
+    pub fn name(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.name)
+    }
+
+    pub fn email(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.email)
+    }
+
+    /// The identity line exactly as it was read.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut line = self.name.clone();
+        line.extend_from_slice(b" <");
+        line.extend_from_slice(&self.email);
+        line.extend_from_slice(format!("> {} {}", self.timestamp, self.timezone).as_bytes());
+        line
+    }
+
+    pub fn time(&self) -> DateTime<Local> {
+        Local
+            .timestamp_opt(self.timestamp, 0)
+            .single()
+            .unwrap_or_else(Local::now)
+    }
+
+    /// The time in the timezone it was recorded in.
+    pub fn local_time(&self) -> DateTime<FixedOffset> {
+        let offset = DateTime::parse_from_str(&format!("0 {}", self.timezone), "%s %z")
+            .map(|t| *t.offset())
+            .unwrap_or_else(|_| FixedOffset::east_opt(0).unwrap());
+        self.time().with_timezone(&offset)
     }
 
     pub fn short_date(&self) -> String {
-        self.time.format("%Y-%m-%d").to_string()
+        self.local_time().format("%Y-%m-%d").to_string()
     }
 }
 
-impl TryFrom<&str> for Author {
+impl TryFrom<&[u8]> for Author {
     type Error = failure::Error;
 
-    fn try_from(line: &str) -> Result<Self, Self::Error> {
-        let mut parts = line.split('<');
-        let name = parts
-            .next()
-            .map(|x| String::from(x.trim()))
-            .expect("failed to get name");
-
-        let line = parts.next().unwrap();
-        let mut matches = line.split_whitespace();
-        let email = matches
-            .next()
-            .expect("failed to get email")
-            .trim_matches(|c| c == '<' || c == '>');
-
-        let time = matches.collect::<Vec<&str>>().join(" ");
-        let time = Local
-            .datetime_from_str(time.as_ref(), "%s %z")
-            .unwrap_or_else(|_| Local::now());
+    fn try_from(line: &[u8]) -> Result<Self, Self::Error> {
+        let malformed = || format_err!("malformed identity: {}", String::from_utf8_lossy(line));
+        let open = line.iter().position(|&b| b == b'<').ok_or_else(malformed)?;
+        let close = open
+            + line[open..]
+                .iter()
+                .position(|&b| b == b'>')
+                .ok_or_else(malformed)?;
+
+        let name = &line[..open];
+        let name = name.strip_suffix(b" ").unwrap_or(name);
+        let email = &line[open + 1..close];
+
+        let mut date = std::str::from_utf8(&line[close + 1..])?.split_whitespace();
+        let timestamp = date.next().ok_or_else(malformed)?.parse()?;
+        let timezone = date.next().unwrap_or("+0000");
         Ok(Self {
-            name,
-            email: String::from(email),
-            time,
+            name: name.to_vec(),
+            email: email.to_vec(),
+            timestamp,
+            timezone: String::from(timezone),
         })
     }
 }
 
+impl TryFrom<&str> for Author {
+    type Error = failure::Error;
+
+    fn try_from(line: &str) -> Result<Self, Self::Error> {
+        Author::try_from(line.as_bytes())
+    }
+}
+
 impl std::fmt::Display for Author {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(
             f,
-            "{} <{}> {}",
-            self.name,
-            self.email,
-            self.time.format("%s %z")
+            "{} <{}> {} {}",
+            self.name(),
+            self.email(),
+            self.timestamp,
+            self.timezone
         )
     }
 }
@@ -1,43 +1,360 @@
-use crate::database::ObjectKind;
-use crate::repository::Repository;
+use crate::commit::Commit;
+use crate::database::{Database, ObjectKind};
+use crate::refs::Refs;
+use crate::revision::walk::{ahead_behind, is_ancestor};
 use crate::revision::RevisionResolver;
+use crate::utilities::glob_match;
 use crate::BoxResult;
 use clap::{App, Arg, ArgMatches, SubCommand};
+use colored::Colorize;
+use std::convert::TryFrom;
 
 pub fn cli() -> App<'static, 'static> {
     SubCommand::with_name("branch")
-        .arg(Arg::with_name("BRANCH").required(true).index(1))
-        .arg(Arg::with_name("START").required(false).index(2))
+        .arg(
+            Arg::with_name("list")
+                .short("l")
+                .long("list")
+                .help("List branches, optionally only those matching PATTERN."),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .multiple(true)
+                .help("Show the tip of each branch; twice to name its upstream too."),
+        )
+        .arg(
+            Arg::with_name("contains")
+                .long("contains")
+                .takes_value(true)
+                .value_name("COMMIT")
+                .help("Only list branches which contain COMMIT."),
+        )
+        .arg(
+            Arg::with_name("delete")
+                .short("d")
+                .long("delete")
+                .help("Delete branches that are merged into their upstream or HEAD."),
+        )
+        .arg(
+            Arg::with_name("force-delete")
+                .short("D")
+                .help("Delete branches whether they are merged or not."),
+        )
+        .arg(
+            Arg::with_name("move")
+                .short("m")
+                .long("move")
+                .help("Rename a branch, along with its reflog and config."),
+        )
+        .arg(
+            Arg::with_name("force-move")
+                .short("M")
+                .help("Rename a branch even if the new name already exists."),
+        )
+        .arg(
+            Arg::with_name("set-upstream-to")
+                .short("u")
+                .long("set-upstream-to")
+                .takes_value(true)
+                .value_name("UPSTREAM")
+                .help("Set the branch that BRANCH, or the current branch, tracks."),
+        )
+        .arg(
+            Arg::with_name("unset-upstream")
+                .long("unset-upstream")
+                .help("Stop BRANCH, or the current branch, tracking an upstream."),
+        )
+        .group(clap::ArgGroup::with_name("mode").args(&[
+            "list",
+            "delete",
+            "force-delete",
+            "move",
+            "force-move",
+            "set-upstream-to",
+            "unset-upstream",
+        ]))
+        .arg(
+            Arg::with_name("BRANCH").index(1).multiple(true).help(
+                "The branch to create followed by its start point, or the branches to act on",
+            ),
+        )
 }
 
 pub fn exec(matches: &ArgMatches) -> BoxResult<()> {
     let root = std::path::Path::new(".");
-    let repository = Repository::new(root)?;
-    let name = matches
-        .value_of("BRANCH")
-        .expect("failed to specify branch name");
-
-    let start_oid = if let Some(start) = matches.value_of("START") {
-        let mut rr = RevisionResolver::new(&repository.database, &repository.refs, start);
-        let res = rr.resolver(ObjectKind::Commit);
-        if let Err(e) = res {
+    let db = Database::new(root.join(".git/objects"));
+    let refs = Refs::new(root.join(".git"));
+
+    let names: Vec<&str> = matches.values_of("BRANCH").into_iter().flatten().collect();
+
+    let force_delete = matches.is_present("force-delete");
+    let force_move = matches.is_present("force-move");
+    if matches.is_present("delete") || force_delete {
+        delete(&db, &refs, &names, force_delete)
+    } else if matches.is_present("move") || force_move {
+        rename(&refs, &names, force_move)
+    } else if let Some(upstream) = matches.value_of("set-upstream-to") {
+        set_upstream(&refs, upstream, names.first().copied())
+    } else if matches.is_present("unset-upstream") {
+        unset_upstream(&refs, names.first().copied())
+    } else if matches.is_present("list")
+        || names.is_empty()
+        || matches.is_present("verbose")
+        || matches.is_present("contains")
+    {
+        list(&db, &refs, &names, matches)
+    } else {
+        create(&db, &refs, &names)
+    }
+}
+
+fn fatal(message: String) -> ! {
+    eprintln!("fatal: {}", message);
+    std::process::exit(128);
+}
+
+fn resolve_commit(db: &Database, refs: &Refs, revision: &str) -> String {
+    let mut rr = RevisionResolver::new(db, refs, revision);
+    match rr.resolver(ObjectKind::Commit) {
+        Ok(oid) => oid,
+        Err(e) => {
             for error in rr.errors {
                 eprintln!("{}", error);
             }
-            eprintln!("fatal: {}", e);
-            None
+            fatal(e.to_string())
+        }
+    }
+}
+
+/// The branch a command without a BRANCH argument acts on.
+fn current_branch(refs: &Refs, action: &str) -> String {
+    match refs.current_ref() {
+        Some(name) => refs.short_name(&name),
+        None => fatal(format!("cannot {} while not on any branch.", action)),
+    }
+}
+
+fn create(db: &Database, refs: &Refs, names: &[&str]) -> BoxResult<()> {
+    let (name, start) = match names {
+        [name] => (*name, None),
+        [name, start] => (*name, Some(*start)),
+        _ => fatal(String::from("too many arguments for a create operation")),
+    };
+
+    let start_oid = match start {
+        Some(start) => Some(resolve_commit(db, refs, start)),
+        None => refs.get_head(),
+    };
+    let message = format!("branch: Created from {}", start.unwrap_or("HEAD"));
+    if let Err(e) = refs.create_branch(name, start_oid, &message) {
+        fatal(e.to_string());
+    }
+    Ok(())
+}
+
+fn list(db: &Database, refs: &Refs, patterns: &[&str], matches: &ArgMatches) -> BoxResult<()> {
+    let verbose = matches.occurrences_of("verbose");
+    let contains = matches
+        .value_of("contains")
+        .map(|commit| resolve_commit(db, refs, commit));
+    let includes = |oid: &str| -> BoxResult<bool> {
+        Ok(match contains {
+            Some(ref commit) => is_ancestor(db, commit, oid)?,
+            None => true,
+        })
+    };
+
+    let mut branches = vec![];
+    for (name, oid) in refs.list_branches() {
+        if (patterns.is_empty() || patterns.iter().any(|p| glob_match(p, &name))) && includes(&oid)?
+        {
+            branches.push((name, oid));
+        }
+    }
+
+    if let (None, Some(head)) = (refs.current_ref(), refs.get_head()) {
+        if patterns.is_empty() && includes(&head)? {
+            let name = format!("(HEAD detached at {})", db.truncate_oid(&head));
+            branches.insert(0, (name, head));
+        }
+    }
+
+    let current = refs.current_ref().map(|name| refs.short_name(&name));
+    let width = branches.iter().map(|(n, _)| n.len()).max().unwrap_or(0);
+    for (name, oid) in &branches {
+        let is_current = current.as_ref() == Some(name) || name.starts_with('(');
+        let marker = if is_current { "* " } else { "  " };
+        let label = if verbose > 0 {
+            format!("{:<width$}", name, width = width)
         } else {
-            res.ok()
+            name.clone()
+        };
+        let label = if is_current {
+            label.green()
+        } else {
+            label.normal()
+        };
+        if verbose == 0 {
+            println!("{}{}", marker, label);
+            continue;
         }
-    } else {
-        repository.refs.get_head()
+
+        let (_, _, data) = db.read_object(oid)?;
+        let commit = Commit::try_from(data)?;
+        let tracking = if name.starts_with('(') {
+            String::new()
+        } else {
+            tracking(db, refs, name, oid, verbose > 1)?
+        };
+        println!(
+            "{}{} {} {}{}",
+            marker,
+            label,
+            db.truncate_oid(oid),
+            tracking,
+            commit.title_line().unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+/// How a branch compares with its upstream, as `-v` shows it: the counts
+/// alone, or with `named` the upstream's name too.
+fn tracking(db: &Database, refs: &Refs, name: &str, oid: &str, named: bool) -> BoxResult<String> {
+    let upstream = match refs.upstream(name) {
+        Some(upstream) => upstream,
+        None => return Ok(String::new()),
     };
+    let mut parts = vec![];
+    match refs.read_ref(&upstream) {
+        Some(upstream_oid) => {
+            let (ahead, behind) = ahead_behind(db, oid, &upstream_oid)?;
+            if ahead > 0 {
+                parts.push(format!("ahead {}", ahead));
+            }
+            if behind > 0 {
+                parts.push(format!("behind {}", behind));
+            }
+        }
+        None => parts.push(String::from("gone")),
+    }
 
-    if start_oid.is_some() {
-        if let Err(e) = repository.refs.create_branch(name, start_oid) {
-            eprintln!("fatal: {}", e);
+    let counts = parts.join(", ");
+    let shown = match (named, counts.is_empty()) {
+        (false, true) => return Ok(String::new()),
+        (false, false) => counts,
+        (true, true) => format!("{}", refs.short_name(&upstream).blue()),
+        (true, false) => format!("{}: {}", refs.short_name(&upstream).blue(), counts),
+    };
+    Ok(format!("[{}] ", shown))
+}
+
+fn delete(db: &Database, refs: &Refs, names: &[&str], force: bool) -> BoxResult<()> {
+    if names.is_empty() {
+        fatal(String::from("branch name required"));
+    }
+    let current = refs.current_ref().map(|name| refs.short_name(&name));
+    let head = refs.get_head();
+
+    let mut failed = false;
+    for name in names {
+        let oid = match refs.read_ref(&format!("refs/heads/{}", name)) {
+            Some(oid) => oid,
+            None => {
+                eprintln!("error: branch '{}' not found.", name);
+                failed = true;
+                continue;
+            }
+        };
+        if current.as_deref() == Some(name) {
+            eprintln!(
+                "error: Cannot delete the branch '{}' which you are currently on.",
+                name
+            );
+            failed = true;
+            continue;
+        }
+        if !force {
+            // a branch with an upstream need only be merged into that
+            let target = refs
+                .upstream(name)
+                .and_then(|upstream| refs.read_ref(&upstream))
+                .or_else(|| head.clone());
+            let merged = match target {
+                Some(target) => is_ancestor(db, &oid, &target)?,
+                None => false,
+            };
+            if !merged {
+                eprintln!("error: The branch '{}' is not fully merged.", name);
+                eprintln!(
+                    "If you are sure you want to delete it, run 'rit branch -D {}'.",
+                    name
+                );
+                failed = true;
+                continue;
+            }
         }
+        refs.delete_branch(name)?;
+        println!("Deleted branch {} (was {}).", name, db.truncate_oid(&oid));
+    }
+    if failed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn rename(refs: &Refs, names: &[&str], force: bool) -> BoxResult<()> {
+    let (old, new) = match names {
+        [new] => (current_branch(refs, "rename the current branch"), *new),
+        [old, new] => (old.to_string(), *new),
+        _ => fatal(String::from("branch name required")),
+    };
+    if let Err(e) = refs.rename_branch(&old, new, force) {
+        fatal(e.to_string());
+    }
+    Ok(())
+}
+
+fn set_upstream(refs: &Refs, upstream: &str, branch: Option<&str>) -> BoxResult<()> {
+    let branch = match branch {
+        Some(branch) => branch.to_owned(),
+        None => current_branch(refs, "set the upstream of HEAD"),
+    };
+    if refs.read_ref(&format!("refs/heads/{}", branch)).is_none() {
+        fatal(format!("branch '{}' does not exist", branch));
+    }
+    let full_name = [
+        format!("refs/heads/{}", upstream),
+        format!("refs/remotes/{}", upstream),
+    ]
+    .iter()
+    .find(|name| refs.read_ref(name).is_some())
+    .cloned()
+    .unwrap_or_else(|| {
+        fatal(format!(
+            "the requested upstream branch '{}' does not exist",
+            upstream
+        ))
+    });
+
+    refs.set_upstream(&branch, &full_name)?;
+    println!(
+        "branch '{}' set up to track '{}'.",
+        branch,
+        refs.short_name(&full_name)
+    );
+    Ok(())
+}
+
+fn unset_upstream(refs: &Refs, branch: Option<&str>) -> BoxResult<()> {
+    let branch = match branch {
+        Some(branch) => branch.to_owned(),
+        None => current_branch(refs, "unset the upstream of HEAD"),
+    };
+    if !refs.unset_upstream(&branch)? {
+        fatal(format!("branch '{}' has no upstream information", branch));
     }
-    repository.commit_changes()?;
     Ok(())
 }
@@ -1,40 +1,308 @@
+use crate::commands::restore::{resolve_tree, restore_paths, RestoreOptions};
+use crate::commit::Commit;
 use crate::database::ObjectKind;
+use crate::repository::pending_commit::PendingCommit;
 use crate::repository::Repository;
-use crate::revision::RevisionResolver;
+use crate::revision::walk::ahead_behind;
+use crate::revision::{Revision, RevisionResolver};
 use crate::BoxResult;
 use clap::{App, Arg, ArgMatches, SubCommand};
+use std::convert::TryFrom;
+
+const DETACHED_HEAD_MESSAGE: &str =
+    "You are in 'detached HEAD' state. You can look around, make experimental
+changes and commit them, and you can discard any commits you make in this
+state without impacting any branches by performing another checkout.
+
+If you want to create a new branch to retain commits you create, you may
+do so (now or later) by using the branch command. Example:
+
+  rit branch <new-branch-name>
+";
+
+/// How HEAD came to be on a branch, which decides how that is reported.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum BranchChange {
+    /// Moved to an existing branch, which may be the one already checked
+    /// out.
+    Switched,
+    /// Moved to a branch created for the purpose.
+    Created,
+    /// Moved to a branch that was reset to a new start point.
+    Reset,
+}
+
+impl BranchChange {
+    fn describe(self) -> &'static str {
+        match self {
+            BranchChange::Switched => "Switched to branch",
+            BranchChange::Created => "Switched to a new branch",
+            BranchChange::Reset => "Reset branch",
+        }
+    }
+}
 
 pub fn cli() -> App<'static, 'static> {
-    SubCommand::with_name("checkout").arg(Arg::with_name("BRANCH").required(true).index(1))
+    SubCommand::with_name("checkout")
+        .arg(
+            Arg::with_name("BRANCH")
+                .index(1)
+                .multiple(true)
+                .required_unless("PATH")
+                .help("The branch or commit to switch to, or the commit and paths to check out"),
+        )
+        .arg(
+            Arg::with_name("PATH")
+                .multiple(true)
+                .last(true)
+                .help("Files or directories to check out from the index or the commit"),
+        )
 }
 
 pub fn exec(matches: &ArgMatches) -> BoxResult<()> {
     let root = std::path::Path::new(".");
     let mut repository = Repository::new(root)?;
-    let branch = matches
-        .value_of("BRANCH")
-        .expect("failed to specify branch name");
+    let args: Vec<&str> = matches.values_of("BRANCH").into_iter().flatten().collect();
+
+    // without `--`, anything after a revision, or anything at all when the
+    // first argument names no revision but a tracked path, is a path
+    let (revision, paths) = match matches.values_of("PATH") {
+        Some(paths) if args.len() <= 1 => (args.first().copied(), paths.collect()),
+        Some(_) => {
+            eprintln!("fatal: only one reference expected, {} given.", args.len());
+            std::process::exit(128);
+        }
+        None if is_revision(&repository, args[0]) => (Some(args[0]), args[1..].to_vec()),
+        None if is_tracked(&repository, args[0]) => (None, args),
+        None => (Some(args[0]), vec![]),
+    };
+    if !paths.is_empty() {
+        return checkout_paths(repository, revision, &paths);
+    }
+    let branch = revision.unwrap();
+    // `@{-n}` names a branch, so check that branch out rather than its commit
+    let previous = match Revision::from(branch) {
+        Ok(Revision::PreviousCheckout { n }) => repository.refs.previous_checkout(n),
+        _ => None,
+    };
+    let branch = previous.as_deref().unwrap_or(branch);
 
     let mut rr = RevisionResolver::new(&repository.database, &repository.refs, branch);
-    let res = rr.resolver(ObjectKind::Commit);
-    let branch_oid = if let Err(e) = res {
-        for error in rr.errors {
-            eprintln!("{}", error);
+    let branch_oid = match rr.resolver(ObjectKind::Commit) {
+        Ok(oid) => oid,
+        Err(_) => {
+            for error in rr.errors {
+                eprintln!("{}", error);
+            }
+            eprintln!(
+                "error: pathspec '{}' did not match any file(s) known to rit",
+                branch
+            );
+            repository.commit_changes()?;
+            std::process::exit(1);
         }
-        eprintln!("fatal: {}", e);
-        None
-    } else {
-        res.ok()
     };
+    let current_ref = repository.refs.current_ref();
     let head = repository.refs.get_head();
 
-    let tree_diff = repository.database.tree_diff(head, branch_oid);
-    let migration = repository.migration(tree_diff).plan_changes();
+    repository = migrate(repository, Some(&branch_oid))?;
+
+    if branch != "HEAD" && branch != "@" {
+        let message = moving_message(&repository, branch);
+        repository.refs.set_head(branch, &branch_oid, &message)?;
+    }
+    print_head_change(
+        &repository,
+        branch,
+        current_ref,
+        head,
+        &branch_oid,
+        BranchChange::Switched,
+    )?;
+
+    repository.commit_changes()?;
+    Ok(())
+}
+
+/// Moves the workspace and index from HEAD's tree to that of `oid`, or
+/// clears them when there is none, exiting if that would lose local changes.
+/// Any merge in progress is abandoned, as HEAD is about to move away from
+/// it.
+pub(crate) fn migrate(mut repository: Repository, oid: Option<&str>) -> BoxResult<Repository> {
+    let head = repository.refs.get_head();
+    let tree_diff = repository.database.tree_diff(head, oid.map(String::from));
+    let migration = match repository.migration(tree_diff).plan_changes(&repository) {
+        Ok(migration) => migration,
+        Err(e) => {
+            eprintln!("{}", e);
+            repository.commit_changes()?;
+            std::process::exit(1);
+        }
+    };
 
     if let Err(e) = repository.apply_migration(migration) {
-        eprintln!("{}", e)
+        eprintln!("{}", e);
+        repository.commit_changes()?;
+        std::process::exit(1);
+    };
+    PendingCommit::new(repository.refs.git_dir()).clear()?;
+    Ok(repository)
+}
+
+/// The reflog message for moving HEAD to `target`, which is what
+/// `@{-n}` reads back.
+pub(crate) fn moving_message(repository: &Repository, target: &str) -> String {
+    let from = match repository.refs.current_ref() {
+        Some(ref name) => repository.refs.short_name(name),
+        None => repository.refs.get_head().unwrap_or_default(),
     };
+    format!("checkout: moving from {} to {}", from, target)
+}
 
+pub(crate) fn is_revision(repository: &Repository, name: &str) -> bool {
+    RevisionResolver::new(&repository.database, &repository.refs, name)
+        .resolver(ObjectKind::Commit)
+        .is_ok()
+}
+
+fn is_tracked(repository: &Repository, path: &str) -> bool {
+    let path = path.trim_end_matches('/');
+    path == "." || repository.index.has_entry(path)
+}
+
+/// Overwrites `paths` with their contents in `revision`, which also updates
+/// the index, or otherwise with their contents in the index.
+fn checkout_paths(
+    mut repository: Repository,
+    revision: Option<&str>,
+    paths: &[&str],
+) -> BoxResult<()> {
+    let source = revision.map(|rev| resolve_tree(&repository, rev));
+    let options = RestoreOptions {
+        source: source.clone(),
+        staged: source.is_some(),
+        worktree: true,
+        overlay: true,
+    };
+    match restore_paths(&mut repository, paths, &options) {
+        Ok(count) => {
+            let from = match source {
+                Some(ref tree) => repository.database.truncate_oid(tree),
+                None => String::from("the index"),
+            };
+            let plural = if count == 1 { "" } else { "s" };
+            eprintln!("Updated {} path{} from {}", count, plural, from);
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            repository.commit_changes()?;
+            std::process::exit(1);
+        }
+    }
     repository.commit_changes()?;
     Ok(())
 }
+
+/// Reports where HEAD has moved to, using `change` to describe arriving on
+/// a branch.
+pub(crate) fn print_head_change(
+    repository: &Repository,
+    target: &str,
+    old_ref: Option<String>,
+    old_oid: Option<String>,
+    new_oid: &str,
+    change: BranchChange,
+) -> BoxResult<()> {
+    let refs = &repository.refs;
+    let new_ref = refs.current_ref();
+
+    if old_ref.is_none() && old_oid.as_deref() != Some(new_oid) {
+        if let Some(ref oid) = old_oid {
+            eprintln!("Previous HEAD position was {}", describe(repository, oid));
+        }
+    }
+    match new_ref {
+        None => {
+            if old_ref.is_some() {
+                eprintln!("Note: checking out '{}'.\n", target);
+                eprintln!("{}", DETACHED_HEAD_MESSAGE);
+            }
+            eprintln!("HEAD is now at {}", describe(repository, new_oid));
+        }
+        Some(ref name) => {
+            let short = refs.short_name(name);
+            if Some(name) == old_ref.as_ref() && change == BranchChange::Switched {
+                eprintln!("Already on '{}'", short);
+            } else {
+                eprintln!("{} '{}'", change.describe(), short);
+            }
+            print_tracking(repository, &short, new_oid)?;
+        }
+    }
+    Ok(())
+}
+
+/// Tells how a branch compares with its upstream, if it has one.
+fn print_tracking(repository: &Repository, branch: &str, oid: &str) -> BoxResult<()> {
+    let refs = &repository.refs;
+    let upstream = match refs.upstream(branch) {
+        Some(upstream) => upstream,
+        None => return Ok(()),
+    };
+    let name = refs.short_name(&upstream);
+    let upstream_oid = match refs.read_ref(&upstream) {
+        Some(oid) => oid,
+        None => {
+            println!(
+                "Your branch is based on '{}', but the upstream is gone.",
+                name
+            );
+            println!("  (use \"rit branch --unset-upstream\" to fixup)");
+            return Ok(());
+        }
+    };
+    let plural = |n: usize| if n == 1 { "commit" } else { "commits" };
+    match ahead_behind(&repository.database, oid, &upstream_oid)? {
+        (0, 0) => println!("Your branch is up to date with '{}'.", name),
+        (ahead, 0) => {
+            println!(
+                "Your branch is ahead of '{}' by {} {}.",
+                name,
+                ahead,
+                plural(ahead)
+            );
+            println!("  (use \"rit push\" to publish your local commits)");
+        }
+        (0, behind) => {
+            println!(
+                "Your branch is behind '{}' by {} {}, and can be fast-forwarded.",
+                name,
+                behind,
+                plural(behind)
+            );
+            println!("  (use \"rit pull\" to update your local branch)");
+        }
+        (ahead, behind) => {
+            println!("Your branch and '{}' have diverged,", name);
+            println!(
+                "and have {} and {} different commits each, respectively.",
+                ahead, behind
+            );
+            println!("  (use \"rit pull\" to merge the remote branch into yours)");
+        }
+    }
+    Ok(())
+}
+
+/// An abbreviated oid followed by the commit's title.
+pub(crate) fn describe(repository: &Repository, oid: &str) -> String {
+    let short = repository.database.truncate_oid(oid);
+    let title = repository
+        .database
+        .read_object(oid)
+        .ok()
+        .and_then(|(_, _, data)| Commit::try_from(data).ok())
+        .and_then(|c| c.title_line())
+        .unwrap_or_default();
+    format!("{} {}", short, title)
+}
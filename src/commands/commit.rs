@@ -3,6 +3,7 @@ use crate::commit::Commit;
 use crate::database::{Database, Storable};
 use crate::index::Index;
 use crate::refs::Refs;
+use crate::repository::pending_commit::PendingCommit;
 use crate::tree::Tree;
 use crate::BoxResult;
 use chrono::Local;
@@ -24,17 +25,24 @@ pub fn exec(matches: &ArgMatches) -> BoxResult<()> {
     let db = Database::new(root.join(".git/objects"));
     let refs = Refs::new(root.join(".git"));
     let index = Index::from(root.join(".git/index"))?;
+    let pending = PendingCommit::new(refs.git_dir());
+    let merging = pending.in_progress();
 
-    let root = Tree::build(index.entries());
-    root.traverse(&|x| db.store(x).unwrap());
-
-    let name = std::env::var("GIT_AUTHOR_NAME")?;
-    let email = std::env::var("GIT_AUTHOR_EMAIL")?;
-    let author = Author::new(name, email, Local::now());
+    if index.is_conflicted() {
+        eprintln!("error: Committing is not possible because you have unmerged files.");
+        eprintln!("hint: Fix them up in the work tree, and then use 'rit add <file>'");
+        eprintln!("hint: as appropriate to mark resolution and make a commit.");
+        eprintln!("fatal: Exiting because of an unresolved conflict.");
+        index.release_lock()?;
+        std::process::exit(128);
+    }
 
     let mut msg = String::new();
     let message = if matches.is_present("msg") {
         matches.value_of("msg").unwrap()
+    } else if merging {
+        msg = pending.merge_message()?;
+        msg.as_ref()
     } else {
         let stdin = std::io::stdin();
         let mut handle = stdin.lock();
@@ -42,20 +50,60 @@ pub fn exec(matches: &ArgMatches) -> BoxResult<()> {
         msg.as_ref()
     };
 
-    let parent = refs.get_head();
-    let parented = parent.is_some();
+    let mut parents = refs.get_head().into_iter().collect::<Vec<_>>();
+    let parented = !parents.is_empty();
+    if merging {
+        parents.push(pending.merge_oid()?);
+    }
 
-    let commit = Commit::new(parent, &root.oid(), author, message);
+    let commit = write_commit(&db, &index, parents, message)?;
 
-    if parented {
-        println!("[{}]", &commit.oid());
+    let title = commit.title_line().unwrap_or_default();
+    let reason = if merging {
+        format!("commit (merge): {}", title)
+    } else if parented {
+        format!("commit: {}", title)
     } else {
-        println!("[(root-commit) {}]", &commit.oid());
-    }
-
-    refs.update_head(&commit.oid())?;
+        format!("commit (initial): {}", title)
+    };
+    let branch = match refs.current_ref() {
+        Some(name) => refs.short_name(&name),
+        None => String::from("detached HEAD"),
+    };
+    let root_commit = if parented { "" } else { " (root-commit)" };
+    let short = db.truncate_oid(&commit.oid());
+    refs.update_head(&commit.oid(), &reason)?;
+    pending.clear()?;
 
-    db.store(commit)?;
+    println!("[{}{} {}] {}", branch, root_commit, short, title);
     index.release_lock()?;
     Ok(())
 }
+
+/// Stores the tree of the index and a commit of it with `parents`, by the
+/// author and committer named in the environment.
+pub(crate) fn write_commit(
+    db: &Database,
+    index: &Index,
+    parents: Vec<String>,
+    message: &str,
+) -> BoxResult<Commit> {
+    let root = Tree::build(index.entries());
+    root.traverse(&|x| db.store(x).unwrap());
+
+    let name = std::env::var("GIT_AUTHOR_NAME")?;
+    let email = std::env::var("GIT_AUTHOR_EMAIL")?;
+    let author = Author::new(name, email, Local::now());
+
+    let committer = match (
+        std::env::var("GIT_COMMITTER_NAME"),
+        std::env::var("GIT_COMMITTER_EMAIL"),
+    ) {
+        (Ok(name), Ok(email)) => Author::new(name, email, Local::now()),
+        _ => author.clone(),
+    };
+
+    let commit = Commit::new(parents, &root.oid(), author, committer, message);
+    db.store(commit.clone())?;
+    Ok(commit)
+}
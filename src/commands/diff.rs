@@ -1,21 +1,37 @@
-use crate::database::{Blob, Storable};
+use crate::attributes::Attributes;
+use crate::database::{Blob, Database, ObjectKind, Storable};
+use crate::diff::binary;
+use crate::diff::edit::EditKind;
 use crate::diff::hunk::Hunk;
 use crate::diff::myers::Myers;
 use crate::index::entry::Entry;
 use crate::repository::{Repository, Status};
+use crate::revision::RevisionResolver;
+use crate::tree::TreeEntry;
 use crate::{BoxResult, CliError};
 use clap::{App, Arg, ArgMatches, SubCommand};
 use colored::Colorize;
-use std::convert::TryFrom;
+use std::collections::BTreeSet;
 use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
 
 pub fn cli() -> App<'static, 'static> {
-    SubCommand::with_name("diff").arg(
-        Arg::with_name("cached")
-            .long("--cached")
-            .help("This form is to view the changes you staged for the next commit relative to the named commit."),
-    )
+    SubCommand::with_name("diff")
+        .arg(
+            Arg::with_name("cached")
+                .long("--cached")
+                .help("This form is to view the changes you staged for the next commit relative to the named commit."),
+        )
+        .arg(
+            Arg::with_name("binary")
+                .long("--binary")
+                .help("Output a binary diff that can be applied with git-apply."),
+        )
+        .arg(
+            Arg::with_name("COMMIT")
+                .index(1)
+                .help("Compare against this commit instead of the index or HEAD."),
+        )
 }
 
 pub fn exec(matches: &ArgMatches) -> BoxResult<()> {
@@ -23,87 +39,204 @@ pub fn exec(matches: &ArgMatches) -> BoxResult<()> {
     let mut repository = Repository::new(root)?;
     //    pager();
     let cached = matches.is_present("cached");
-    repository.status()?;
-    if cached {
-        repository.diff_head_index()?
-    } else {
-        repository.diff_index_workspace()?;
+    let options = DiffOptions {
+        binary: matches.is_present("binary"),
+        attributes: Attributes::load(&repository.workspace),
+    };
+    let commit = match matches.value_of("COMMIT") {
+        Some(rev) => {
+            let mut rr = RevisionResolver::new(&repository.database, &repository.refs, rev);
+            match rr.resolver(ObjectKind::Commit) {
+                Ok(oid) => Some(oid),
+                Err(_) => {
+                    for error in rr.errors {
+                        eprintln!("{}", error);
+                    }
+                    eprintln!("fatal: bad revision '{}'", rev);
+                    repository.commit_changes()?;
+                    std::process::exit(128);
+                }
+            }
+        }
+        None => None,
+    };
+
+    match commit {
+        Some(oid) => {
+            repository.status_against(Some(oid))?;
+            if cached {
+                repository.diff_head_index(&options)?
+            } else {
+                repository.diff_tree_workspace(&options)?
+            }
+        }
+        None => {
+            repository.status()?;
+            if cached {
+                repository.diff_head_index(&options)?
+            } else {
+                repository.diff_index_workspace(&options)?;
+            }
+        }
     }
     repository.commit_changes()?;
     Ok(())
 }
 
+pub(crate) struct DiffOptions {
+    pub(crate) binary: bool,
+    pub(crate) attributes: Attributes,
+}
+
+impl DiffOptions {
+    fn is_binary(&self, path: &Path, a: &[u8], b: &[u8]) -> bool {
+        self.attributes
+            .is_binary(path)
+            .unwrap_or_else(|| binary::is_binary(a) || binary::is_binary(b))
+    }
+}
+
 trait Differ {
-    fn diff_head_index(&self) -> BoxResult<()>;
-    fn diff_index_workspace(&self) -> BoxResult<()>;
+    fn diff_head_index(&self, options: &DiffOptions) -> BoxResult<()>;
+    fn diff_index_workspace(&self, options: &DiffOptions) -> BoxResult<()>;
+    fn diff_tree_workspace(&self, options: &DiffOptions) -> BoxResult<()>;
     fn get_index_file(&self, path: &str) -> BoxResult<Target>;
     fn get_head_file(&self, path: &str) -> BoxResult<Target>;
     fn get_workspace_file(&self, path: &str) -> BoxResult<Target>;
     fn get_deleted_file(&self) -> BoxResult<Target>;
-    fn print_diff(&self, a: Target, b: Target);
+    fn print_diff(&self, a: Target, b: Target, options: &DiffOptions);
 }
 
 const NILL_PATH: &str = "/dev/null";
 const NILL_OID: &str = "0000000000000000000000000000000000000000";
 
-struct Target {
+pub(crate) struct Target {
     path: PathBuf,
     oid: String,
     mode: Option<String>,
-    data: String,
+    data: Vec<u8>,
+}
+
+impl Target {
+    /// The side of a diff held by a tree entry, or `/dev/null` when there is
+    /// no entry.
+    pub(crate) fn from_tree_entry(
+        db: &Database,
+        path: &Path,
+        entry: Option<&TreeEntry>,
+    ) -> BoxResult<Self> {
+        match entry {
+            Some(entry) => {
+                let oid = entry.oid();
+                let (_, _, data) = db.read_object(&oid)?;
+                Ok(Target {
+                    path: path.to_path_buf(),
+                    oid,
+                    mode: Some(entry.mode()),
+                    data,
+                })
+            }
+            None => Ok(Target {
+                path: PathBuf::from(NILL_PATH),
+                oid: String::from(NILL_OID),
+                mode: None,
+                data: Vec::new(),
+            }),
+        }
+    }
+
+    pub(crate) fn size(&self) -> usize {
+        self.data.len()
+    }
 }
 
 impl Differ for Repository {
-    fn diff_head_index(&self) -> BoxResult<()> {
+    fn diff_head_index(&self, options: &DiffOptions) -> BoxResult<()> {
         let changes = self.index_changes.clone();
         for (path, change) in changes {
             let path = path.as_str();
             match change {
-                Status::Added => {
-                    self.print_diff(self.get_deleted_file()?, self.get_index_file(path)?)
-                }
+                Status::Added => self.print_diff(
+                    self.get_deleted_file()?,
+                    self.get_index_file(path)?,
+                    options,
+                ),
                 Status::Deleted => {
-                    self.print_diff(self.get_head_file(path)?, self.get_deleted_file()?)
-                }
-                Status::Modified => {
-                    self.print_diff(self.get_head_file(path)?, self.get_index_file(path)?)
+                    self.print_diff(self.get_head_file(path)?, self.get_deleted_file()?, options)
                 }
+                Status::Modified => self.print_diff(
+                    self.get_head_file(path)?,
+                    self.get_index_file(path)?,
+                    options,
+                ),
                 _ => continue,
             };
         }
         Ok(())
     }
 
-    fn diff_index_workspace(&self) -> BoxResult<()> {
+    fn diff_index_workspace(&self, options: &DiffOptions) -> BoxResult<()> {
         let workspace_changes = self.workspace_changes.clone();
         for (path, change) in workspace_changes {
             let path = path.as_str();
             match change {
-                Status::Modified => {
-                    self.print_diff(self.get_index_file(path)?, self.get_workspace_file(path)?)
-                }
-                Status::Deleted => {
-                    self.print_diff(self.get_index_file(path)?, self.get_deleted_file()?)
-                }
+                Status::Modified => self.print_diff(
+                    self.get_index_file(path)?,
+                    self.get_workspace_file(path)?,
+                    options,
+                ),
+                Status::Deleted => self.print_diff(
+                    self.get_index_file(path)?,
+                    self.get_deleted_file()?,
+                    options,
+                ),
                 _ => continue,
             };
         }
         Ok(())
     }
 
+    fn diff_tree_workspace(&self, options: &DiffOptions) -> BoxResult<()> {
+        let mut paths = self
+            .tree
+            .keys()
+            .map(|p| p.to_str().unwrap().to_owned())
+            .collect::<BTreeSet<_>>();
+        for entry in self.index.entries() {
+            paths.insert(entry.path.to_str().unwrap().to_owned());
+        }
+
+        for path in paths {
+            let path = path.as_str();
+            let a = match self.tree.get(Path::new(path)) {
+                Some(_) => self.get_head_file(path)?,
+                None => self.get_deleted_file()?,
+            };
+            // a tracked file the workspace has not changed matches the index
+            let b = match self.workspace_changes.get(path) {
+                _ if !self.index.has_entry(path) => self.get_deleted_file()?,
+                Some(Status::Deleted) => self.get_deleted_file()?,
+                Some(_) => self.get_workspace_file(path)?,
+                None => self.get_index_file(path)?,
+            };
+            if a.oid != b.oid || a.mode != b.mode {
+                self.print_diff(a, b, options);
+            }
+        }
+        Ok(())
+    }
+
     fn get_index_file(&self, path: &str) -> BoxResult<Target> {
         if let Some(entry) = self.index.get_entry(path) {
             let mode = String::from(&entry.mode());
             let oid = String::from(&entry.oid);
-            let oid = self.database.truncate_oid(oid.as_ref());
             let path = Path::new(path).to_path_buf();
             let (_, _, data) = self.database.read_object(&entry.oid)?;
-            let blob = Blob::try_from(data)?;
             Ok(Target {
                 path,
                 oid,
                 mode: Some(mode),
-                data: blob.data,
+                data,
             })
         } else {
             Err(CliError::new("Failed to get file from workspace").into())
@@ -114,15 +247,13 @@ impl Differ for Repository {
         if let Some(entry) = self.tree.get(Path::new(path)) {
             let mode = String::from(&entry.mode);
             let oid = String::from(&entry.oid);
-            let oid = self.database.truncate_oid(oid.as_ref());
             let path = Path::new(path).to_path_buf();
             let (_, _, data) = self.database.read_object(&entry.oid)?;
-            let blob = Blob::try_from(data)?;
             Ok(Target {
                 path,
                 oid,
                 mode: Some(mode),
-                data: blob.data,
+                data,
             })
         } else {
             Err(CliError::new("Failed to get file from tree").into())
@@ -133,7 +264,6 @@ impl Differ for Repository {
         if let Ok(file) = self.workspace.read_file(path) {
             let blob = Blob::new(file);
             let oid = blob.oid();
-            let oid = self.database.truncate_oid(oid.as_ref());
             let stats = self
                 .stats
                 .get(Path::new(path))
@@ -154,60 +284,108 @@ impl Differ for Repository {
 
     fn get_deleted_file(&self) -> BoxResult<Target> {
         let path = Path::new(NILL_PATH).to_path_buf();
-        let oid = self.database.truncate_oid(NILL_OID);
+        let oid = String::from(NILL_OID);
         Ok(Target {
             path,
             oid,
             mode: None,
-            data: String::new(),
+            data: Vec::new(),
         })
     }
 
-    fn print_diff(&self, a: Target, b: Target) {
-        let a_pth_str = Path::new("a").join(a.path);
-        let a_pth_str = a_pth_str.to_str().expect("couldn't extract path for diff");
-        let b_pth_str = Path::new("b").join(b.path);
-        let b_pth_str = b_pth_str.to_str().expect("couldn't extract path for diff");
+    fn print_diff(&self, a: Target, b: Target, options: &DiffOptions) {
+        print_diff(&self.database, a, b, options)
+    }
+}
+
+/// The path a diff is about: the new path, unless the file was deleted.
+pub(crate) fn diff_path<'t>(a: &'t Target, b: &'t Target) -> &'t Path {
+    if a.mode.is_none() {
+        &b.path
+    } else {
+        &a.path
+    }
+}
+
+/// Lines added and removed between two sides, or `None` for binary files.
+pub(crate) fn line_counts(a: &Target, b: &Target, options: &DiffOptions) -> Option<(usize, usize)> {
+    if options.is_binary(diff_path(a, b), &a.data, &b.data) {
+        return None;
+    }
+    let edits = Myers::from(&a.data, &b.data).diff();
+    let added = edits.iter().filter(|e| e.kind == EditKind::Insert).count();
+    let deleted = edits.iter().filter(|e| e.kind == EditKind::Delete).count();
+    Some((added, deleted))
+}
+
+pub(crate) fn print_diff(db: &Database, a: Target, b: Target, options: &DiffOptions) {
+    let path = diff_path(&a, &b).to_path_buf();
+    let a_pth_str = Path::new("a").join(&a.path);
+    let a_pth_str = a_pth_str.to_str().expect("couldn't extract path for diff");
+    let b_pth_str = Path::new("b").join(&b.path);
+    let b_pth_str = b_pth_str.to_str().expect("couldn't extract path for diff");
 
+    println!(
+        "{}",
+        format!(
+            "diff --git {} {}",
+            Path::new("a").join(&path).display(),
+            Path::new("b").join(&path).display()
+        )
+        .bold()
+    );
+
+    let mode_str = if a.mode.is_none() {
+        println!("{}", format!("new file mode {}", b.mode.unwrap()).bold());
+        String::new()
+    } else if b.mode.is_none() {
         println!(
             "{}",
-            format!("diff --git {} {}", a_pth_str, b_pth_str).bold()
+            format!("deleted file mode {}", a.mode.unwrap()).bold()
         );
+        String::new()
+    } else if a.mode != b.mode {
+        println!("{}", format!("old mode {}", a.mode.unwrap()).bold());
+        println!("{}", format!("new mode {}", b.mode.unwrap()).bold());
+        String::new()
+    } else {
+        format!(" {}", &a.mode.unwrap().bold())
+    };
 
-        let mode_str = if a.mode.is_none() {
-            println!("{}", format!("new file mode {}", b.mode.unwrap()).bold());
-            String::new()
-        } else if b.mode.is_none() {
-            println!(
-                "{}",
-                format!("deleted file mode {}", a.mode.unwrap()).bold()
-            );
-            String::new()
-        } else if a.mode != b.mode {
-            println!("{}", format!("old mode {}", a.mode.unwrap()).bold());
-            println!("{}", format!("new mode {}", b.mode.unwrap()).bold());
-            String::new()
-        } else {
-            format!(" {}", &a.mode.unwrap().bold())
-        };
+    if a.oid == b.oid {
+        return;
+    }
+
+    let is_binary = options.is_binary(&path, &a.data, &b.data);
+    let (a_oid, b_oid) = if is_binary && options.binary {
+        (a.oid.clone(), b.oid.clone())
+    } else {
+        (db.truncate_oid(&a.oid), db.truncate_oid(&b.oid))
+    };
+    println!(
+        "{}",
+        format!("index {}..{}{}", a_oid, b_oid, mode_str).bold()
+    );
 
-        if a.oid == b.oid {
-            return;
+    if is_binary {
+        if options.binary {
+            print!("{}", binary::patch(&a.data, &b.data));
+        } else {
+            println!("Binary files {} and {} differ", a_pth_str, b_pth_str);
         }
+        return;
+    }
 
-        println!(
-            "{}",
-            format!("index {}..{}{}", a.oid, b.oid, mode_str).bold()
-        );
-        println!("{}", format!("--- {}", a_pth_str).bold());
-        println!("{}", format!("+++ {}", b_pth_str).bold());
-
-        let edits = Myers::from(a.data.as_ref(), b.data.as_ref()).diff();
-        for hunk in Hunk::filter(edits) {
-            println!("{}", hunk.header().cyan());
-            for edit in hunk.edits {
-                println!("{}", edit);
-            }
+    println!("{}", format!("--- {}", a_pth_str).bold());
+    println!("{}", format!("+++ {}", b_pth_str).bold());
+
+    let edits = Myers::from(&a.data, &b.data).diff();
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    for hunk in Hunk::filter(edits) {
+        println!("{}", hunk.header().cyan());
+        for edit in hunk.edits {
+            edit.write_to(&mut out).expect("couldn't write diff");
         }
     }
 }
@@ -0,0 +1,14 @@
+use crate::commands::repack;
+use crate::refs::Refs;
+use crate::BoxResult;
+use clap::{App, ArgMatches, SubCommand};
+
+pub fn cli() -> App<'static, 'static> {
+    SubCommand::with_name("gc")
+}
+
+pub fn exec(_matches: &ArgMatches) -> BoxResult<()> {
+    let root = std::path::Path::new(".");
+    Refs::new(root.join(".git")).pack_refs(true, true)?;
+    repack::repack(true, true, repack::DEFAULT_WINDOW, repack::DEFAULT_DEPTH)
+}
@@ -0,0 +1,484 @@
+use crate::attributes::Attributes;
+use crate::commands::diff::{diff_path, line_counts, print_diff, DiffOptions, Target};
+use crate::commit::Commit;
+use crate::database::Database;
+use crate::refs::Refs;
+use crate::revision::walk::{matches_paths, Order, RevWalk};
+use crate::revision::{RevisionResolver, Tip};
+use crate::utilities::parse_date;
+use crate::workspace::Workspace;
+use crate::BoxResult;
+use chrono::{DateTime, Local};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use colored::Colorize;
+use regex::Regex;
+use std::path::PathBuf;
+
+pub(crate) const DATE_FORMAT: &str = "%a %b %-d %H:%M:%S %Y %z";
+const STAT_WIDTH: usize = 80;
+
+pub fn cli() -> App<'static, 'static> {
+    SubCommand::with_name("log")
+        .arg(
+            Arg::with_name("oneline")
+                .long("oneline")
+                .help("Show each commit on a single line."),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .alias("pretty")
+                .takes_value(true)
+                .help("Pretty-print commits with a format string such as '%h %s'."),
+        )
+        .arg(
+            Arg::with_name("max-count")
+                .short("n")
+                .long("max-count")
+                .takes_value(true)
+                .help("Limit the number of commits shown."),
+        )
+        .arg(
+            Arg::with_name("patch")
+                .short("p")
+                .long("patch")
+                .help("Show the changes each commit introduces."),
+        )
+        .arg(
+            Arg::with_name("stat")
+                .long("stat")
+                .help("Show a summary of the files each commit changes."),
+        )
+        .arg(
+            Arg::with_name("author")
+                .long("author")
+                .takes_value(true)
+                .help("Only show commits whose author matches the pattern."),
+        )
+        .arg(
+            Arg::with_name("since")
+                .long("since")
+                .alias("after")
+                .takes_value(true)
+                .help("Only show commits more recent than a date."),
+        )
+        .arg(
+            Arg::with_name("until")
+                .long("until")
+                .alias("before")
+                .takes_value(true)
+                .help("Only show commits older than a date."),
+        )
+        .arg(
+            Arg::with_name("topo-order")
+                .long("topo-order")
+                .help("Show no parents before all of their children."),
+        )
+        .arg(
+            Arg::with_name("date-order")
+                .long("date-order")
+                .overrides_with("topo-order")
+                .help("Show commits in commit timestamp order."),
+        )
+        .arg(
+            Arg::with_name("reverse")
+                .long("reverse")
+                .help("Show the selected commits oldest first."),
+        )
+        .arg(
+            Arg::with_name("first-parent")
+                .long("first-parent")
+                .help("Follow only the first parent of merge commits."),
+        )
+        .arg(
+            Arg::with_name("ancestry-path")
+                .long("ancestry-path")
+                .help("Show only commits that descend from an excluded commit."),
+        )
+        .arg(Arg::with_name("REVISION").index(1).multiple(true))
+        .arg(Arg::with_name("PATH").multiple(true).last(true))
+}
+
+pub(crate) enum Format {
+    Medium,
+    Oneline,
+    Custom(String),
+}
+
+impl Format {
+    /// The format selected by `--oneline` or `--format`/`--pretty`.
+    pub(crate) fn from_matches(matches: &ArgMatches) -> Self {
+        if matches.is_present("oneline") {
+            return Format::Oneline;
+        }
+        match matches.value_of("format") {
+            None | Some("medium") => Format::Medium,
+            Some("oneline") => Format::Oneline,
+            Some(format) => {
+                let format = format
+                    .strip_prefix("format:")
+                    .or_else(|| format.strip_prefix("tformat:"))
+                    .unwrap_or(format);
+                Format::Custom(format.to_owned())
+            }
+        }
+    }
+}
+
+pub(crate) struct LogOptions {
+    pub(crate) format: Format,
+    pub(crate) patch: bool,
+    pub(crate) stat: bool,
+    pub(crate) paths: Vec<PathBuf>,
+    pub(crate) diff: DiffOptions,
+}
+
+pub fn exec(matches: &ArgMatches) -> BoxResult<()> {
+    let root = std::path::Path::new(".");
+    let workspace = Workspace::new(root);
+    let db = Database::new(root.join(".git/objects"));
+    let refs = Refs::new(root.join(".git"));
+
+    let (mut tips, paths) = resolve_revisions(&workspace, &db, &refs, matches);
+    if tips.is_empty() {
+        match refs.get_head() {
+            Some(head) => tips.push(Tip::include(head)),
+            None => {
+                let branch = refs.current_ref().unwrap_or_else(|| String::from("HEAD"));
+                eprintln!(
+                    "fatal: your current branch '{}' does not have any commits yet",
+                    refs.short_name(&branch)
+                );
+                std::process::exit(128);
+            }
+        }
+    }
+
+    let format = Format::from_matches(matches);
+    let max_count = match matches.value_of("max-count") {
+        Some(n) => Some(n.parse::<usize>()?),
+        None => None,
+    };
+    let author = match matches.value_of("author") {
+        Some(pattern) => Some(Regex::new(pattern)?),
+        None => None,
+    };
+    let since = date_option(matches, "since")?;
+    let until = date_option(matches, "until")?;
+
+    let options = LogOptions {
+        format,
+        patch: matches.is_present("patch"),
+        stat: matches.is_present("stat"),
+        paths: paths.clone(),
+        diff: DiffOptions {
+            binary: false,
+            attributes: Attributes::load(&workspace),
+        },
+    };
+
+    let mut walk = RevWalk::new(&db);
+    walk.set_paths(paths);
+    walk.set_ancestry_path(matches.is_present("ancestry-path"));
+    walk.set_first_parent(matches.is_present("first-parent"));
+    if matches.is_present("topo-order") {
+        walk.set_order(Order::Topo);
+    }
+    for tip in &tips {
+        walk.push_tip(tip)?;
+    }
+
+    let reverse = matches.is_present("reverse");
+    let mut selected = vec![];
+    let mut shown = 0;
+    while let Some((oid, commit)) = walk.next_commit()? {
+        if max_count.map(|n| shown >= n).unwrap_or(false) {
+            break;
+        }
+        let time = commit.committer.time();
+        if since.map(|t| time < t).unwrap_or(false) || until.map(|t| time > t).unwrap_or(false) {
+            continue;
+        }
+        if let Some(ref author) = author {
+            let ident = format!("{} <{}>", commit.author.name(), commit.author.email());
+            if !author.is_match(&ident) {
+                continue;
+            }
+        }
+        if reverse {
+            selected.push((oid, commit));
+        } else {
+            show_commit(&db, &oid, &commit, &options, shown == 0)?;
+        }
+        shown += 1;
+    }
+    // -n picks the newest commits before they are reversed
+    for (i, (oid, commit)) in selected.iter().rev().enumerate() {
+        show_commit(&db, oid, commit, &options, i == 0)?;
+    }
+    Ok(())
+}
+
+/// Splits the positional arguments into the commits to walk and the paths
+/// to limit the walk to. Arguments that are not revisions but exist in the
+/// workspace are taken as paths, as they are after `--`.
+pub(crate) fn resolve_revisions(
+    workspace: &Workspace,
+    db: &Database,
+    refs: &Refs,
+    matches: &ArgMatches,
+) -> (Vec<Tip>, Vec<PathBuf>) {
+    let mut paths = matches
+        .values_of("PATH")
+        .map(|v| v.map(PathBuf::from).collect::<Vec<_>>())
+        .unwrap_or_default();
+    let mut tips = vec![];
+    for rev in matches.values_of("REVISION").into_iter().flatten() {
+        let mut rr = RevisionResolver::new(db, refs, rev);
+        match rr.resolve_tips() {
+            Ok(mut resolved) => tips.append(&mut resolved),
+            Err(_) if workspace.workspace_path(rev).exists() => paths.push(PathBuf::from(rev)),
+            Err(_) => {
+                for error in rr.errors {
+                    eprintln!("{}", error);
+                }
+                eprintln!(
+                    "fatal: ambiguous argument '{}': unknown revision or path not in the working tree.",
+                    rev
+                );
+                std::process::exit(128);
+            }
+        }
+    }
+    (tips, paths)
+}
+
+fn date_option(matches: &ArgMatches, name: &str) -> BoxResult<Option<DateTime<Local>>> {
+    match matches.value_of(name) {
+        Some(date) => match parse_date(date, Local::now()) {
+            Some(time) => Ok(Some(time)),
+            None => Err(format!("fatal: invalid date '{}'", date).into()),
+        },
+        None => Ok(None),
+    }
+}
+
+pub(crate) fn show_commit(
+    db: &Database,
+    oid: &str,
+    commit: &Commit,
+    options: &LogOptions,
+    first: bool,
+) -> BoxResult<()> {
+    match options.format {
+        Format::Medium => {
+            if !first {
+                println!();
+            }
+            println!("{}", format!("commit {}", oid).yellow());
+            if commit.is_merge() {
+                let parents = commit
+                    .parents
+                    .iter()
+                    .map(|p| db.truncate_oid(p))
+                    .collect::<Vec<_>>();
+                println!("Merge: {}", parents.join(" "));
+            }
+            println!(
+                "Author: {} <{}>",
+                commit.author.name(),
+                commit.author.email()
+            );
+            println!("Date:   {}", commit.author.local_time().format(DATE_FORMAT));
+            println!();
+            for line in commit.message().trim_end().lines() {
+                println!("    {}", line);
+            }
+            if (options.patch || options.stat) && !commit.is_merge() {
+                println!();
+            }
+        }
+        Format::Oneline => {
+            println!(
+                "{} {}",
+                db.truncate_oid(oid).yellow(),
+                commit.title_line().unwrap_or_default()
+            );
+        }
+        Format::Custom(ref format) => println!("{}", expand_format(format, db, oid, commit)),
+    }
+
+    if commit.is_merge() || !(options.patch || options.stat) {
+        return Ok(());
+    }
+    let mut changes = db
+        .tree_diff(commit.parent().cloned(), Some(oid.to_owned()))
+        .into_iter()
+        .filter(|(path, _)| matches_paths(&options.paths, path))
+        .collect::<Vec<_>>();
+    changes.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut targets = vec![];
+    for (path, (a, b)) in changes {
+        targets.push((
+            Target::from_tree_entry(db, &path, a.as_ref())?,
+            Target::from_tree_entry(db, &path, b.as_ref())?,
+        ));
+    }
+    if options.stat {
+        print_stat(&targets, &options.diff);
+        if options.patch {
+            println!();
+        }
+    }
+    if options.patch {
+        for (a, b) in targets {
+            print_diff(db, a, b, &options.diff);
+        }
+    }
+    Ok(())
+}
+
+fn print_stat(targets: &[(Target, Target)], options: &DiffOptions) {
+    let stats = targets
+        .iter()
+        .map(|(a, b)| {
+            let name = diff_path(a, b).display().to_string();
+            (name, line_counts(a, b, options), a.size(), b.size())
+        })
+        .collect::<Vec<_>>();
+
+    let name_width = stats.iter().map(|s| s.0.len()).max().unwrap_or(0);
+    let max_change = stats
+        .iter()
+        .filter_map(|s| s.1.map(|(added, deleted)| added + deleted))
+        .max()
+        .unwrap_or(0);
+    let count_width = max_change.to_string().len();
+    let graph_width = STAT_WIDTH.saturating_sub(name_width + count_width + 4);
+
+    let (mut insertions, mut deletions) = (0, 0);
+    for (name, counts, a_size, b_size) in &stats {
+        match counts {
+            Some((added, deleted)) => {
+                insertions += added;
+                deletions += deleted;
+                let (plus, minus) = scale(*added, *deleted, max_change, graph_width);
+                println!(
+                    " {:<nw$} | {:>cw$} {}{}",
+                    name,
+                    added + deleted,
+                    "+".repeat(plus).green(),
+                    "-".repeat(minus).red(),
+                    nw = name_width,
+                    cw = count_width
+                );
+            }
+            None => println!(
+                " {:<nw$} | Bin {} -> {} bytes",
+                name,
+                a_size,
+                b_size,
+                nw = name_width
+            ),
+        }
+    }
+
+    let plural = |n: usize, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+    let mut summary = format!(" {} changed", plural(stats.len(), "file"));
+    if insertions > 0 || deletions == 0 {
+        summary.push_str(&format!(", {}(+)", plural(insertions, "insertion")));
+    }
+    if deletions > 0 || insertions == 0 {
+        summary.push_str(&format!(", {}(-)", plural(deletions, "deletion")));
+    }
+    println!("{}", summary);
+}
+
+/// Shrinks a file's share of the graph when the largest change would not
+/// fit, keeping at least one mark for any non-zero count.
+fn scale(added: usize, deleted: usize, max_change: usize, width: usize) -> (usize, usize) {
+    if max_change <= width || max_change == 0 {
+        return (added, deleted);
+    }
+    let shrink = |n: usize| {
+        if n == 0 {
+            0
+        } else {
+            std::cmp::max(1, n * width / max_change)
+        }
+    };
+    (shrink(added), shrink(deleted))
+}
+
+fn expand_format(format: &str, db: &Database, oid: &str, commit: &Commit) -> String {
+    let mut out = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        let mut code = String::new();
+        if let Some(&next) = chars.peek() {
+            chars.next();
+            code.push(next);
+            if (next == 'a' || next == 'c') && chars.peek().is_some() {
+                code.push(chars.next().unwrap());
+            }
+        }
+        let message = commit.message();
+        let expansion = match code.as_str() {
+            "H" => oid.to_owned(),
+            "h" => db.truncate_oid(oid),
+            "T" => commit.tree.clone(),
+            "t" => db.truncate_oid(&commit.tree),
+            "P" => commit.parents.join(" "),
+            "p" => commit
+                .parents
+                .iter()
+                .map(|p| db.truncate_oid(p))
+                .collect::<Vec<_>>()
+                .join(" "),
+            "an" => commit.author.name().into_owned(),
+            "ae" => commit.author.email().into_owned(),
+            "ad" => commit.author.local_time().format(DATE_FORMAT).to_string(),
+            "ar" => relative_date(commit.author.time()),
+            "at" => commit.author.time().timestamp().to_string(),
+            "cn" => commit.committer.name().into_owned(),
+            "ce" => commit.committer.email().into_owned(),
+            "cd" => commit
+                .committer
+                .local_time()
+                .format(DATE_FORMAT)
+                .to_string(),
+            "cr" => relative_date(commit.committer.time()),
+            "ct" => commit.committer.time().timestamp().to_string(),
+            "s" => commit.title_line().unwrap_or_default(),
+            "b" => message
+                .split_once("\n\n")
+                .map(|(_, body)| body.trim_end().to_owned())
+                .unwrap_or_default(),
+            "B" => message.trim_end().to_owned(),
+            "n" => String::from("\n"),
+            "%" => String::from("%"),
+            _ => format!("%{}", code),
+        };
+        out.push_str(&expansion);
+    }
+    out
+}
+
+fn relative_date(time: DateTime<Local>) -> String {
+    let seconds = (Local::now() - time).num_seconds().max(0);
+    let (n, unit) = match seconds {
+        s if s < 90 => (s, "second"),
+        s if s < 90 * 60 => (s / 60, "minute"),
+        s if s < 36 * 3600 => (s / 3600, "hour"),
+        s if s < 14 * 86400 => (s / 86400, "day"),
+        s if s < 70 * 86400 => (s / (7 * 86400), "week"),
+        s if s < 365 * 86400 => (s / (30 * 86400), "month"),
+        s => (s / (365 * 86400), "year"),
+    };
+    format!("{} {}{} ago", n, unit, if n == 1 { "" } else { "s" })
+}
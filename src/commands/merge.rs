@@ -0,0 +1,264 @@
+use crate::commands::commit::write_commit;
+use crate::database::tree_diff::TreeDifference;
+use crate::database::{Blob, ObjectKind, Storable};
+use crate::merge::{merge_blobs, resolve, Conflict, ConflictKind};
+use crate::repository::pending_commit::PendingCommit;
+use crate::repository::Repository;
+use crate::revision::walk::merge_bases;
+use crate::revision::RevisionResolver;
+use crate::BoxResult;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub fn cli() -> App<'static, 'static> {
+    SubCommand::with_name("merge")
+        .arg(
+            Arg::with_name("message")
+                .short("m")
+                .long("message")
+                .takes_value(true)
+                .help("The message for the merge commit."),
+        )
+        .arg(
+            Arg::with_name("REVISION")
+                .required(true)
+                .index(1)
+                .help("The branch or commit to merge into the current one"),
+        )
+}
+
+pub fn exec(matches: &ArgMatches) -> BoxResult<()> {
+    let root = std::path::Path::new(".");
+    let mut repository = Repository::new(root)?;
+    let pending = PendingCommit::new(repository.refs.git_dir());
+    let revision = matches.value_of("REVISION").unwrap();
+
+    if pending.in_progress() {
+        eprintln!("error: You have not concluded your merge (MERGE_HEAD exists).");
+        eprintln!("hint: Please, commit your changes before merging.");
+        fatal(repository, "Exiting because of unfinished merge.")
+    }
+    let head = match repository.refs.get_head() {
+        Some(head) => head,
+        None => fatal(repository, "no commit to merge into"),
+    };
+    let theirs = match RevisionResolver::new(&repository.database, &repository.refs, revision)
+        .resolver(ObjectKind::Commit)
+    {
+        Ok(oid) => oid,
+        Err(_) => {
+            eprintln!("merge: {} - not something we can merge", revision);
+            repository.commit_changes()?;
+            std::process::exit(1);
+        }
+    };
+
+    let base = merge_bases(&repository.database, &head, &theirs)?
+        .into_iter()
+        .next();
+    let base = match base {
+        Some(base) => base,
+        None => fatal(repository, "refusing to merge unrelated histories"),
+    };
+    if base == theirs {
+        println!("Already up to date.");
+        repository.commit_changes()?;
+        return Ok(());
+    }
+
+    if base != head {
+        repository = check_index_is_clean(repository)?;
+    }
+
+    repository.refs.set_orig_head(&head)?;
+    if base == head {
+        return fast_forward(repository, revision, &head, &theirs);
+    }
+
+    let message = match matches.value_of("message") {
+        Some(message) => format!("{}\n", message),
+        None => default_message(&repository, revision),
+    };
+    three_way(repository, revision, &base, &head, &theirs, &message)
+}
+
+fn fatal(repository: Repository, message: &str) -> ! {
+    eprintln!("fatal: {}", message);
+    let _ = repository.commit_changes();
+    std::process::exit(128);
+}
+
+/// Exits unless the index matches HEAD, as the merge commit is written
+/// from the index and would take any staged changes with it.
+fn check_index_is_clean(mut repository: Repository) -> BoxResult<Repository> {
+    repository.status()?;
+    let mut staged: Vec<&String> = repository.index_changes.keys().collect();
+    staged.extend(repository.conflicts.keys());
+    if staged.is_empty() {
+        return Ok(repository);
+    }
+    staged.sort();
+    eprintln!("error: Your local changes to the following files would be overwritten by merge:");
+    for path in staged {
+        eprintln!("\t{}", path);
+    }
+    eprintln!("Please commit your changes or stash them before you merge.");
+    eprintln!("Aborting");
+    repository.commit_changes()?;
+    std::process::exit(1);
+}
+
+fn default_message(repository: &Repository, revision: &str) -> String {
+    let kind = if repository
+        .refs
+        .read_ref(&format!("refs/heads/{}", revision))
+        .is_some()
+    {
+        "branch"
+    } else {
+        "commit"
+    };
+    format!("Merge {} '{}'\n", kind, revision)
+}
+
+/// Applies `diff` to the index and workspace, exiting if that, or writing
+/// the paths in `guarded`, would lose local changes.
+fn apply(
+    mut repository: Repository,
+    diff: TreeDifference,
+    guarded: TreeDifference,
+) -> BoxResult<Repository> {
+    let migration = repository.migration(diff).for_merge(guarded);
+    match migration.plan_changes(&repository) {
+        Ok(migration) => repository.apply_migration(migration)?,
+        Err(e) => {
+            eprintln!("{}", e);
+            repository.commit_changes()?;
+            std::process::exit(1);
+        }
+    }
+    Ok(repository)
+}
+
+fn fast_forward(
+    mut repository: Repository,
+    revision: &str,
+    head: &str,
+    theirs: &str,
+) -> BoxResult<()> {
+    let db = &repository.database;
+    println!(
+        "Updating {}..{}",
+        db.truncate_oid(head),
+        db.truncate_oid(theirs)
+    );
+    println!("Fast-forward");
+
+    let diff = db.tree_diff(Some(head.to_owned()), Some(theirs.to_owned()));
+    repository = apply(repository, diff, TreeDifference::new())?;
+    let message = format!("merge {}: Fast-forward", revision);
+    repository.refs.update_head(theirs, &message)?;
+    repository.commit_changes()?;
+    Ok(())
+}
+
+fn three_way(
+    mut repository: Repository,
+    revision: &str,
+    base: &str,
+    head: &str,
+    theirs: &str,
+    message: &str,
+) -> BoxResult<()> {
+    let resolution = resolve(&repository.database, Some(base), head, theirs)?;
+    let guarded = resolution.conflict_diff();
+    repository = apply(repository, resolution.clean, guarded)?;
+
+    let mut reports: BTreeMap<&PathBuf, Option<&Conflict>> = resolution
+        .auto_merged
+        .iter()
+        .map(|path| (path, None))
+        .collect();
+    reports.extend(resolution.conflicts.iter().map(|(path, c)| (path, Some(c))));
+    for (path, conflict) in reports {
+        match conflict {
+            Some(conflict) => {
+                write_conflict(&repository, path, conflict, revision)?;
+                let versions = [&conflict.base, &conflict.ours, &conflict.theirs];
+                repository.index.add_conflict_set(path, versions);
+            }
+            None => println!("Auto-merging {}", path.display()),
+        }
+    }
+    if !resolution.conflicts.is_empty() {
+        PendingCommit::new(repository.refs.git_dir()).start(theirs, message)?;
+        println!("Automatic merge failed; fix conflicts and then commit the result.");
+        repository.commit_changes()?;
+        std::process::exit(1);
+    }
+
+    let parents = vec![head.to_owned(), theirs.to_owned()];
+    let commit = write_commit(&repository.database, &repository.index, parents, message)?;
+    let strategy = "Merge made by the 'resolve' strategy.";
+    let reason = format!("merge {}: {}", revision, strategy);
+    repository.refs.update_head(&commit.oid(), &reason)?;
+    println!("{}", strategy);
+    repository.commit_changes()?;
+    Ok(())
+}
+
+/// Leaves a conflicted path in the workspace for the user to resolve, and
+/// says what happened to it.
+fn write_conflict(
+    repository: &Repository,
+    path: &Path,
+    conflict: &Conflict,
+    revision: &str,
+) -> BoxResult<()> {
+    let name = path.display();
+    match conflict.kind {
+        ConflictKind::Content => {
+            println!("Auto-merging {}", name);
+            let db = &repository.database;
+            match merge_blobs(db, &conflict.base, &conflict.ours, &conflict.theirs)? {
+                Some(merged) => {
+                    let data = merged.render("HEAD", revision, None);
+                    let mode = conflict.ours.as_ref().unwrap().mode();
+                    repository.workspace.write_file(path, &data, &mode)?;
+                }
+                None => eprintln!(
+                    "warning: Cannot merge binary files: {} (HEAD vs. {})",
+                    name, revision
+                ),
+            }
+
+            let kind = if conflict.base.is_some() {
+                "content"
+            } else {
+                "add/add"
+            };
+            println!("CONFLICT ({}): Merge conflict in {}", kind, name);
+        }
+        ConflictKind::DeletedByUs => {
+            let theirs = conflict.theirs.as_ref().unwrap();
+            let (_, _, data) = repository.database.read_object(&theirs.oid())?;
+            repository
+                .workspace
+                .write_file(path, Blob::from(data).content(), &theirs.mode())?;
+            println!(
+                "CONFLICT (modify/delete): {} deleted in HEAD and modified in {}. \
+                 Version {} of {} left in tree.",
+                name, revision, revision, name
+            );
+        }
+        ConflictKind::DeletedByThem => {
+            println!(
+                "CONFLICT (modify/delete): {} deleted in {} and modified in HEAD. \
+                 Version HEAD of {} left in tree.",
+                name, revision, name
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use crate::diff::binary::is_binary;
+use crate::diff::diff3;
+use crate::BoxResult;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use std::io::Write;
+
+pub fn cli() -> App<'static, 'static> {
+    SubCommand::with_name("merge-file")
+        .arg(
+            Arg::with_name("stdout")
+                .short("p")
+                .long("stdout")
+                .help("Print the result rather than overwriting CURRENT."),
+        )
+        .arg(
+            Arg::with_name("diff3")
+                .long("diff3")
+                .help("Show the base's lines in conflicts too."),
+        )
+        .arg(
+            Arg::with_name("label")
+                .short("L")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .max_values(3)
+                .value_name("LABEL")
+                .help("Name CURRENT, BASE and OTHER, in that order, in conflict markers."),
+        )
+        .arg(
+            Arg::with_name("CURRENT")
+                .required(true)
+                .index(1)
+                .help("Our version, which the result is written to"),
+        )
+        .arg(
+            Arg::with_name("BASE")
+                .required(true)
+                .index(2)
+                .help("The version both others descend from"),
+        )
+        .arg(
+            Arg::with_name("OTHER")
+                .required(true)
+                .index(3)
+                .help("Their version"),
+        )
+}
+
+pub fn exec(matches: &ArgMatches) -> BoxResult<()> {
+    let paths: Vec<&str> = ["CURRENT", "BASE", "OTHER"]
+        .iter()
+        .map(|name| matches.value_of(name).unwrap())
+        .collect();
+    let mut labels: Vec<&str> = matches.values_of("label").into_iter().flatten().collect();
+    labels.extend_from_slice(&paths[labels.len()..]);
+
+    let mut texts = vec![];
+    for path in &paths {
+        let data = match std::fs::read(path) {
+            Ok(data) => data,
+            Err(_) => fail(&format!("Could not stat {}", path)),
+        };
+        if is_binary(&data) {
+            fail(&format!("Cannot merge binary files: {}", path));
+        }
+        texts.push(data);
+    }
+
+    let merged = diff3::merge(&texts[1], &texts[0], &texts[2]);
+    let base_label = if matches.is_present("diff3") {
+        Some(labels[1])
+    } else {
+        None
+    };
+    let result = merged.render(labels[0], labels[2], base_label);
+    if matches.is_present("stdout") {
+        std::io::stdout().write_all(&result)?;
+    } else {
+        std::fs::write(paths[0], result)?;
+    }
+
+    // like git, report how many conflicts there were in the exit status
+    let conflicts = merged.conflicts();
+    if conflicts > 0 {
+        std::process::exit(conflicts.min(127) as i32);
+    }
+    Ok(())
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    std::process::exit(255);
+}
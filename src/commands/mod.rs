@@ -2,4 +2,18 @@ pub mod branch;
 pub mod checkout;
 pub mod commit;
 pub mod diff;
+pub mod gc;
+pub mod log;
+pub mod merge;
+pub mod merge_file;
+pub mod pack_refs;
+pub mod reflog;
+pub mod repack;
+pub mod reset;
+pub mod restore;
+pub mod rev_list;
+pub mod rev_parse;
+pub mod show;
 pub mod status;
+pub mod switch;
+pub mod tag;
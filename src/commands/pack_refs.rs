@@ -0,0 +1,24 @@
+use crate::refs::Refs;
+use crate::BoxResult;
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+pub fn cli() -> App<'static, 'static> {
+    SubCommand::with_name("pack-refs")
+        .arg(
+            Arg::with_name("all")
+                .long("all")
+                .help("Pack all refs, not just tags and refs that are already packed."),
+        )
+        .arg(
+            Arg::with_name("no-prune")
+                .long("no-prune")
+                .help("Keep the loose refs after packing them."),
+        )
+}
+
+pub fn exec(matches: &ArgMatches) -> BoxResult<()> {
+    let root = std::path::Path::new(".");
+    let refs = Refs::new(root.join(".git"));
+    refs.pack_refs(matches.is_present("all"), !matches.is_present("no-prune"))?;
+    Ok(())
+}
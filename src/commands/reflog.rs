@@ -0,0 +1,93 @@
+use crate::database::Database;
+use crate::refs::Refs;
+use crate::utilities::parse_date;
+use crate::{BoxResult, CliError};
+use chrono::Local;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use colored::Colorize;
+
+const DEFAULT_EXPIRE: &str = "90.days.ago";
+
+pub fn cli() -> App<'static, 'static> {
+    SubCommand::with_name("reflog")
+        .setting(AppSettings::ArgsNegateSubcommands)
+        .arg(Arg::with_name("REF").index(1))
+        .subcommand(SubCommand::with_name("show").arg(Arg::with_name("REF").index(1)))
+        .subcommand(
+            SubCommand::with_name("expire")
+                .arg(
+                    Arg::with_name("expire")
+                        .long("expire")
+                        .takes_value(true)
+                        .help("Prune entries older than the given time (default 90.days.ago)."),
+                )
+                .arg(
+                    Arg::with_name("all")
+                        .long("all")
+                        .help("Process the reflogs of all references."),
+                )
+                .arg(Arg::with_name("REF").index(1).multiple(true)),
+        )
+}
+
+pub fn exec(matches: &ArgMatches) -> BoxResult<()> {
+    let root = std::path::Path::new(".");
+    let db = Database::new(root.join(".git/objects"));
+    let refs = Refs::new(root.join(".git"));
+
+    match matches.subcommand() {
+        ("expire", Some(m)) => expire(&refs, m),
+        ("show", Some(m)) => show(&db, &refs, m.value_of("REF").unwrap_or("HEAD")),
+        _ => show(&db, &refs, matches.value_of("REF").unwrap_or("HEAD")),
+    }
+}
+
+fn show(db: &Database, refs: &Refs, name: &str) -> BoxResult<()> {
+    let full_name = refs.expand_name(name).unwrap_or_else(|| name.to_owned());
+    let entries = refs.reflog(&full_name)?;
+    for (i, entry) in entries.iter().rev().enumerate() {
+        println!(
+            "{} {}@{{{}}}: {}",
+            db.truncate_oid(&entry.new_oid).yellow(),
+            name,
+            i,
+            entry.message
+        );
+    }
+    Ok(())
+}
+
+fn expire(refs: &Refs, matches: &ArgMatches) -> BoxResult<()> {
+    let expire = matches.value_of("expire").unwrap_or(DEFAULT_EXPIRE);
+    let cutoff = match expire {
+        "never" | "false" => return Ok(()),
+        "all" => Local::now(),
+        other => parse_date(other, Local::now()).ok_or_else(|| {
+            CliError::new(&format!("fatal: malformed expiration date '{}'", other))
+        })?,
+    };
+
+    let names = if matches.is_present("all") {
+        refs.list_reflogs()
+    } else {
+        match matches.values_of("REF") {
+            Some(names) => names
+                .map(|n| refs.expand_name(n).unwrap_or_else(|| n.to_owned()))
+                .collect(),
+            None => return Err(CliError::new("fatal: no reflog specified").into()),
+        }
+    };
+
+    for name in names {
+        let entries = refs.reflog(&name)?;
+        let kept = entries
+            .iter()
+            .filter(|e| e.committer.time() > cutoff)
+            .cloned()
+            .collect::<Vec<_>>();
+        if kept.len() != entries.len() {
+            refs.write_reflog(&name, &kept)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use crate::database::pack::writer::Writer;
+use crate::database::Database;
+use crate::index::Index;
+use crate::refs::Refs;
+use crate::BoxResult;
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+pub const DEFAULT_WINDOW: usize = 10;
+pub const DEFAULT_DEPTH: usize = 50;
+
+pub fn cli() -> App<'static, 'static> {
+    SubCommand::with_name("repack")
+        .arg(
+            Arg::with_name("all")
+                .short("a")
+                .help("Pack everything referenced into a single pack."),
+        )
+        .arg(
+            Arg::with_name("delete")
+                .short("d")
+                .help("Remove redundant packs and loose objects after packing."),
+        )
+        .arg(
+            Arg::with_name("window")
+                .long("window")
+                .takes_value(true)
+                .help("The number of objects considered as delta bases for each object."),
+        )
+        .arg(
+            Arg::with_name("depth")
+                .long("depth")
+                .takes_value(true)
+                .help("The maximum delta chain length."),
+        )
+}
+
+pub fn exec(matches: &ArgMatches) -> BoxResult<()> {
+    let window = match matches.value_of("window") {
+        Some(w) => w.parse()?,
+        None => DEFAULT_WINDOW,
+    };
+    let depth = match matches.value_of("depth") {
+        Some(d) => d.parse()?,
+        None => DEFAULT_DEPTH,
+    };
+    repack(
+        matches.is_present("all"),
+        matches.is_present("delete"),
+        window,
+        depth,
+    )
+}
+
+pub fn repack(all: bool, delete: bool, window: usize, depth: usize) -> BoxResult<()> {
+    let root = std::path::Path::new(".");
+    let db = Database::new(root.join(".git/objects"));
+    let refs = Refs::new(root.join(".git"));
+
+    let mut writer = Writer::new(&db, !all);
+    if let Some(head) = refs.get_head() {
+        writer.add_reachable(&head)?;
+    }
+    for (_, oid) in refs.list_refs() {
+        writer.add_reachable(&oid)?;
+    }
+    // keep whatever the reflogs can still take a ref back to
+    for name in refs.list_reflogs() {
+        for entry in refs.reflog(&name)? {
+            if db.has_object(&entry.new_oid) {
+                writer.add_reachable(&entry.new_oid)?;
+            }
+        }
+    }
+
+    // and whatever is staged, which may be held by no commit at all
+    let index = Index::from(root.join(".git/index"))?;
+    for entry in index.entries() {
+        if db.has_object(&entry.oid) {
+            writer.add_reachable(&entry.oid)?;
+        }
+    }
+    index.release_lock()?;
+
+    if writer.is_empty() {
+        eprintln!("Nothing new to pack.");
+        return Ok(());
+    }
+
+    writer.compress(window, depth);
+    let name = writer.write(db.pack_path())?;
+    eprintln!("Total {} (delta {})", writer.len(), writer.deltas());
+
+    db.reload_packs();
+    if delete {
+        if all {
+            db.remove_packs_except(&name)?;
+        }
+        db.prune_packed()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,230 @@
+use crate::commands::checkout::{describe, is_revision};
+use crate::commands::restore::{normalize, resolve_tree, restore_paths, RestoreOptions};
+use crate::database::ObjectKind;
+use crate::repository::pending_commit::PendingCommit;
+use crate::repository::Repository;
+use crate::revision::RevisionResolver;
+use crate::BoxResult;
+use clap::{App, Arg, ArgGroup, ArgMatches, SubCommand};
+use failure::format_err;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+    Soft,
+    Mixed,
+    Hard,
+}
+
+pub fn cli() -> App<'static, 'static> {
+    SubCommand::with_name("reset")
+        .arg(
+            Arg::with_name("soft")
+                .long("soft")
+                .help("Only move HEAD, leaving the index and workspace alone."),
+        )
+        .arg(
+            Arg::with_name("mixed")
+                .long("mixed")
+                .help("Move HEAD and reset the index, which is the default."),
+        )
+        .arg(
+            Arg::with_name("hard")
+                .long("hard")
+                .help("Move HEAD and reset the index and workspace, discarding local changes."),
+        )
+        .group(ArgGroup::with_name("mode").args(&["soft", "mixed", "hard"]))
+        .arg(
+            Arg::with_name("REVISION").index(1).multiple(true).help(
+                "The commit to reset to, which defaults to HEAD, optionally followed by paths",
+            ),
+        )
+        .arg(
+            Arg::with_name("PATH")
+                .multiple(true)
+                .last(true)
+                .help("Files or directories to reset in the index only"),
+        )
+}
+
+pub fn exec(matches: &ArgMatches) -> BoxResult<()> {
+    let root = std::path::Path::new(".");
+    let mut repository = Repository::new(root)?;
+    let args: Vec<&str> = matches
+        .values_of("REVISION")
+        .into_iter()
+        .flatten()
+        .collect();
+
+    let (revision, paths) = match matches.values_of("PATH") {
+        Some(paths) if args.len() <= 1 => (args.first().copied(), paths.collect()),
+        Some(_) => fatal(
+            repository,
+            format!("only one revision expected, {} given.", args.len()),
+        ),
+        None if args.is_empty() => (None, vec![]),
+        None if is_revision(&repository, args[0]) => (Some(args[0]), args[1..].to_vec()),
+        None => (None, args),
+    };
+    let mode = if matches.is_present("soft") {
+        Mode::Soft
+    } else if matches.is_present("hard") {
+        Mode::Hard
+    } else {
+        Mode::Mixed
+    };
+
+    if !paths.is_empty() {
+        if mode != Mode::Mixed {
+            let name = format!("{:?}", mode).to_lowercase();
+            fatal(repository, format!("Cannot do {} reset with paths.", name));
+        }
+        return reset_paths(repository, revision, &paths);
+    }
+
+    let head = repository.refs.get_head();
+    let (revision, oid) = match (revision, head.clone()) {
+        (None, None) => {
+            // nothing has been committed, so there is only the index to clear
+            if mode != Mode::Soft {
+                unstage(&mut repository, &[], mode == Mode::Hard)?;
+            }
+            repository.commit_changes()?;
+            return Ok(());
+        }
+        (None, Some(head)) => ("HEAD", head),
+        (Some(revision), _) => (revision, resolve_commit(&repository, revision)),
+    };
+
+    let pending = PendingCommit::new(repository.refs.git_dir());
+    if mode == Mode::Soft && pending.in_progress() {
+        fatal(
+            repository,
+            String::from("Cannot do a soft reset in the middle of a merge."),
+        );
+    }
+    match mode {
+        Mode::Soft => {}
+        Mode::Mixed => {
+            let tree = resolve_tree(&repository, &oid);
+            restore_paths(&mut repository, &[], &staged_from(tree, false))?;
+        }
+        Mode::Hard => {
+            // unlike a checkout, local changes and untracked files in the
+            // way are overwritten rather than refused
+            let tree = resolve_tree(&repository, &oid);
+            restore_paths(&mut repository, &[], &staged_from(tree, true))?;
+        }
+    }
+    if let Some(ref head) = head {
+        repository.refs.set_orig_head(head)?;
+    }
+    let message = format!("reset: moving to {}", revision);
+    repository.refs.update_head(&oid, &message)?;
+    pending.clear()?;
+
+    match mode {
+        Mode::Hard => println!("HEAD is now at {}", describe(&repository, &oid)),
+        _ => print_unstaged(&mut repository)?,
+    }
+    repository.commit_changes()?;
+    Ok(())
+}
+
+fn fatal(repository: Repository, message: String) -> ! {
+    eprintln!("fatal: {}", message);
+    let _ = repository.commit_changes();
+    std::process::exit(128);
+}
+
+fn resolve_commit(repository: &Repository, revision: &str) -> String {
+    let mut rr = RevisionResolver::new(&repository.database, &repository.refs, revision);
+    match rr.resolver(ObjectKind::Commit) {
+        Ok(oid) => oid,
+        Err(_) => {
+            for error in rr.errors {
+                eprintln!("{}", error);
+            }
+            eprintln!(
+                "fatal: Failed to resolve '{}' as a valid revision.",
+                revision
+            );
+            std::process::exit(128);
+        }
+    }
+}
+
+fn staged_from(tree: String, worktree: bool) -> RestoreOptions {
+    RestoreOptions {
+        source: Some(tree),
+        staged: true,
+        worktree,
+        overlay: false,
+    }
+}
+
+/// Sets the index entries for `paths` back to their contents in `revision`,
+/// or HEAD, without touching HEAD or the workspace.
+fn reset_paths(
+    mut repository: Repository,
+    revision: Option<&str>,
+    paths: &[&str],
+) -> BoxResult<()> {
+    let result = match (revision, repository.refs.get_head()) {
+        (None, None) => unstage(&mut repository, paths, false),
+        (revision, _) => {
+            let tree = resolve_tree(&repository, revision.unwrap_or("HEAD"));
+            restore_paths(&mut repository, paths, &staged_from(tree, false))
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        repository.commit_changes()?;
+        std::process::exit(1);
+    }
+    print_unstaged(&mut repository)?;
+    repository.commit_changes()?;
+    Ok(())
+}
+
+/// Removes the entries matching `paths`, or all of them, from the index, and
+/// with `worktree` from the workspace too, for when there is no commit to
+/// reset them to.
+fn unstage(repository: &mut Repository, paths: &[&str], worktree: bool) -> BoxResult<usize> {
+    let specs: Vec<_> = paths.iter().map(|p| normalize(p)).collect();
+    let entries = repository.index.entries();
+    if let Some(spec) = specs
+        .iter()
+        .find(|spec| !entries.iter().any(|entry| entry.path.starts_with(spec)))
+    {
+        return Err(format_err!(
+            "pathspec '{}' did not match any file(s) known to rit",
+            spec.display()
+        )
+        .into());
+    }
+
+    let mut removed = 0;
+    for entry in entries {
+        if specs.is_empty() || specs.iter().any(|spec| entry.path.starts_with(spec)) {
+            if worktree {
+                repository.workspace.remove_file(&entry.path)?;
+            }
+            repository.index.remove(entry.path.to_str().unwrap());
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Lists the tracked files whose workspace copies now differ from the index.
+fn print_unstaged(repository: &mut Repository) -> BoxResult<()> {
+    repository.status()?;
+    if repository.workspace_changes.is_empty() {
+        return Ok(());
+    }
+    println!("Unstaged changes after reset:");
+    for (path, status) in &repository.workspace_changes {
+        println!("{}\t{}", status, path);
+    }
+    Ok(())
+}
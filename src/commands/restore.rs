@@ -0,0 +1,210 @@
+use crate::database::{Blob, ObjectKind};
+use crate::repository::{Repository, Status};
+use crate::revision::RevisionResolver;
+use crate::BoxResult;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use failure::format_err;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub fn cli() -> App<'static, 'static> {
+    SubCommand::with_name("restore")
+        .arg(
+            Arg::with_name("source")
+                .short("s")
+                .long("source")
+                .takes_value(true)
+                .value_name("TREE")
+                .help("Restore from TREE rather than the index, or HEAD with --staged."),
+        )
+        .arg(
+            Arg::with_name("staged")
+                .short("S")
+                .long("staged")
+                .help("Restore the index."),
+        )
+        .arg(
+            Arg::with_name("worktree")
+                .short("W")
+                .long("worktree")
+                .help("Restore the working tree, which is the default."),
+        )
+        .arg(
+            Arg::with_name("PATH")
+                .required(true)
+                .multiple(true)
+                .help("The files or directories to restore"),
+        )
+}
+
+pub fn exec(matches: &ArgMatches) -> BoxResult<()> {
+    let root = std::path::Path::new(".");
+    let mut repository = Repository::new(root)?;
+
+    let staged = matches.is_present("staged");
+    let worktree = matches.is_present("worktree") || !staged;
+    let source = match matches.value_of("source") {
+        Some(source) => Some(source),
+        None if staged => Some("HEAD"),
+        None => None,
+    };
+    let tree = source.map(|source| resolve_tree(&repository, source));
+    let paths: Vec<&str> = matches.values_of("PATH").unwrap().collect();
+
+    let options = RestoreOptions {
+        source: tree,
+        staged,
+        worktree,
+        overlay: false,
+    };
+    if let Err(e) = restore_paths(&mut repository, &paths, &options) {
+        eprintln!("error: {}", e);
+        repository.commit_changes()?;
+        std::process::exit(1);
+    }
+    repository.commit_changes()?;
+    Ok(())
+}
+
+/// Where `restore_paths` takes files from and what it writes them to.
+pub(crate) struct RestoreOptions {
+    /// The tree to restore from, or `None` for the index.
+    pub(crate) source: Option<String>,
+    pub(crate) staged: bool,
+    pub(crate) worktree: bool,
+    /// Whether files that are missing from the source are kept, as
+    /// `checkout` does, rather than removed, as `restore` does.
+    pub(crate) overlay: bool,
+}
+
+/// Resolves a tree-ish such as a commit or tag, exiting if it cannot be.
+pub(crate) fn resolve_tree(repository: &Repository, revision: &str) -> String {
+    let expr = format!("{}^{{tree}}", revision);
+    let mut rr = RevisionResolver::new(&repository.database, &repository.refs, &expr);
+    match rr.resolver(ObjectKind::Tree) {
+        Ok(oid) => oid,
+        Err(_) => {
+            for error in rr.errors {
+                eprintln!("{}", error);
+            }
+            eprintln!("fatal: could not resolve {}", revision);
+            std::process::exit(128);
+        }
+    }
+}
+
+/// Rewrites the files matching `pathspecs`, or every file when there are
+/// none, from the source, returning how many paths were updated. Nothing is
+/// touched unless every pathspec matches something.
+pub(crate) fn restore_paths(
+    repository: &mut Repository,
+    pathspecs: &[&str],
+    options: &RestoreOptions,
+) -> BoxResult<usize> {
+    let everything = pathspecs.is_empty();
+    let pathspecs: Vec<PathBuf> = pathspecs.iter().map(|p| normalize(p)).collect();
+    let matches = |path: &Path| everything || pathspecs.iter().any(|spec| path.starts_with(spec));
+
+    let items: BTreeMap<PathBuf, (String, String)> = match options.source {
+        Some(ref tree) => {
+            repository.load_tree(tree)?;
+            repository
+                .tree
+                .iter()
+                .map(|(path, marker)| (path.clone(), (marker.oid.clone(), marker.mode.clone())))
+                .collect()
+        }
+        None => repository
+            .index
+            .entries()
+            .into_iter()
+            .map(|entry| (entry.path.clone(), (entry.oid.clone(), entry.mode())))
+            .collect(),
+    };
+    let tracked: Vec<PathBuf> = repository
+        .index
+        .entries()
+        .into_iter()
+        .map(|entry| entry.path)
+        .collect();
+
+    for spec in &pathspecs {
+        let known = items.keys().any(|path| path.starts_with(spec))
+            || (!options.overlay && tracked.iter().any(|path| path.starts_with(spec)));
+        if !known {
+            return Err(format_err!(
+                "pathspec '{}' did not match any file(s) known to rit",
+                spec.display()
+            )
+            .into());
+        }
+    }
+
+    let mut updated = 0;
+    if !options.overlay {
+        for path in tracked
+            .iter()
+            .filter(|p| matches(p) && !items.contains_key(*p))
+        {
+            if options.worktree {
+                repository.workspace.remove_file(path)?;
+            }
+            if options.staged {
+                repository.index.remove(path.to_str().unwrap());
+            }
+            updated += 1;
+        }
+    }
+
+    for (path, (oid, mode)) in items.iter().filter(|(p, _)| matches(p)) {
+        if is_unchanged(repository, path, oid, mode, options)? {
+            updated += 1;
+            continue;
+        }
+        if options.worktree {
+            let (_, _, data) = repository.database.read_object(oid)?;
+            repository
+                .workspace
+                .write_file(path, Blob::from(data).content(), mode)?;
+        }
+        let written = options.worktree && (options.staged || options.source.is_none());
+        if written {
+            let stat = repository.workspace.stat_file(path)?;
+            repository.index.add(path, oid, stat);
+        } else if options.staged {
+            repository.index.add_from_db(path, oid, mode);
+        }
+        updated += 1;
+    }
+    Ok(updated)
+}
+
+/// Whether the index, and the workspace if it is being restored too,
+/// already hold the source's version of `path`.
+fn is_unchanged(
+    repository: &Repository,
+    path: &Path,
+    oid: &str,
+    mode: &str,
+    options: &RestoreOptions,
+) -> BoxResult<bool> {
+    let entry = match repository.index.get_entry(path.to_str().unwrap()) {
+        Some(entry) if entry.oid == oid && entry.mode() == mode => entry,
+        _ => return Ok(false),
+    };
+    if !options.worktree {
+        return Ok(true);
+    }
+    let stat = repository.workspace.stat_file(&path.to_path_buf()).ok();
+    let status = repository.compare_index_to_workspace(Some(entry), stat.as_ref())?;
+    Ok(status == Status::None)
+}
+
+/// Turns a pathspec such as `./dir/` into the path it names relative to the
+/// root of the workspace, with `.` meaning everything.
+pub(crate) fn normalize(pathspec: &str) -> PathBuf {
+    let path = Path::new(pathspec);
+    path.components()
+        .filter(|c| *c != std::path::Component::CurDir)
+        .collect()
+}
@@ -0,0 +1,129 @@
+use crate::commands::log::resolve_revisions;
+use crate::database::Database;
+use crate::refs::Refs;
+use crate::revision::walk::{Order, RevWalk};
+use crate::workspace::Workspace;
+use crate::BoxResult;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use std::collections::HashSet;
+
+pub fn cli() -> App<'static, 'static> {
+    SubCommand::with_name("rev-list")
+        .arg(
+            Arg::with_name("count")
+                .long("count")
+                .help("Print the number of commits instead of listing them."),
+        )
+        .arg(
+            Arg::with_name("left-right")
+                .long("left-right")
+                .help("Mark which side of a symmetric difference each commit is on."),
+        )
+        .arg(
+            Arg::with_name("max-count")
+                .short("n")
+                .long("max-count")
+                .takes_value(true)
+                .help("Limit the number of commits listed."),
+        )
+        .arg(
+            Arg::with_name("topo-order")
+                .long("topo-order")
+                .help("Show no parents before all of their children."),
+        )
+        .arg(
+            Arg::with_name("date-order")
+                .long("date-order")
+                .overrides_with("topo-order")
+                .help("Show commits in commit timestamp order."),
+        )
+        .arg(
+            Arg::with_name("reverse")
+                .long("reverse")
+                .help("List the selected commits oldest first."),
+        )
+        .arg(
+            Arg::with_name("first-parent")
+                .long("first-parent")
+                .help("Follow only the first parent of merge commits."),
+        )
+        .arg(
+            Arg::with_name("ancestry-path")
+                .long("ancestry-path")
+                .help("List only commits that descend from an excluded commit."),
+        )
+        .arg(Arg::with_name("REVISION").index(1).multiple(true))
+        .arg(Arg::with_name("PATH").multiple(true).last(true))
+}
+
+pub fn exec(matches: &ArgMatches) -> BoxResult<()> {
+    let root = std::path::Path::new(".");
+    let workspace = Workspace::new(root);
+    let db = Database::new(root.join(".git/objects"));
+    let refs = Refs::new(root.join(".git"));
+
+    let (tips, paths) = resolve_revisions(&workspace, &db, &refs, matches);
+    if tips.is_empty() {
+        eprintln!("usage: rit rev-list [<options>] <commit>... [--] [<path>...]");
+        std::process::exit(129);
+    }
+    let max_count = match matches.value_of("max-count") {
+        Some(n) => Some(n.parse::<usize>()?),
+        None => None,
+    };
+    let first_parent = matches.is_present("first-parent");
+
+    let mut walk = RevWalk::new(&db);
+    walk.set_paths(paths);
+    walk.set_ancestry_path(matches.is_present("ancestry-path"));
+    walk.set_first_parent(first_parent);
+    if matches.is_present("topo-order") {
+        walk.set_order(Order::Topo);
+    }
+    for tip in &tips {
+        walk.push_tip(tip)?;
+    }
+
+    let mut commits = vec![];
+    while let Some((oid, _)) = walk.next_commit()? {
+        if max_count.map(|n| commits.len() >= n).unwrap_or(false) {
+            break;
+        }
+        commits.push(oid);
+    }
+    if matches.is_present("reverse") {
+        commits.reverse();
+    }
+
+    let left_right = matches.is_present("left-right");
+    let mut left = HashSet::new();
+    if left_right && tips.iter().any(|t| t.left) {
+        let mut walk = RevWalk::new(&db);
+        walk.set_first_parent(first_parent);
+        for tip in tips.iter().filter(|t| t.left || t.exclude) {
+            walk.push_tip(tip)?;
+        }
+        while let Some((oid, _)) = walk.next_commit()? {
+            left.insert(oid);
+        }
+    }
+
+    if matches.is_present("count") {
+        if left_right {
+            let lefts = commits.iter().filter(|oid| left.contains(*oid)).count();
+            println!("{}\t{}", lefts, commits.len() - lefts);
+        } else {
+            println!("{}", commits.len());
+        }
+        return Ok(());
+    }
+    for oid in commits {
+        if left_right {
+            let mark = if left.contains(&oid) { '<' } else { '>' };
+            println!("{}{}", mark, oid);
+        } else {
+            println!("{}", oid);
+        }
+    }
+    Ok(())
+}
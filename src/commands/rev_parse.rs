@@ -0,0 +1,171 @@
+use crate::database::{Database, MIN_ABBREV};
+use crate::refs::Refs;
+use crate::revision::{Revision, RevisionResolver, Tip};
+use crate::utilities::find_toplevel;
+use crate::BoxResult;
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+pub fn cli() -> App<'static, 'static> {
+    SubCommand::with_name("rev-parse")
+        .arg(
+            Arg::with_name("verify")
+                .long("verify")
+                .help("Check that exactly one argument names an object."),
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .short("q")
+                .long("quiet")
+                .requires("verify")
+                .help("With --verify, exit with status 1 instead of printing an error."),
+        )
+        .arg(
+            Arg::with_name("short")
+                .long("short")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .value_name("n")
+                .help("Print the shortest unique prefix of at least n characters."),
+        )
+        .arg(
+            Arg::with_name("abbrev-ref")
+                .long("abbrev-ref")
+                .help("Print the short name of each ref."),
+        )
+        .arg(
+            Arg::with_name("symbolic-full-name")
+                .long("symbolic-full-name")
+                .conflicts_with("abbrev-ref")
+                .help("Print the full name of each ref."),
+        )
+        .arg(
+            Arg::with_name("git-dir")
+                .long("git-dir")
+                .help("Print the path of the .git directory."),
+        )
+        .arg(
+            Arg::with_name("show-toplevel")
+                .long("show-toplevel")
+                .help("Print the absolute path of the top of the working tree."),
+        )
+        .arg(
+            Arg::with_name("is-inside-work-tree")
+                .long("is-inside-work-tree")
+                .help("Print whether the current directory is inside the working tree."),
+        )
+        .arg(Arg::with_name("ARGS").index(1).multiple(true))
+}
+
+pub fn exec(matches: &ArgMatches) -> BoxResult<()> {
+    let cwd = std::fs::canonicalize(".")?;
+    let root = match find_toplevel(&cwd) {
+        Some(root) => root,
+        None => {
+            eprintln!("fatal: not a git repository (or any of the parent directories): .git");
+            std::process::exit(128);
+        }
+    };
+    let db = Database::new(root.join(".git/objects"));
+    let refs = Refs::new(root.join(".git"));
+
+    if matches.is_present("git-dir") {
+        // relative at the top of the working tree, as git prints it
+        if cwd == root {
+            println!(".git");
+        } else {
+            println!("{}", refs.git_dir().display());
+        }
+    }
+    if matches.is_present("show-toplevel") {
+        println!("{}", root.display());
+    }
+    if matches.is_present("is-inside-work-tree") {
+        println!("{}", !cwd.starts_with(refs.git_dir()));
+    }
+
+    let short = match matches.value_of("short") {
+        Some(n) => Some(n.parse::<usize>()?.max(MIN_ABBREV)),
+        None => None,
+    };
+    let show_oid = |oid: &str| match short {
+        Some(len) => db.abbreviate(oid, len),
+        None if matches.is_present("short") => db.truncate_oid(oid),
+        None => oid.to_owned(),
+    };
+
+    let args: Vec<&str> = matches.values_of("ARGS").into_iter().flatten().collect();
+    if matches.is_present("verify") {
+        let oid = match args.as_slice() {
+            [arg] => {
+                let mut rr = RevisionResolver::new(&db, &refs, arg);
+                rr.resolve_object().map_err(|_| rr.errors)
+            }
+            _ => Err(vec![]),
+        };
+        match oid {
+            Ok(oid) => println!("{}", show_oid(&oid)),
+            Err(_) if matches.is_present("quiet") => std::process::exit(1),
+            Err(errors) => {
+                for error in errors {
+                    eprint!("{}", error);
+                }
+                eprintln!("fatal: Needed a single revision");
+                std::process::exit(128);
+            }
+        }
+        return Ok(());
+    }
+
+    let symbolic = matches.is_present("abbrev-ref") || matches.is_present("symbolic-full-name");
+    for arg in args {
+        if symbolic {
+            if let Some(name) = full_name(&refs, arg) {
+                if matches.is_present("abbrev-ref") {
+                    println!("{}", refs.short_name(&name));
+                } else {
+                    println!("{}", name);
+                }
+                continue;
+            }
+        }
+
+        let mut rr = RevisionResolver::new(&db, &refs, arg);
+        let is_set = Revision::from(arg).map(|r| r.is_set()).unwrap_or(false);
+        let tips = if is_set {
+            rr.resolve_tips()
+        } else {
+            rr.resolve_object().map(|oid| vec![Tip::include(oid)])
+        };
+        match tips {
+            // revisions that are not refs have no name to print
+            Ok(_) if symbolic => {}
+            Ok(tips) => {
+                for tip in tips {
+                    let mark = if tip.exclude { "^" } else { "" };
+                    println!("{}{}", mark, show_oid(&tip.oid));
+                }
+            }
+            Err(_) => {
+                println!("{}", arg);
+                for error in rr.errors {
+                    eprint!("{}", error);
+                }
+                eprintln!(
+                    "fatal: ambiguous argument '{}': unknown revision or path not in the working tree.",
+                    arg
+                );
+                std::process::exit(128);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The full name of the ref `arg` names, following HEAD to its branch.
+fn full_name(refs: &Refs, arg: &str) -> Option<String> {
+    match arg {
+        "HEAD" | "@" => Some(refs.current_ref().unwrap_or_else(|| String::from("HEAD"))),
+        name => refs.expand_name(name),
+    }
+}
@@ -0,0 +1,132 @@
+use crate::attributes::Attributes;
+use crate::commands::diff::DiffOptions;
+use crate::commands::log::{show_commit, Format, LogOptions, DATE_FORMAT};
+use crate::commit::Commit;
+use crate::database::{Database, ObjectKind};
+use crate::refs::Refs;
+use crate::revision::RevisionResolver;
+use crate::tag::Tag;
+use crate::tree::Tree;
+use crate::workspace::Workspace;
+use crate::BoxResult;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use colored::Colorize;
+use std::convert::TryFrom;
+use std::io::Write;
+
+pub fn cli() -> App<'static, 'static> {
+    SubCommand::with_name("show")
+        .arg(
+            Arg::with_name("oneline")
+                .long("oneline")
+                .help("Show each commit on a single line."),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .alias("pretty")
+                .takes_value(true)
+                .help("Pretty-print commits with a format string such as '%h %s'."),
+        )
+        .arg(
+            Arg::with_name("stat")
+                .long("stat")
+                .help("Show a summary of the files each commit changes."),
+        )
+        .arg(
+            Arg::with_name("no-patch")
+                .short("s")
+                .long("no-patch")
+                .help("Do not show the changes a commit introduces."),
+        )
+        .arg(
+            Arg::with_name("OBJECT")
+                .index(1)
+                .multiple(true)
+                .help("Commits, trees or blobs to show, such as HEAD~2:src/main.rs"),
+        )
+}
+
+pub fn exec(matches: &ArgMatches) -> BoxResult<()> {
+    let root = std::path::Path::new(".");
+    let workspace = Workspace::new(root);
+    let db = Database::new(root.join(".git/objects"));
+    let refs = Refs::new(root.join(".git"));
+
+    let stat = matches.is_present("stat");
+    let options = LogOptions {
+        format: Format::from_matches(matches),
+        patch: !matches.is_present("no-patch") && !stat,
+        stat,
+        paths: vec![],
+        diff: DiffOptions {
+            binary: false,
+            attributes: Attributes::load(&workspace),
+        },
+    };
+
+    let objects = match matches.values_of("OBJECT") {
+        Some(objects) => objects.collect(),
+        None => vec!["HEAD"],
+    };
+    for (i, name) in objects.into_iter().enumerate() {
+        let mut rr = RevisionResolver::new(&db, &refs, name);
+        let oid = match rr.resolve_object() {
+            Ok(oid) => oid,
+            Err(_) => {
+                for error in rr.errors {
+                    eprintln!("{}", error);
+                }
+                eprintln!(
+                    "fatal: ambiguous argument '{}': unknown revision or path not in the working tree.",
+                    name
+                );
+                std::process::exit(128);
+            }
+        };
+
+        show_object(&db, name, &oid, &options, i == 0)?;
+    }
+    Ok(())
+}
+
+fn show_object(
+    db: &Database,
+    name: &str,
+    oid: &str,
+    options: &LogOptions,
+    first: bool,
+) -> BoxResult<()> {
+    let (kind, _, data) = db.read_object(oid)?;
+    match kind {
+        ObjectKind::Commit => {
+            let commit = Commit::try_from(data)?;
+            show_commit(db, oid, &commit, options, first)?;
+        }
+        ObjectKind::Tree => {
+            if !first {
+                println!();
+            }
+            println!("tree {}\n", name);
+            for (entry_name, entry) in Tree::try_from(data)?.entries {
+                let slash = if entry.is_tree() { "/" } else { "" };
+                println!("{}{}", entry_name, slash);
+            }
+        }
+        ObjectKind::Blob => std::io::stdout().write_all(&data)?,
+        ObjectKind::Tag => {
+            let tag = Tag::try_from(data)?;
+            if !first {
+                println!();
+            }
+            println!("{}", format!("tag {}", tag.name).yellow());
+            if let Some(ref tagger) = tag.tagger {
+                println!("Tagger: {} <{}>", tagger.name(), tagger.email());
+                println!("Date:   {}", tagger.local_time().format(DATE_FORMAT));
+            }
+            println!("\n{}", tag.message().trim_end());
+            show_object(db, &tag.object, &tag.object, options, false)?;
+        }
+    }
+    Ok(())
+}
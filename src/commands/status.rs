@@ -45,6 +45,7 @@ impl StatusPrinter for Repository {
         let untracked = self.untracked.clone();
 
         print_changes("Changes to be committed", index, "green");
+        print_conflicts(&self.conflicts);
         print_changes("Changes not staged for commit", workspace, "red");
 
         if !untracked.is_empty() {
@@ -62,7 +63,7 @@ impl StatusPrinter for Repository {
         if !self.index_changes.is_empty() {
             return;
         }
-        if !self.workspace_changes.is_empty() {
+        if !self.workspace_changes.is_empty() || !self.conflicts.is_empty() {
             println!("no changes added to commit");
         } else if !self.untracked.is_empty() {
             println!("nothing added to commit but untracked files present");
@@ -88,6 +89,9 @@ impl StatusPrinter for Repository {
     }
 
     fn status_for(&self, file: &str) -> String {
+        if let Some(stages) = self.conflicts.get(file) {
+            return conflict_status(stages).0.to_owned();
+        }
         format!(
             "{}{}",
             self.index_changes.get(file).unwrap_or(&Status::None),
@@ -105,6 +109,32 @@ fn long_format(status: Status) -> String {
     }
 }
 
+/// The short and long descriptions of a conflict, from the stages the
+/// index has for it.
+fn conflict_status(stages: &[u16]) -> (&'static str, &'static str) {
+    match stages {
+        [1, 2, 3] => ("UU", "both modified:"),
+        [1, 2] => ("UD", "deleted by them:"),
+        [1, 3] => ("DU", "deleted by us:"),
+        [2, 3] => ("AA", "both added:"),
+        [2] => ("AU", "added by us:"),
+        [3] => ("UA", "added by them:"),
+        _ => ("DD", "both deleted:"),
+    }
+}
+
+fn print_conflicts(conflicts: &BTreeMap<String, Vec<u16>>) {
+    if !conflicts.is_empty() {
+        println!("Unmerged paths");
+        println!();
+        for (path, stages) in conflicts {
+            let item = format!("{:17}{}", conflict_status(stages).1, path).red();
+            println!("\t{}", item);
+        }
+        println!();
+    }
+}
+
 fn print_changes(msg: &str, index: BTreeMap<String, Status>, colour: &str) {
     if !index.is_empty() {
         println!("{}", msg);
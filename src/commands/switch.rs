@@ -0,0 +1,187 @@
+use crate::commands::checkout::{
+    describe, migrate, moving_message, print_head_change, BranchChange,
+};
+use crate::database::ObjectKind;
+use crate::repository::Repository;
+use crate::revision::RevisionResolver;
+use crate::BoxResult;
+use clap::{App, Arg, ArgGroup, ArgMatches, SubCommand};
+
+pub fn cli() -> App<'static, 'static> {
+    SubCommand::with_name("switch")
+        .arg(
+            Arg::with_name("create")
+                .short("c")
+                .long("create")
+                .takes_value(true)
+                .value_name("NEW_BRANCH")
+                .help("Create NEW_BRANCH at START, or HEAD, and switch to it."),
+        )
+        .arg(
+            Arg::with_name("force-create")
+                .short("C")
+                .long("force-create")
+                .takes_value(true)
+                .value_name("NEW_BRANCH")
+                .help("Like --create, but reset NEW_BRANCH if it already exists."),
+        )
+        .arg(
+            Arg::with_name("detach")
+                .short("d")
+                .long("detach")
+                .help("Detach HEAD at START, or HEAD, rather than switch to a branch."),
+        )
+        .arg(
+            Arg::with_name("orphan")
+                .long("orphan")
+                .takes_value(true)
+                .value_name("NEW_BRANCH")
+                .help("Switch to a new branch with no history, emptying the index and workspace."),
+        )
+        .group(ArgGroup::with_name("mode").args(&["create", "force-create", "detach", "orphan"]))
+        .arg(
+            Arg::with_name("BRANCH")
+                .index(1)
+                .required_unless_one(&["create", "force-create", "detach", "orphan"])
+                .conflicts_with("orphan")
+                .help("The branch to switch to, '-' for the previous one, or the START of a new branch"),
+        )
+}
+
+pub fn exec(matches: &ArgMatches) -> BoxResult<()> {
+    let root = std::path::Path::new(".");
+    let repository = Repository::new(root)?;
+    let target = matches.value_of("BRANCH");
+
+    if let Some(name) = matches.value_of("orphan") {
+        orphan(repository, name)
+    } else if matches.is_present("detach") {
+        detach(repository, target.unwrap_or("HEAD"))
+    } else if let Some(name) = matches.value_of("create") {
+        create(repository, name, target.unwrap_or("HEAD"), false)
+    } else if let Some(name) = matches.value_of("force-create") {
+        create(repository, name, target.unwrap_or("HEAD"), true)
+    } else {
+        switch(repository, target.unwrap())
+    }
+}
+
+fn fatal(repository: Repository, message: String) -> ! {
+    eprintln!("fatal: {}", message);
+    let _ = repository.commit_changes();
+    std::process::exit(128);
+}
+
+fn resolve_commit(repository: &Repository, revision: &str) -> Option<String> {
+    RevisionResolver::new(&repository.database, &repository.refs, revision)
+        .resolver(ObjectKind::Commit)
+        .ok()
+}
+
+fn switch(mut repository: Repository, target: &str) -> BoxResult<()> {
+    let previous = match target {
+        "-" => repository.refs.previous_checkout(1),
+        _ => Some(target.to_owned()),
+    };
+    let branch = match previous {
+        Some(branch) => branch,
+        None => fatal(repository, String::from("no previous branch to switch to")),
+    };
+    let oid = match repository.refs.read_ref(&format!("refs/heads/{}", branch)) {
+        Some(oid) => oid,
+        None if resolve_commit(&repository, &branch).is_some() => {
+            eprintln!("hint: If you want to detach HEAD at the commit, try again with the --detach option.");
+            fatal(
+                repository,
+                format!("a branch is expected, got commit '{}'", branch),
+            )
+        }
+        None => fatal(repository, format!("invalid reference: {}", branch)),
+    };
+
+    let old_ref = repository.refs.current_ref();
+    let old_oid = repository.refs.get_head();
+    repository = migrate(repository, Some(&oid))?;
+    let message = moving_message(&repository, &branch);
+    repository.refs.set_head(&branch, &oid, &message)?;
+    print_head_change(
+        &repository,
+        &branch,
+        old_ref,
+        old_oid,
+        &oid,
+        BranchChange::Switched,
+    )?;
+    repository.commit_changes()?;
+    Ok(())
+}
+
+fn create(mut repository: Repository, name: &str, start: &str, force: bool) -> BoxResult<()> {
+    let oid = match resolve_commit(&repository, start) {
+        Some(oid) => oid,
+        None => fatal(repository, format!("invalid reference: {}", start)),
+    };
+    let full_name = format!("refs/heads/{}", name);
+    let exists = repository.refs.read_ref(&full_name).is_some();
+    if !(force && exists) {
+        if let Err(e) = repository.refs.check_branch_name(name) {
+            fatal(repository, e.to_string());
+        }
+    }
+
+    let old_ref = repository.refs.current_ref();
+    let old_oid = repository.refs.get_head();
+    repository = migrate(repository, Some(&oid))?;
+    let change = if exists {
+        let message = format!("branch: Reset to {}", start);
+        repository.refs.update_ref(&full_name, &oid, &message)?;
+        BranchChange::Reset
+    } else {
+        let message = format!("branch: Created from {}", start);
+        repository
+            .refs
+            .create_branch(name, Some(oid.clone()), &message)?;
+        BranchChange::Created
+    };
+    let message = moving_message(&repository, name);
+    repository.refs.set_head(name, &oid, &message)?;
+    print_head_change(&repository, name, old_ref, old_oid, &oid, change)?;
+    repository.commit_changes()?;
+    Ok(())
+}
+
+fn detach(mut repository: Repository, target: &str) -> BoxResult<()> {
+    let oid = match resolve_commit(&repository, target) {
+        Some(oid) => oid,
+        None => fatal(repository, format!("invalid reference: {}", target)),
+    };
+
+    let was_detached = repository.refs.is_detached();
+    let old_oid = repository.refs.get_head();
+    repository = migrate(repository, Some(&oid))?;
+    let message = moving_message(&repository, target);
+    repository.refs.detach_head(&oid, &message)?;
+
+    if let Some(old_oid) = old_oid {
+        if was_detached && old_oid != oid {
+            eprintln!(
+                "Previous HEAD position was {}",
+                describe(&repository, &old_oid)
+            );
+        }
+    }
+    eprintln!("HEAD is now at {}", describe(&repository, &oid));
+    repository.commit_changes()?;
+    Ok(())
+}
+
+fn orphan(mut repository: Repository, name: &str) -> BoxResult<()> {
+    if let Err(e) = repository.refs.check_branch_name(name) {
+        fatal(repository, e.to_string());
+    }
+    repository = migrate(repository, None)?;
+    repository.refs.orphan_head(name)?;
+    eprintln!("Switched to a new branch '{}'", name);
+    repository.commit_changes()?;
+    Ok(())
+}
@@ -0,0 +1,215 @@
+use crate::author::Author;
+use crate::database::{Database, ObjectKind, Storable};
+use crate::refs::Refs;
+use crate::revision::RevisionResolver;
+use crate::tag::Tag;
+use crate::utilities::glob_match;
+use crate::BoxResult;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use failure::format_err;
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+
+pub fn cli() -> App<'static, 'static> {
+    SubCommand::with_name("tag")
+        .arg(
+            Arg::with_name("list")
+                .short("l")
+                .long("list")
+                .help("List tags, optionally only those matching PATTERN."),
+        )
+        .arg(
+            Arg::with_name("delete")
+                .short("d")
+                .long("delete")
+                .conflicts_with_all(&["list", "verify"])
+                .help("Delete the named tags."),
+        )
+        .arg(
+            Arg::with_name("verify")
+                .short("v")
+                .long("verify")
+                .conflicts_with("list")
+                .help("Verify the GPG signature of the named tags."),
+        )
+        .arg(
+            Arg::with_name("annotate")
+                .short("a")
+                .long("annotate")
+                .help("Make an annotated tag object."),
+        )
+        .arg(
+            Arg::with_name("message")
+                .short("m")
+                .long("message")
+                .takes_value(true)
+                .help("Use the given message for an annotated tag."),
+        )
+        .arg(
+            Arg::with_name("force")
+                .short("f")
+                .long("force")
+                .help("Replace an existing tag."),
+        )
+        .arg(
+            Arg::with_name("NAME")
+                .index(1)
+                .multiple(true)
+                .help("The tag to create, or the patterns and tags to list, delete or verify"),
+        )
+}
+
+pub fn exec(matches: &ArgMatches) -> BoxResult<()> {
+    let root = std::path::Path::new(".");
+    let db = Database::new(root.join(".git/objects"));
+    let refs = Refs::new(root.join(".git"));
+
+    let names: Vec<&str> = matches.values_of("NAME").into_iter().flatten().collect();
+
+    if matches.is_present("delete") {
+        delete(&db, &refs, &names)
+    } else if matches.is_present("verify") {
+        verify(&db, &refs, &names)
+    } else if matches.is_present("list") || names.is_empty() {
+        list(&refs, &names);
+        Ok(())
+    } else {
+        create(&db, &refs, &names, matches)
+    }
+}
+
+fn list(refs: &Refs, patterns: &[&str]) {
+    let mut tags: Vec<String> = refs
+        .list_tags()
+        .into_iter()
+        .map(|(name, _)| name)
+        .filter(|name| patterns.is_empty() || patterns.iter().any(|p| glob_match(p, name)))
+        .collect();
+    tags.sort();
+    for tag in tags {
+        println!("{}", tag);
+    }
+}
+
+fn create(db: &Database, refs: &Refs, names: &[&str], matches: &ArgMatches) -> BoxResult<()> {
+    let (name, target) = match names {
+        [name] => (*name, "HEAD"),
+        [name, target] => (*name, *target),
+        _ => {
+            eprintln!("fatal: too many params");
+            std::process::exit(128);
+        }
+    };
+
+    let mut rr = RevisionResolver::new(db, refs, target);
+    let mut oid = match rr.resolve_object() {
+        Ok(oid) => oid,
+        Err(_) => {
+            for error in rr.errors {
+                eprintln!("{}", error);
+            }
+            eprintln!("fatal: Failed to resolve '{}' as a valid ref.", target);
+            std::process::exit(128);
+        }
+    };
+
+    if matches.is_present("annotate") || matches.is_present("message") {
+        let mut msg = String::new();
+        let message = match matches.value_of("message") {
+            Some(message) => message,
+            None => {
+                std::io::stdin().lock().read_to_string(&mut msg)?;
+                msg.as_ref()
+            }
+        };
+        let message = format!("{}\n", message.trim_end());
+        let (kind, _, _) = db.read_object(&oid)?;
+        let tag = Tag::new(&oid, kind, name, Author::committer(), &message);
+        oid = tag.oid();
+        db.store(tag)?;
+    }
+
+    match refs.create_tag(name, &oid, matches.is_present("force")) {
+        Ok(Some(old)) if old != oid => {
+            println!("Updated tag '{}' (was {})", name, db.truncate_oid(&old))
+        }
+        Ok(_) => {}
+        Err(e) => {
+            eprintln!("fatal: {}", e);
+            std::process::exit(128);
+        }
+    }
+    Ok(())
+}
+
+fn delete(db: &Database, refs: &Refs, names: &[&str]) -> BoxResult<()> {
+    let mut failed = false;
+    for name in names {
+        match refs.delete_ref(&format!("refs/tags/{}", name))? {
+            Some(oid) => println!("Deleted tag '{}' (was {})", name, db.truncate_oid(&oid)),
+            None => {
+                eprintln!("error: tag '{}' not found.", name);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn verify(db: &Database, refs: &Refs, names: &[&str]) -> BoxResult<()> {
+    let mut failed = false;
+    for name in names {
+        if let Err(e) = verify_tag(db, refs, name) {
+            eprintln!("error: {}", e);
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Checks a tag's signature with `gpg --verify`, which reads the detached
+/// signature from a file and the signed payload from stdin.
+fn verify_tag(db: &Database, refs: &Refs, name: &str) -> BoxResult<()> {
+    let oid = refs
+        .read_ref(&format!("refs/tags/{}", name))
+        .ok_or_else(|| format_err!("tag '{}' not found.", name))?;
+    let (kind, _, data) = db.read_object(&oid)?;
+    if kind != ObjectKind::Tag {
+        return Err(
+            format_err!("{}: cannot verify a non-tag object of type {}.", name, kind).into(),
+        );
+    }
+    let tag = Tag::try_from(data.clone())?;
+    let (payload, signature) = tag
+        .signature()
+        .ok_or_else(|| format_err!("no signature found"))?;
+
+    let sig_path = refs
+        .git_dir()
+        .join(format!(".tmp-vtag-{}", uuid::Uuid::new_v4().to_simple()));
+    std::fs::write(&sig_path, &signature)?;
+    let child = Command::new("gpg")
+        .args(["--verify"])
+        .arg(&sig_path)
+        .arg("-")
+        .stdin(Stdio::piped())
+        .spawn();
+    let status = child.and_then(|mut child| {
+        child.stdin.take().unwrap().write_all(&payload)?;
+        child.wait()
+    });
+    std::fs::remove_file(&sig_path)?;
+
+    if !status?.success() {
+        return Err(format_err!("could not verify the tag '{}'", name).into());
+    }
+    std::io::stdout().write_all(&data)?;
+    Ok(())
+}
@@ -1,68 +1,145 @@
 use crate::author::Author;
 use crate::database::Storable;
-use std::collections::HashMap;
+use failure::format_err;
+use failure::Error;
+use std::borrow::Cow;
 use std::convert::TryFrom;
-use std::fmt::Write;
 
-#[derive(Debug)]
+/// A commit object. Headers git knows about but rit does not use, such as
+/// `encoding`, `mergetag` and `gpgsig`, are kept in order with their
+/// continuation lines so that a parsed commit serializes to the same bytes.
+/// Header values are bytes, as an `encoding` header means they need not be
+/// UTF-8.
+#[derive(Clone, Debug)]
 pub struct Commit {
-    pub parent: Option<String>,
+    pub parents: Vec<String>,
     pub tree: String,
     pub(crate) author: Author,
-    message: String,
+    pub(crate) committer: Author,
+    headers: Headers,
+    message: Vec<u8>,
 }
 
 impl Commit {
-    pub fn new(parent: Option<String>, tree: &str, author: Author, message: &str) -> Self {
+    pub fn new(
+        parents: Vec<String>,
+        tree: &str,
+        author: Author,
+        committer: Author,
+        message: &str,
+    ) -> Self {
         let tree = String::from(tree);
-        let message = String::from(message);
+        let message = message.as_bytes().to_vec();
         Self {
-            parent,
+            parents,
             tree,
             author,
+            committer,
+            headers: vec![],
             message,
         }
     }
+
+    /// The first parent, which is the one history follows by default.
+    pub fn parent(&self) -> Option<&String> {
+        self.parents.first()
+    }
+
+    pub fn is_merge(&self) -> bool {
+        self.parents.len() > 1
+    }
+
+    /// The value of an extra header such as `encoding`, with continuation
+    /// lines joined by newlines.
+    pub fn header(&self, key: &str) -> Option<&[u8]> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_slice())
+    }
+
+    pub fn message(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.message)
+    }
+
     pub fn title_line(&self) -> Option<String> {
-        self.message.lines().nth(0).map(String::from)
+        self.message().lines().next().map(String::from)
+    }
+}
+
+/// Header lines as key and value pairs, in the order they were written.
+pub(crate) type Headers = Vec<(String, Vec<u8>)>;
+
+/// Splits a commit or tag into its headers, with continuation lines joined
+/// by newlines, and the message that follows the first blank line.
+pub(crate) fn parse_headers(data: &[u8]) -> Result<(Headers, Vec<u8>), Error> {
+    let (head, message) = match data.windows(2).position(|w| w == b"\n\n") {
+        Some(pos) => (&data[..pos], data[pos + 2..].to_vec()),
+        None => (data.strip_suffix(b"\n").unwrap_or(data), vec![]),
+    };
+
+    let mut headers: Headers = vec![];
+    for line in head.split(|&b| b == b'\n') {
+        if let Some(continuation) = line.strip_prefix(b" ") {
+            let (_, value) = headers
+                .last_mut()
+                .ok_or_else(|| format_err!("object starts with a continuation line"))?;
+            value.push(b'\n');
+            value.extend_from_slice(continuation);
+        } else {
+            let (key, value) = match line.iter().position(|&b| b == b' ') {
+                Some(pos) => (&line[..pos], &line[pos + 1..]),
+                None => (line, &b""[..]),
+            };
+            headers.push((String::from_utf8(key.to_vec())?, value.to_vec()));
+        }
+    }
+    Ok((headers, message))
+}
+
+/// Writes a header line, continuing a multi-line value on lines that start
+/// with a space.
+pub(crate) fn write_header(content: &mut Vec<u8>, key: &str, value: &[u8]) {
+    content.extend_from_slice(key.as_bytes());
+    content.push(b' ');
+    for (i, line) in value.split(|&b| b == b'\n').enumerate() {
+        if i > 0 {
+            content.extend_from_slice(b"\n ");
+        }
+        content.extend_from_slice(line);
     }
+    content.push(b'\n');
 }
 
 impl TryFrom<Vec<u8>> for Commit {
     type Error = failure::Error;
 
     fn try_from(data: Vec<u8>) -> Result<Self, Self::Error> {
-        let mut headers = HashMap::new();
-        let data = String::from_utf8(data)?;
-        let mut data = data.lines();
-        loop {
-            let line = data.next();
-            if let Some(line) = line {
-                let line = line.trim();
-                if line == "" {
-                    break;
-                }
-                let mut matches = line.split_whitespace();
-                let key = matches.next().unwrap();
-                let val = matches.collect::<Vec<&str>>().join(" ");
-                headers.insert(key, val);
-            }
+        let (headers, message) = parse_headers(&data)?;
+        let mut headers = headers.into_iter().peekable();
+        let tree = match headers.next() {
+            Some((ref key, value)) if key == "tree" => String::from_utf8(value)?,
+            _ => return Err(format_err!("commit does not start with a tree")),
+        };
+        let mut parents = vec![];
+        while let Some((_, parent)) = headers.next_if(|(k, _)| k == "parent") {
+            parents.push(String::from_utf8(parent)?);
         }
-        let message = data.collect::<Vec<&str>>().join("\n");
-        let parent = headers.get("parent").and_then(|x| Some(x.to_string()));
-        let tree = headers
-            .get("tree")
-            .expect("failed to read tree from commit")
-            .to_string();
-        let author = headers
-            .get("author")
-            .map(|x| Author::try_from(x.as_ref()))
-            .expect("failed to read author from commit")
-            .unwrap();
+        let author = match headers.next_if(|(k, _)| k == "author") {
+            Some((_, value)) => Author::try_from(value.as_slice())?,
+            None => return Err(format_err!("failed to read author from commit")),
+        };
+        let committer = match headers.next_if(|(k, _)| k == "committer") {
+            Some((_, value)) => Author::try_from(value.as_slice())?,
+            None => return Err(format_err!("failed to read committer from commit")),
+        };
+
         Ok(Self {
-            parent,
+            parents,
             tree,
             author,
+            committer,
+            headers: headers.collect(),
             message,
         })
     }
@@ -70,17 +147,124 @@ impl TryFrom<Vec<u8>> for Commit {
 
 impl Storable for Commit {
     fn serialize(&self) -> Vec<u8> {
-        let mut content = format!("tree {}\n", self.tree);
-        match &self.parent {
-            Some(p) => writeln!(&mut content, "parent {}", p).unwrap(),
-            None => {}
+        let mut content = vec![];
+        write_header(&mut content, "tree", self.tree.as_bytes());
+        for parent in &self.parents {
+            write_header(&mut content, "parent", parent.as_bytes());
+        }
+        write_header(&mut content, "author", &self.author.to_bytes());
+        write_header(&mut content, "committer", &self.committer.to_bytes());
+        for (key, value) in &self.headers {
+            write_header(&mut content, key, value);
         }
-        write!(
-            &mut content,
-            "author {}\ncommitter {}\n\n{}",
-            self.author, self.author, self.message
-        )
-        .unwrap();
-        format!("commit {}\0{}", content.len(), content).into()
+        content.push(b'\n');
+        content.extend_from_slice(&self.message);
+
+        let mut object = format!("commit {}\0", content.len()).into_bytes();
+        object.extend(content);
+        object
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Commit;
+    use crate::database::Storable;
+    use std::convert::TryFrom;
+
+    // `git hash-object -t commit --literally` gives this the oid checked below
+    const SIGNED: &str = concat!(
+        "tree 4b825dc642cb6eb9a060e54bf8d69288fbee4904\n",
+        "parent 1fc2ec1e1e1e1e1e1e1e1e1e1e1e1e1e1e1e1e1e\n",
+        "parent 2fc2ec1e1e1e1e1e1e1e1e1e1e1e1e1e1e1e1e1e\n",
+        "author A. U. Thor <author@example.com> 1500000000 -0000\n",
+        "committer C. O. Mitter <committer@example.com> 1500003600 +0530\n",
+        "encoding ISO-8859-1\n",
+        "gpgsig -----BEGIN PGP SIGNATURE-----\n",
+        " \n",
+        " iQEzBAABCAAdFiEE\n",
+        " =abcd\n",
+        " -----END PGP SIGNATURE-----\n",
+        "x-unknown  two  spaces\n",
+        "\n",
+        "Merge branch 'side'\n",
+        "\n",
+        "Body line.\n",
+    );
+
+    // written by `git commit -S` with an ssh signing key
+    const GIT_SIGNED: &str = concat!(
+        "tree c49897f29f9819a0ab6850d7e22443508a1a29d5\n",
+        "author A. U. Thor <author@example.com> 1500000000 +0000\n",
+        "committer A. U. Thor <author@example.com> 1500000000 +0000\n",
+        "gpgsig -----BEGIN SSH SIGNATURE-----\n",
+        " U1NIU0lHAAAAAQAAADMAAAALc3NoLWVkMjU1MTkAAAAgMv4NsiYdkkeF83+/EeuPL+XRwW\n",
+        " fut7zAABomhZ6F5q4AAAADZ2l0AAAAAAAAAAZzaGE1MTIAAABTAAAAC3NzaC1lZDI1NTE5\n",
+        " AAAAQARoafxSDcJLnl3qFTVzGBDzEJIj8lKcnOoGtQ0cnDXzeFNJh8lrvpDLZr5CXmEu1I\n",
+        " YLQE2ZBzSOOF211Yxe3QI=\n",
+        " -----END SSH SIGNATURE-----\n",
+        "\n",
+        "Signed commit\n",
+    );
+
+    // an ISO-8859-1 commit, as git writes one with i18n.commitEncoding set
+    const LATIN1: &[u8] = b"tree c49897f29f9819a0ab6850d7e22443508a1a29d5\n\
+        author Ren\xe9 Fran\xe7ois <rene@example.com> 1500000000 +0100\n\
+        committer Ren\xe9 Fran\xe7ois <rene@example.com> 1500000000 +0100\n\
+        encoding ISO-8859-1\n\
+        \n\
+        Caf\xe9 cr\xe8me\n";
+
+    #[test]
+    fn test_round_trip() {
+        let commit = Commit::try_from(SIGNED.as_bytes().to_vec()).unwrap();
+        assert_eq!(commit.parents.len(), 2);
+        assert_eq!(commit.committer.name(), "C. O. Mitter");
+        assert_eq!(commit.header("encoding"), Some(&b"ISO-8859-1"[..]));
+        assert!(commit
+            .header("gpgsig")
+            .unwrap()
+            .ends_with(b"\n=abcd\n-----END PGP SIGNATURE-----"));
+        assert_eq!(
+            commit.title_line(),
+            Some(String::from("Merge branch 'side'"))
+        );
+
+        let expected = format!("commit {}\0{}", SIGNED.len(), SIGNED);
+        assert_eq!(commit.serialize(), expected.as_bytes());
+        assert_eq!(commit.oid(), "ad435b22ca39c8ec8c29e6eb25b63f00d79098fa");
+    }
+
+    #[test]
+    fn test_non_utf8_message() {
+        let mut data = SIGNED.as_bytes().to_vec();
+        data.extend_from_slice(b"caf\xe9\n");
+        let commit = Commit::try_from(data.clone()).unwrap();
+        let mut expected = format!("commit {}\0", data.len()).into_bytes();
+        expected.extend(data);
+        assert_eq!(commit.serialize(), expected);
+    }
+
+    #[test]
+    fn test_git_signed_commit() {
+        let commit = Commit::try_from(GIT_SIGNED.as_bytes().to_vec()).unwrap();
+        assert!(commit
+            .header("gpgsig")
+            .unwrap()
+            .starts_with(b"-----BEGIN SSH SIGNATURE-----\nU1NI"));
+        let expected = format!("commit {}\0{}", GIT_SIGNED.len(), GIT_SIGNED);
+        assert_eq!(commit.serialize(), expected.as_bytes());
+        assert_eq!(commit.oid(), "1e5872ac469d1d50ff153c607efddd44ac294b70");
+    }
+
+    #[test]
+    fn test_non_utf8_headers() {
+        let commit = Commit::try_from(LATIN1.to_vec()).unwrap();
+        assert_eq!(commit.author.name(), "Ren\u{fffd} Fran\u{fffd}ois");
+        assert_eq!(commit.author.email(), "rene@example.com");
+        let mut expected = format!("commit {}\0", LATIN1.len()).into_bytes();
+        expected.extend_from_slice(LATIN1);
+        assert_eq!(commit.serialize(), expected);
+        assert_eq!(commit.oid(), "dd1f3fcbf42b325cf9545c3c808e31942d7ee946");
     }
 }
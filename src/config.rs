@@ -0,0 +1,346 @@
+use crate::lockfile::Lockfile;
+use failure::format_err;
+use failure::Error;
+use std::path::Path;
+
+/// A variable from a git config file. Section and key names are stored in
+/// lower case, as git compares them case-insensitively; subsections are
+/// case-sensitive.
+#[derive(Clone, Debug, PartialEq)]
+struct Variable {
+    section: String,
+    subsection: Option<String>,
+    key: String,
+    value: String,
+    line: usize,
+}
+
+/// A `[section "subsection"]` header and the line it is on.
+#[derive(Clone, Debug, PartialEq)]
+struct Section {
+    name: String,
+    subsection: Option<String>,
+    line: usize,
+}
+
+/// The variables in a git config file such as `.git/config`. The original
+/// lines are kept so that edits leave comments and layout alone.
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    lines: Vec<String>,
+    sections: Vec<Section>,
+    variables: Vec<Variable>,
+}
+
+impl Config {
+    /// A missing file is the same as an empty one.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Config::parse(&text),
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let lines: Vec<String> = text.lines().map(String::from).collect();
+        let mut sections: Vec<Section> = vec![];
+        let mut variables = vec![];
+        for (n, line) in lines.iter().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            let bad_line = || format_err!("bad config line {}", n + 1);
+            if let Some(header) = line.strip_prefix('[') {
+                let header = &header[..header.find(']').ok_or_else(bad_line)?];
+                let (name, subsection) = match header.split_once(' ') {
+                    Some((name, sub)) => {
+                        let sub = sub.trim().trim_matches('"').replace("\\\"", "\"");
+                        (name.to_lowercase(), Some(sub))
+                    }
+                    None => match header.split_once('.') {
+                        // the deprecated [section.subsection] form
+                        Some((name, sub)) => (name.to_lowercase(), Some(sub.to_lowercase())),
+                        None => (header.to_lowercase(), None),
+                    },
+                };
+                sections.push(Section {
+                    name,
+                    subsection,
+                    line: n,
+                });
+                continue;
+            }
+            let section = sections.last().ok_or_else(bad_line)?;
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), parse_value(value)),
+                // a bare key is a boolean set to true
+                None => (line, String::from("true")),
+            };
+            variables.push(Variable {
+                section: section.name.clone(),
+                subsection: section.subsection.clone(),
+                key: key.to_lowercase(),
+                value,
+                line: n,
+            });
+        }
+        Ok(Self {
+            lines,
+            sections,
+            variables,
+        })
+    }
+
+    /// Writes the config back to `path` through a lockfile.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let lock = Lockfile::new(path)?.try_lock()?;
+        for line in &self.lines {
+            lock.write_all(line.as_bytes())?;
+            lock.write_all(b"\n")?;
+        }
+        lock.commit()
+    }
+
+    /// The last value set for a name like `core.abbrev` or
+    /// `branch.master.remote`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        let (section, subsection, key) = split_name(name)?;
+        self.variables
+            .iter()
+            .rev()
+            .find(|v| v.section == section && v.subsection.as_deref() == subsection && v.key == key)
+            .map(|v| v.value.as_str())
+    }
+
+    /// Sets `name` to `value`, replacing the last existing value or adding
+    /// the variable to the end of its section, which is created if needed.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), Error> {
+        let (section, subsection, key) = split_name(name)
+            .ok_or_else(|| format_err!("key does not contain a section: {}", name))?;
+        let line = format!("\t{} = {}", key, quote_value(value));
+        let existing = self.variables.iter().rev().find(|v| {
+            v.section == section && v.subsection.as_deref() == subsection && v.key == key
+        });
+        if let Some(variable) = existing {
+            self.lines[variable.line] = line;
+        } else if let Some(end) = self.section_end(&section, subsection) {
+            self.lines.insert(end, line);
+        } else {
+            self.lines.push(section_header(&section, subsection));
+            self.lines.push(line);
+        }
+        self.reparse()
+    }
+
+    /// Removes every value of `name`, returning whether there were any.
+    pub fn unset(&mut self, name: &str) -> Result<bool, Error> {
+        let (section, subsection, key) = match split_name(name) {
+            Some(parts) => parts,
+            None => return Ok(false),
+        };
+        let lines: Vec<usize> = self
+            .variables
+            .iter()
+            .filter(|v| {
+                v.section == section && v.subsection.as_deref() == subsection && v.key == key
+            })
+            .map(|v| v.line)
+            .collect();
+        self.remove_lines(&lines)
+    }
+
+    /// Removes a section such as `branch.topic` and everything in it,
+    /// returning whether it existed.
+    pub fn remove_section(&mut self, name: &str) -> Result<bool, Error> {
+        let (section, subsection) = split_section(name);
+        let mut lines = vec![];
+        for (i, header) in self.sections.iter().enumerate() {
+            if header.name == section && header.subsection.as_deref() == subsection {
+                let end = self
+                    .sections
+                    .get(i + 1)
+                    .map(|next| next.line)
+                    .unwrap_or_else(|| self.lines.len());
+                lines.extend(header.line..end);
+            }
+        }
+        self.remove_lines(&lines)
+    }
+
+    /// Renames a section such as `branch.topic`, returning whether it
+    /// existed.
+    pub fn rename_section(&mut self, old: &str, new: &str) -> Result<bool, Error> {
+        let (section, subsection) = split_section(old);
+        let (new_section, new_subsection) = split_section(new);
+        let mut renamed = false;
+        for header in &self.sections {
+            if header.name == section && header.subsection.as_deref() == subsection {
+                self.lines[header.line] = section_header(&new_section, new_subsection);
+                renamed = true;
+            }
+        }
+        self.reparse()?;
+        Ok(renamed)
+    }
+
+    /// The line after the last variable of the last matching section.
+    fn section_end(&self, section: &str, subsection: Option<&str>) -> Option<usize> {
+        let header = self
+            .sections
+            .iter()
+            .rev()
+            .find(|s| s.name == section && s.subsection.as_deref() == subsection)?;
+        let last = self
+            .variables
+            .iter()
+            .filter(|v| v.line > header.line)
+            .take_while(|v| v.section == section && v.subsection.as_deref() == subsection)
+            .map(|v| v.line)
+            .last()
+            .unwrap_or(header.line);
+        Some(last + 1)
+    }
+
+    fn remove_lines(&mut self, lines: &[usize]) -> Result<bool, Error> {
+        if lines.is_empty() {
+            return Ok(false);
+        }
+        self.lines = std::mem::take(&mut self.lines)
+            .into_iter()
+            .enumerate()
+            .filter(|(n, _)| !lines.contains(n))
+            .map(|(_, line)| line)
+            .collect();
+        self.reparse()?;
+        Ok(true)
+    }
+
+    fn reparse(&mut self) -> Result<(), Error> {
+        *self = Config::parse(&self.lines.join("\n"))?;
+        Ok(())
+    }
+}
+
+/// Splits `section.subsection.key` into its parts; the subsection may itself
+/// contain dots.
+fn split_name(name: &str) -> Option<(String, Option<&str>, String)> {
+    let (section, rest) = name.split_once('.')?;
+    let (subsection, key) = match rest.rsplit_once('.') {
+        Some((subsection, key)) => (Some(subsection), key),
+        None => (None, rest),
+    };
+    Some((section.to_lowercase(), subsection, key.to_lowercase()))
+}
+
+/// Splits `section.subsection` into its parts.
+fn split_section(name: &str) -> (String, Option<&str>) {
+    match name.split_once('.') {
+        Some((section, subsection)) => (section.to_lowercase(), Some(subsection)),
+        None => (name.to_lowercase(), None),
+    }
+}
+
+fn section_header(section: &str, subsection: Option<&str>) -> String {
+    match subsection {
+        Some(sub) => format!(
+            "[{} \"{}\"]",
+            section,
+            sub.replace('\\', "\\\\").replace('"', "\\\"")
+        ),
+        None => format!("[{}]", section),
+    }
+}
+
+/// Escapes a value, quoting it when it would otherwise lose whitespace or
+/// be cut short by a comment character.
+fn quote_value(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t");
+    if value.trim() != value || value.contains(&['#', ';'][..]) {
+        format!("\"{}\"", escaped)
+    } else {
+        escaped
+    }
+}
+
+/// Strips quotes, escapes and trailing comments from a value.
+fn parse_value(raw: &str) -> String {
+    let mut value = String::new();
+    let mut quoted = false;
+    let mut chars = raw.trim().chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' | ';' if !quoted => break,
+            '\\' => match chars.next() {
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some(c) => value.push(c),
+                None => {}
+            },
+            c => value.push(c),
+        }
+    }
+    if quoted {
+        value
+    } else {
+        value.trim_end().to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+
+    #[test]
+    fn test_get() {
+        let config = Config::parse(
+            "[core]\n\tbare = false\n\tAbbrev = 9 ; comment\n[branch \"topic.x\"]\n\tremote = origin\n\tmerge = \"refs/heads/a#b\"\n[core]\n\tabbrev = 10\n\tfilemode\n",
+        )
+        .unwrap();
+        assert_eq!(config.get("core.abbrev"), Some("10"));
+        assert_eq!(config.get("CORE.bare"), Some("false"));
+        assert_eq!(config.get("core.filemode"), Some("true"));
+        assert_eq!(config.get("branch.topic.x.remote"), Some("origin"));
+        assert_eq!(config.get("branch.topic.x.merge"), Some("refs/heads/a#b"));
+        assert_eq!(config.get("branch.TOPIC.x.remote"), None);
+        assert_eq!(config.get("core"), None);
+    }
+
+    #[test]
+    fn test_rejects_variables_outside_sections() {
+        assert!(Config::parse("abbrev = 7\n").is_err());
+    }
+
+    #[test]
+    fn test_set_and_unset() {
+        let mut config =
+            Config::parse("# user settings\n[core]\n\tbare = false\n[user]\n\tname = x\n").unwrap();
+        config.set("core.abbrev", "9").unwrap();
+        config.set("core.bare", "true").unwrap();
+        config.set("branch.topic.merge", "refs/heads/a;b").unwrap();
+        assert_eq!(config.get("core.abbrev"), Some("9"));
+        assert_eq!(config.get("branch.topic.merge"), Some("refs/heads/a;b"));
+        assert_eq!(
+            config.lines.join("\n"),
+            "# user settings\n[core]\n\tbare = true\n\tabbrev = 9\n[user]\n\tname = x\n[branch \"topic\"]\n\tmerge = \"refs/heads/a;b\""
+        );
+
+        assert!(config
+            .rename_section("branch.topic", "branch.other")
+            .unwrap());
+        assert_eq!(config.get("branch.other.merge"), Some("refs/heads/a;b"));
+        assert!(config.unset("core.abbrev").unwrap());
+        assert!(!config.unset("core.abbrev").unwrap());
+        assert!(config.remove_section("branch.other").unwrap());
+        assert_eq!(
+            config.lines.join("\n"),
+            "# user settings\n[core]\n\tbare = true\n[user]\n\tname = x"
+        );
+    }
+}
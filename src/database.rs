@@ -1,17 +1,25 @@
+use crate::config::Config;
+use crate::database::pack::{Pack, Record};
 use crate::database::tree_diff::{TreeDiff, TreeDifference};
 use failure::format_err;
 use failure::Error;
 use flate2::bufread::ZlibDecoder;
 use flate2::write::ZlibEncoder;
 use flate2::Compression;
-use std::convert::TryFrom;
+use std::cell::{Cell, Ref, RefCell};
 use std::fs::OpenOptions;
 use std::io::{BufRead, BufReader, Read, Write};
 use std::path::{Path, PathBuf};
 
 pub mod marker;
+pub mod pack;
 pub mod tree_diff;
 
+/// Git refuses to abbreviate object ids to fewer characters than this.
+pub const MIN_ABBREV: usize = 4;
+/// The abbreviation length when `core.abbrev` is unset or `auto`.
+pub const DEFAULT_ABBREV: usize = 7;
+
 //macro_rules! parsed_kind {
 //    ($knd:ty: $($k:ty => $s:ident),+) => {
 //        #[derive(Clone, Debug)]
@@ -24,6 +32,7 @@ pub enum ObjectKind {
     Commit,
     Tree,
     Blob,
+    Tag,
 }
 
 impl std::fmt::Display for ObjectKind {
@@ -35,17 +44,20 @@ impl std::fmt::Display for ObjectKind {
                 ObjectKind::Commit => "commit",
                 ObjectKind::Tree => "tree",
                 ObjectKind::Blob => "blob",
+                ObjectKind::Tag => "tag",
             }
         )
     }
 }
 
 impl ObjectKind {
-    pub fn parse(k: &str) -> Self {
+    pub fn parse(k: &str) -> Result<Self, Error> {
         match k {
-            "commit" => ObjectKind::Commit,
-            "tree" => ObjectKind::Tree,
-            _ => ObjectKind::Blob,
+            "commit" => Ok(ObjectKind::Commit),
+            "tree" => Ok(ObjectKind::Tree),
+            "blob" => Ok(ObjectKind::Blob),
+            "tag" => Ok(ObjectKind::Tag),
+            _ => Err(format_err!("invalid object type \"{}\"", k)),
         }
     }
 
@@ -60,20 +72,92 @@ impl ObjectKind {
 #[derive(Clone, Default, Debug)]
 pub struct Database {
     path: PathBuf,
+    packs: RefCell<Option<Vec<Pack>>>,
+    abbrev: Cell<Option<usize>>,
 }
 
 impl Database {
     pub fn new<P: AsRef<Path>>(path: P) -> Self {
         Self {
             path: path.as_ref().to_path_buf(),
+            packs: RefCell::new(None),
+            abbrev: Cell::new(None),
         }
     }
 
     pub fn read_object(&self, oid: &str) -> Result<(ObjectKind, u64, Vec<u8>), Error> {
         let (_, path) = self.object_path(oid)?;
-        if !path.exists() {
-            return Err(format_err!("object {} does not exist", oid));
+        if path.exists() {
+            return self.read_loose(&path);
+        }
+        for pack in self.packs()?.iter() {
+            if let Some(offset) = pack.offset_for(oid) {
+                let (kind, data) = self.read_packed(pack, offset)?;
+                return Ok((kind, data.len() as u64, data));
+            }
+        }
+        Err(format_err!("object {} does not exist", oid))
+    }
+
+    pub fn has_object(&self, oid: &str) -> bool {
+        if let Ok((_, path)) = self.object_path(oid) {
+            if path.exists() {
+                return true;
+            }
+        }
+        self.is_packed(oid)
+    }
+
+    pub fn is_packed(&self, oid: &str) -> bool {
+        self.packs()
+            .map(|packs| packs.iter().any(|p| p.offset_for(oid).is_some()))
+            .unwrap_or(false)
+    }
+
+    pub fn pack_path(&self) -> PathBuf {
+        self.path.join("pack")
+    }
+
+    /// Removes loose objects that are also stored in a pack, returning how
+    /// many were deleted.
+    pub fn prune_packed(&self) -> Result<usize, Error> {
+        let mut pruned = 0;
+        for shard in std::fs::read_dir(&self.path)? {
+            let shard = shard?.path();
+            let prefix = match shard.file_name().and_then(|n| n.to_str()) {
+                Some(p) if p.len() == 2 && shard.is_dir() => p.to_owned(),
+                _ => continue,
+            };
+            for object in std::fs::read_dir(&shard)? {
+                let object = object?.path();
+                let name = object.file_name().and_then(|n| n.to_str()).unwrap_or("");
+                if self.is_packed(&format!("{}{}", prefix, name)) {
+                    std::fs::remove_file(&object)?;
+                    pruned += 1;
+                }
+            }
+            if std::fs::read_dir(&shard)?.next().is_none() {
+                std::fs::remove_dir(&shard)?;
+            }
+        }
+        Ok(pruned)
+    }
+
+    /// Deletes every pack other than `pack-<keep>`.
+    pub fn remove_packs_except(&self, keep: &str) -> Result<(), Error> {
+        let keep = format!("pack-{}", keep);
+        for file in std::fs::read_dir(self.pack_path())? {
+            let file = file?.path();
+            let stem = file.file_stem().and_then(|n| n.to_str()).unwrap_or("");
+            if stem.starts_with("pack-") && stem != keep {
+                std::fs::remove_file(&file)?;
+            }
         }
+        self.reload_packs();
+        Ok(())
+    }
+
+    fn read_loose(&self, path: &Path) -> Result<(ObjectKind, u64, Vec<u8>), Error> {
         let mut out = Vec::new();
         let file = OpenOptions::new().read(true).open(path)?;
         let reader = BufReader::new(file);
@@ -85,7 +169,7 @@ impl Database {
         cursor.read_until(b' ', &mut tp)?;
         let tp = String::from_utf8(tp)?;
         let tp = tp.trim_end_matches(' ');
-        let kind = ObjectKind::parse(tp);
+        let kind = ObjectKind::parse(tp)?;
 
         let mut size = vec![];
         cursor.read_until(b'\0', &mut size)?;
@@ -108,43 +192,127 @@ impl Database {
         self.write(oid, content)
     }
 
+    /// The shortest unambiguous abbreviation of `oid` that is at least
+    /// `core.abbrev` characters long.
     pub fn truncate_oid(&self, oid: &str) -> String {
-        oid.get(0..=6)
-            .map(String::from)
-            .unwrap_or_else(|| String::from(oid))
+        self.abbreviate(oid, self.abbrev_len())
+    }
+
+    /// The shortest prefix of `oid`, at least `len` characters long, that no
+    /// other object in the database shares.
+    pub fn abbreviate(&self, oid: &str, len: usize) -> String {
+        let mut len = len.max(MIN_ABBREV);
+        while len < oid.len() {
+            match self.prefix_match(&oid[..len]) {
+                Ok(ref matches) if matches.len() <= 1 => break,
+                _ => len += 1,
+            }
+        }
+        oid.get(..len).unwrap_or(oid).to_owned()
     }
 
     pub fn prefix_match(&self, name: &str) -> Result<Vec<String>, Error> {
-        if let Ok((dir, _)) = self.object_path(name) {
-            let prefix = &dir.file_name().unwrap().to_str().unwrap();
-            let entries = std::fs::read_dir(&dir)?
-                .map(|f| {
-                    let entry = f.unwrap();
-                    let n = entry.file_name();
-                    let n = n.to_str().expect("failed to get name");
-                    format!("{}{}", prefix, n)
-                })
-                .collect::<Vec<String>>();
-
-            let set = entries
-                .iter()
-                .filter(|&e| e.starts_with(name))
-                .map(String::from)
-                .collect::<Vec<String>>();
+        let mut set = vec![];
+        // like git, only take names of four or more hex digits as oids
+        if name.len() < MIN_ABBREV || !name.bytes().all(|b| b.is_ascii_hexdigit()) {
             return Ok(set);
         }
-        Ok(vec![])
+        if let Ok((dir, _)) = self.object_path(name) {
+            if dir.is_dir() {
+                let prefix = &dir.file_name().unwrap().to_str().unwrap();
+                let entries = std::fs::read_dir(&dir)?
+                    .map(|f| {
+                        let entry = f.unwrap();
+                        let n = entry.file_name();
+                        let n = n.to_str().expect("failed to get name");
+                        format!("{}{}", prefix, n)
+                    })
+                    .collect::<Vec<String>>();
+
+                set.extend(
+                    entries
+                        .iter()
+                        .filter(|&e| e.starts_with(name))
+                        .map(String::from),
+                );
+            }
+        }
+        for pack in self.packs()?.iter() {
+            set.extend(pack.index().prefix_match(name));
+        }
+        set.sort();
+        set.dedup();
+        Ok(set)
+    }
+
+    /// `core.abbrev` from the repository's config, read once.
+    fn abbrev_len(&self) -> usize {
+        if let Some(len) = self.abbrev.get() {
+            return len;
+        }
+        let config = match self.path.parent() {
+            Some(git_dir) => Config::load(git_dir.join("config")).unwrap_or_default(),
+            None => Config::default(),
+        };
+        let len = match config.get("core.abbrev") {
+            Some("no") | Some("false") => 40,
+            Some(len) => len.parse().unwrap_or(DEFAULT_ABBREV),
+            None => DEFAULT_ABBREV,
+        };
+        self.abbrev.set(Some(len));
+        len
+    }
+
+    /// Forgets the cached pack indexes so that packs written or removed since
+    /// they were loaded are noticed.
+    pub fn reload_packs(&self) {
+        self.packs.replace(None);
     }
 
     pub fn tree_diff(&self, a: Option<String>, b: Option<String>) -> TreeDifference {
         let mut td = TreeDiff::new(self);
-        td.compare_oids(&a, &b, Some(&self.path));
+        td.compare_oids(&a, &b, None::<PathBuf>);
         td.changes
     }
 
+    fn packs(&self) -> Result<Ref<'_, Vec<Pack>>, Error> {
+        if self.packs.borrow().is_none() {
+            let mut packs = vec![];
+            let dir = self.pack_path();
+            if dir.is_dir() {
+                let mut idxs = std::fs::read_dir(dir)?
+                    .filter_map(|e| e.ok().map(|e| e.path()))
+                    .filter(|p| p.extension().map(|e| e == "idx").unwrap_or(false))
+                    .collect::<Vec<_>>();
+                idxs.sort();
+                for idx in idxs {
+                    packs.push(Pack::open(idx)?);
+                }
+            }
+            self.packs.replace(Some(packs));
+        }
+        Ok(Ref::map(self.packs.borrow(), |p| {
+            p.as_ref().expect("pack list was just loaded")
+        }))
+    }
+
+    fn read_packed(&self, pack: &Pack, offset: u64) -> Result<(ObjectKind, Vec<u8>), Error> {
+        match pack.read_record(offset)? {
+            Record::Object(kind, data) => Ok((kind, data)),
+            Record::OfsDelta(base, delta) => {
+                let (kind, base) = self.read_packed(pack, base)?;
+                Ok((kind, pack::delta::expand(&base, &delta)?))
+            }
+            Record::RefDelta(base, delta) => {
+                let (kind, _, base) = self.read_object(&base)?;
+                Ok((kind, pack::delta::expand(&base, &delta)?))
+            }
+        }
+    }
+
     fn write(&self, oid: String, content: Vec<u8>) -> Result<(), Error> {
         let (dir, path) = self.object_path(oid.as_ref())?;
-        if path.exists() {
+        if self.has_object(oid.as_ref()) {
             return Ok(());
         }
 
@@ -184,31 +352,80 @@ pub trait Storable {
 
 #[derive(Clone, Debug, Default)]
 pub struct Blob {
-    pub(crate) data: String,
+    pub(crate) data: Vec<u8>,
 }
 
 impl Blob {
-    pub fn new(data: String) -> Self {
+    pub fn new(data: Vec<u8>) -> Self {
         Self { data }
     }
 
-    fn content(&self) -> String {
-        self.data.to_string()
+    pub fn content(&self) -> &[u8] {
+        &self.data
     }
 }
 
-impl TryFrom<Vec<u8>> for Blob {
-    type Error = failure::Error;
-
-    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
-        let data = String::from_utf8(value)?;
-        Ok(Self { data })
+impl From<Vec<u8>> for Blob {
+    fn from(data: Vec<u8>) -> Self {
+        Self { data }
     }
 }
 
 impl Storable for Blob {
     fn serialize(&self) -> Vec<u8> {
-        let s = self.content();
-        format!("blob {}\0{}", s.len(), s).into()
+        let mut out: Vec<u8> = format!("blob {}\0", self.data.len()).into();
+        out.extend_from_slice(&self.data);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Blob, Database, Storable};
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::collections::HashMap;
+    use std::io::Write;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_abbreviate_grows_past_collisions() {
+        let dir = TempDir::new("objects").unwrap();
+        let db = Database::new(dir.path());
+
+        // store blobs until two share a four character prefix
+        let mut prefixes: HashMap<String, String> = HashMap::new();
+        let (a, b) = (0..5000)
+            .find_map(|n| {
+                let blob = Blob::new(n.to_string().into_bytes());
+                let oid = blob.oid();
+                db.store(blob).unwrap();
+                prefixes
+                    .insert(oid[..4].to_owned(), oid.clone())
+                    .map(|other| (oid, other))
+            })
+            .unwrap();
+
+        let short = db.abbreviate(&a, 4);
+        assert!(short.len() > 4);
+        assert!(a.starts_with(&short));
+        assert!(!b.starts_with(&short));
+        assert_eq!(db.prefix_match(&short).unwrap(), vec![a.clone()]);
+        assert_eq!(db.truncate_oid(&a).len(), 7);
+    }
+
+    #[test]
+    fn test_unknown_object_type() {
+        let dir = TempDir::new("objects").unwrap();
+        let db = Database::new(dir.path());
+        let oid = "0123456789012345678901234567890123456789";
+        let (dir, path) = db.object_path(oid).unwrap();
+        std::fs::create_dir_all(dir).unwrap();
+        let mut z = ZlibEncoder::new(Vec::new(), Compression::default());
+        z.write_all(b"blub 3\0abc").unwrap();
+        std::fs::write(path, z.finish().unwrap()).unwrap();
+
+        let err = db.read_object(oid).unwrap_err();
+        assert_eq!(err.to_string(), "invalid object type \"blub\"");
     }
 }
@@ -0,0 +1,128 @@
+use crate::database::ObjectKind;
+use failure::format_err;
+use failure::Error;
+use flate2::bufread::ZlibDecoder;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+pub mod compressor;
+pub mod delta;
+pub mod index;
+pub mod numbers;
+pub mod writer;
+pub mod xdelta;
+
+use index::Index;
+
+pub const SIGNATURE: &[u8; 4] = b"PACK";
+pub const VERSION: u32 = 2;
+
+pub const COMMIT: u8 = 1;
+pub const TREE: u8 = 2;
+pub const BLOB: u8 = 3;
+pub const TAG: u8 = 4;
+pub const OFS_DELTA: u8 = 6;
+pub const REF_DELTA: u8 = 7;
+
+/// A single entry read out of a packfile. Deltas are returned unresolved so
+/// that the caller can find their base, which for a `RefDelta` may live in a
+/// different pack or as a loose object.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Record {
+    Object(ObjectKind, Vec<u8>),
+    OfsDelta(u64, Vec<u8>),
+    RefDelta(String, Vec<u8>),
+}
+
+#[derive(Clone, Debug)]
+pub struct Pack {
+    path: PathBuf,
+    index: Index,
+}
+
+impl Pack {
+    /// Opens the packfile that sits alongside the given `.idx` file.
+    pub fn open<P: AsRef<Path>>(idx: P) -> Result<Self, Error> {
+        let idx = idx.as_ref();
+        let index = Index::open(idx)?;
+        let path = idx.with_extension("pack");
+        if !path.exists() {
+            return Err(format_err!("packfile {} does not exist", path.display()));
+        }
+        Ok(Self { path, index })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn index(&self) -> &Index {
+        &self.index
+    }
+
+    pub fn offset_for(&self, oid: &str) -> Option<u64> {
+        self.index.offset_for(oid)
+    }
+
+    pub fn read_record(&self, offset: u64) -> Result<Record, Error> {
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut reader = BufReader::new(file);
+
+        let (kind, size) = numbers::read_type_and_size(&mut reader)?;
+        match kind {
+            COMMIT | TREE | BLOB | TAG => {
+                let data = inflate(&mut reader, size)?;
+                Ok(Record::Object(kind_from_type(kind)?, data))
+            }
+            OFS_DELTA => {
+                let relative = numbers::read_offset(&mut reader)?;
+                if relative > offset {
+                    return Err(format_err!("delta base offset is out of bounds"));
+                }
+                let data = inflate(&mut reader, size)?;
+                Ok(Record::OfsDelta(offset - relative, data))
+            }
+            REF_DELTA => {
+                let mut oid = [0; 20];
+                reader.read_exact(&mut oid)?;
+                let data = inflate(&mut reader, size)?;
+                Ok(Record::RefDelta(hex::encode(oid), data))
+            }
+            _ => Err(format_err!("unknown pack object type {}", kind)),
+        }
+    }
+}
+
+pub fn kind_from_type(kind: u8) -> Result<ObjectKind, Error> {
+    match kind {
+        COMMIT => Ok(ObjectKind::Commit),
+        TREE => Ok(ObjectKind::Tree),
+        BLOB => Ok(ObjectKind::Blob),
+        TAG => Ok(ObjectKind::Tag),
+        _ => Err(format_err!("unsupported pack object type {}", kind)),
+    }
+}
+
+pub fn type_for_kind(kind: &ObjectKind) -> u8 {
+    match kind {
+        ObjectKind::Commit => COMMIT,
+        ObjectKind::Tree => TREE,
+        ObjectKind::Blob => BLOB,
+        ObjectKind::Tag => TAG,
+    }
+}
+
+fn inflate<R: std::io::BufRead>(reader: &mut R, size: u64) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::with_capacity(size as usize);
+    ZlibDecoder::new(reader).take(size).read_to_end(&mut out)?;
+    if out.len() as u64 != size {
+        return Err(format_err!(
+            "pack object inflated to {} bytes, expected {}",
+            out.len(),
+            size
+        ));
+    }
+    Ok(out)
+}
@@ -0,0 +1,70 @@
+use crate::database::pack::type_for_kind;
+use crate::database::pack::writer::Entry;
+use crate::database::pack::xdelta::XDelta;
+use std::collections::VecDeque;
+
+/// Objects smaller than this are never worth deltifying.
+const MIN_SIZE: usize = 50;
+
+/// Picks delta bases for a set of objects using a sliding window. Objects are
+/// ordered so that those with the same type and file name, and then similar
+/// sizes, sit next to each other, largest first.
+pub struct Compressor {
+    window: usize,
+    depth: usize,
+}
+
+impl Compressor {
+    pub fn new(window: usize, depth: usize) -> Self {
+        Self { window, depth }
+    }
+
+    pub fn compress(&self, entries: &mut [Entry]) {
+        if self.window == 0 || self.depth == 0 {
+            return;
+        }
+        let mut order = (0..entries.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| {
+            let (a, b) = (&entries[a], &entries[b]);
+            type_for_kind(&a.kind)
+                .cmp(&type_for_kind(&b.kind))
+                .then_with(|| a.filename().cmp(&b.filename()))
+                .then_with(|| b.data.len().cmp(&a.data.len()))
+        });
+
+        let mut window: VecDeque<usize> = VecDeque::with_capacity(self.window);
+        for target in order {
+            if entries[target].data.len() >= MIN_SIZE {
+                self.try_delta(entries, &window, target);
+            }
+            if window.len() == self.window {
+                window.pop_front();
+            }
+            window.push_back(target);
+        }
+    }
+
+    fn try_delta(&self, entries: &mut [Entry], window: &VecDeque<usize>, target: usize) {
+        let mut best: Option<(usize, Vec<u8>)> = None;
+        for &source in window.iter().rev() {
+            let (base, object) = (&entries[source], &entries[target]);
+            if base.kind != object.kind || base.depth >= self.depth {
+                continue;
+            }
+            if base.data.len() < MIN_SIZE || base.data.len() / 32 > object.data.len() {
+                continue;
+            }
+            let limit = match best {
+                Some((_, ref delta)) => delta.len() - 1,
+                None => object.data.len() / 2,
+            };
+            if let Some(delta) = XDelta::new(&base.data).compress(&object.data, limit) {
+                best = Some((source, delta));
+            }
+        }
+        if let Some((source, delta)) = best {
+            entries[target].depth = entries[source].depth + 1;
+            entries[target].delta = Some((source, delta));
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use crate::database::pack::numbers;
+use failure::format_err;
+use failure::Error;
+use std::io::{Cursor, Read};
+
+/// Rebuilds an object by applying a git delta to its base.
+pub fn expand(base: &[u8], delta: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut delta = Cursor::new(delta);
+    let source_size = numbers::read_size(&mut delta)?;
+    if source_size != base.len() as u64 {
+        return Err(format_err!(
+            "delta base is {} bytes, expected {}",
+            base.len(),
+            source_size
+        ));
+    }
+    let target_size = numbers::read_size(&mut delta)?;
+    let mut out = Vec::with_capacity(target_size as usize);
+
+    let len = delta.get_ref().len() as u64;
+    while delta.position() < len {
+        let mut op = [0; 1];
+        delta.read_exact(&mut op)?;
+        let op = op[0];
+        if op & 0x80 != 0 {
+            let offset = read_copy_field(&mut delta, op, 0, 4)? as usize;
+            let mut size = read_copy_field(&mut delta, op, 4, 3)? as usize;
+            if size == 0 {
+                size = 0x10000;
+            }
+            let end = offset + size;
+            if end > base.len() {
+                return Err(format_err!("delta copy is out of bounds"));
+            }
+            out.extend_from_slice(&base[offset..end]);
+        } else if op != 0 {
+            let mut insert = vec![0; op as usize];
+            delta.read_exact(&mut insert)?;
+            out.extend_from_slice(&insert);
+        } else {
+            return Err(format_err!("delta contains a reserved opcode"));
+        }
+    }
+
+    if out.len() as u64 != target_size {
+        return Err(format_err!(
+            "delta produced {} bytes, expected {}",
+            out.len(),
+            target_size
+        ));
+    }
+    Ok(out)
+}
+
+/// Copy instructions only carry the bytes of their offset and size that are
+/// non-zero; the low bits of the opcode say which ones are present.
+fn read_copy_field<R: Read>(delta: &mut R, op: u8, first: u8, count: u8) -> Result<u32, Error> {
+    let mut value = 0u32;
+    for i in 0..count {
+        if op & (1 << (first + i)) != 0 {
+            let mut byte = [0; 1];
+            delta.read_exact(&mut byte)?;
+            value |= u32::from(byte[0]) << (8 * i);
+        }
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand;
+
+    #[test]
+    fn test_copy_and_insert() {
+        let base = b"hello world";
+        // source 11, target 13, copy 6 bytes from 0, insert "there!", copy 1 from 5
+        let delta = vec![
+            11, 13, 0x90, 6, 6, b't', b'h', b'e', b'r', b'e', b'!', 0x91, 5, 1,
+        ];
+        assert_eq!(expand(base, &delta).unwrap(), b"hello there! ".to_vec());
+    }
+
+    #[test]
+    fn test_rejects_wrong_base() {
+        let delta = vec![3, 1, 1, b'a'];
+        assert!(expand(b"ab", &delta).is_err());
+    }
+}
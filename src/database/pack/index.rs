@@ -0,0 +1,134 @@
+use byteorder::{BigEndian, ReadBytesExt};
+use failure::format_err;
+use failure::Error;
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+pub const IDX_SIGNATURE: u32 = 0xff74_4f63;
+pub const IDX_VERSION: u32 = 2;
+pub const IDX_MAX_OFFSET: u32 = 0x8000_0000;
+
+/// An in-memory copy of a version 2 `.idx` file, mapping object ids to their
+/// offsets within the matching `.pack`.
+#[derive(Clone, Debug)]
+pub struct Index {
+    fanout: Vec<u32>,
+    oids: Vec<[u8; 20]>,
+    offsets: Vec<u64>,
+}
+
+impl Index {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let data = std::fs::read(path)?;
+        Index::parse(data)
+    }
+
+    pub fn parse(data: Vec<u8>) -> Result<Self, Error> {
+        if data.len() < 8 + 256 * 4 + 40 {
+            return Err(format_err!("pack index is truncated"));
+        }
+        let (body, csum) = data.split_at(data.len() - 20);
+        if sha1::Sha1::from(body).digest().bytes() != csum {
+            return Err(format_err!("pack index checksum does not match"));
+        }
+
+        let mut data = Cursor::new(body);
+        if data.read_u32::<BigEndian>()? != IDX_SIGNATURE {
+            return Err(format_err!("pack index has an unknown signature"));
+        }
+        let version = data.read_u32::<BigEndian>()?;
+        if version != IDX_VERSION {
+            return Err(format_err!("unsupported pack index version {}", version));
+        }
+
+        let mut fanout = Vec::with_capacity(256);
+        for _ in 0..256 {
+            fanout.push(data.read_u32::<BigEndian>()?);
+        }
+        let count = fanout[255] as usize;
+
+        let mut oids = Vec::with_capacity(count);
+        for _ in 0..count {
+            let mut oid = [0; 20];
+            data.read_exact(&mut oid)?;
+            oids.push(oid);
+        }
+
+        // skip the CRC32 table, we trust the pack's own checksum instead
+        data.set_position(data.position() + 4 * count as u64);
+
+        let mut small = Vec::with_capacity(count);
+        for _ in 0..count {
+            small.push(data.read_u32::<BigEndian>()?);
+        }
+        let mut offsets = Vec::with_capacity(count);
+        for offset in small {
+            if offset & IDX_MAX_OFFSET == 0 {
+                offsets.push(u64::from(offset));
+            } else {
+                let pos = 8 + 256 * 4 + 28 * count as u64 + 8 * u64::from(offset & !IDX_MAX_OFFSET);
+                let mut large = Cursor::new(body);
+                large.set_position(pos);
+                offsets.push(large.read_u64::<BigEndian>()?);
+            }
+        }
+
+        Ok(Self {
+            fanout,
+            oids,
+            offsets,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.oids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.oids.is_empty()
+    }
+
+    pub fn oids(&self) -> Vec<String> {
+        self.oids.iter().map(hex::encode).collect()
+    }
+
+    pub fn offset_for(&self, oid: &str) -> Option<u64> {
+        let raw = hex::decode(oid).ok()?;
+        if raw.len() != 20 {
+            return None;
+        }
+        let (start, end) = self.bounds(raw[0]);
+        self.oids[start..end]
+            .binary_search_by(|probe| probe[..].cmp(&raw[..]))
+            .ok()
+            .map(|pos| self.offsets[start + pos])
+    }
+
+    /// Every object in this index whose hex id starts with `prefix`.
+    pub fn prefix_match(&self, prefix: &str) -> Vec<String> {
+        let first = match prefix
+            .get(0..2)
+            .and_then(|b| u8::from_str_radix(b, 16).ok())
+        {
+            Some(b) => b,
+            None => return vec![],
+        };
+        let (start, end) = self.bounds(first);
+        let head = &self.oids[start..end];
+        let from = head.partition_point(|oid| hex::encode(oid).as_str() < prefix);
+        head[from..]
+            .iter()
+            .map(hex::encode)
+            .take_while(|oid| oid.starts_with(prefix))
+            .collect()
+    }
+
+    fn bounds(&self, first: u8) -> (usize, usize) {
+        let start = if first == 0 {
+            0
+        } else {
+            self.fanout[first as usize - 1] as usize
+        };
+        (start, self.fanout[first as usize] as usize)
+    }
+}
@@ -0,0 +1,120 @@
+use failure::Error;
+use std::io::Read;
+
+fn read_byte<R: Read>(reader: &mut R) -> Result<u8, Error> {
+    let mut byte = [0; 1];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+/// Reads the header of a packed object: three bits of type followed by a
+/// variable length size, the first four bits of which share the type byte.
+pub fn read_type_and_size<R: Read>(reader: &mut R) -> Result<(u8, u64), Error> {
+    let mut byte = read_byte(reader)?;
+    let kind = (byte >> 4) & 0x7;
+    let mut size = u64::from(byte & 0xf);
+    let mut shift = 4;
+    while byte & 0x80 != 0 {
+        byte = read_byte(reader)?;
+        size |= u64::from(byte & 0x7f) << shift;
+        shift += 7;
+    }
+    Ok((kind, size))
+}
+
+/// Reads the negative offset that locates the base of an `OFS_DELTA`. Each
+/// continuation byte implicitly adds one so that encodings are unique.
+pub fn read_offset<R: Read>(reader: &mut R) -> Result<u64, Error> {
+    let mut byte = read_byte(reader)?;
+    let mut offset = u64::from(byte & 0x7f);
+    while byte & 0x80 != 0 {
+        byte = read_byte(reader)?;
+        offset = ((offset + 1) << 7) | u64::from(byte & 0x7f);
+    }
+    Ok(offset)
+}
+
+/// Reads a little-endian base-128 size, as used in delta headers.
+pub fn read_size<R: Read>(reader: &mut R) -> Result<u64, Error> {
+    let mut size = 0;
+    let mut shift = 0;
+    loop {
+        let byte = read_byte(reader)?;
+        size |= u64::from(byte & 0x7f) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Ok(size);
+        }
+    }
+}
+
+pub fn write_type_and_size(kind: u8, size: u64) -> Vec<u8> {
+    let mut byte = (kind << 4) | (size & 0xf) as u8;
+    let mut size = size >> 4;
+    let mut out = vec![];
+    while size > 0 {
+        out.push(byte | 0x80);
+        byte = (size & 0x7f) as u8;
+        size >>= 7;
+    }
+    out.push(byte);
+    out
+}
+
+pub fn write_offset(offset: u64) -> Vec<u8> {
+    let mut offset = offset;
+    let mut out = vec![(offset & 0x7f) as u8];
+    offset >>= 7;
+    while offset > 0 {
+        offset -= 1;
+        out.push(0x80 | (offset & 0x7f) as u8);
+        offset >>= 7;
+    }
+    out.reverse();
+    out
+}
+
+pub fn write_size(size: u64) -> Vec<u8> {
+    let mut size = size;
+    let mut out = vec![];
+    loop {
+        let byte = (size & 0x7f) as u8;
+        size >>= 7;
+        if size == 0 {
+            out.push(byte);
+            return out;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_type_and_size_round_trip() {
+        for size in &[0, 15, 16, 1000, 1 << 20] {
+            let bytes = write_type_and_size(3, *size);
+            let parsed = read_type_and_size(&mut Cursor::new(bytes)).unwrap();
+            assert_eq!(parsed, (3, *size));
+        }
+    }
+
+    #[test]
+    fn test_offset_round_trip() {
+        for offset in &[0, 127, 128, 16511, 16512, 1 << 30] {
+            let bytes = write_offset(*offset);
+            assert_eq!(read_offset(&mut Cursor::new(bytes)).unwrap(), *offset);
+        }
+    }
+
+    #[test]
+    fn test_size_round_trip() {
+        for size in &[0, 127, 128, 300, 1 << 24] {
+            let bytes = write_size(*size);
+            assert_eq!(read_size(&mut Cursor::new(bytes)).unwrap(), *size);
+        }
+    }
+}
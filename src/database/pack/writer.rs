@@ -0,0 +1,254 @@
+use crate::commit::Commit;
+use crate::database::pack::compressor::Compressor;
+use crate::database::pack::index::{IDX_MAX_OFFSET, IDX_SIGNATURE, IDX_VERSION};
+use crate::database::pack::{numbers, type_for_kind, OFS_DELTA, SIGNATURE, VERSION};
+use crate::database::{Database, ObjectKind};
+use crate::tag::Tag;
+use crate::tree::{Tree, TreeEntry};
+use byteorder::{BigEndian, WriteBytesExt};
+use failure::format_err;
+use failure::Error;
+use flate2::write::ZlibEncoder;
+use flate2::{Compression, Crc};
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const GITLINK_MODE: &str = "160000";
+
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub oid: String,
+    pub kind: ObjectKind,
+    pub path: Option<PathBuf>,
+    pub(crate) data: Vec<u8>,
+    pub(crate) delta: Option<(usize, Vec<u8>)>,
+    pub(crate) depth: usize,
+    offset: Option<u64>,
+    crc32: u32,
+}
+
+impl Entry {
+    pub fn filename(&self) -> Option<&str> {
+        self.path
+            .as_ref()
+            .and_then(|p| p.file_name())
+            .and_then(|n| n.to_str())
+    }
+}
+
+/// Collects the objects reachable from a set of commits and writes them out
+/// as a version 2 packfile with a matching index.
+pub struct Writer<'a> {
+    db: &'a Database,
+    entries: Vec<Entry>,
+    seen: HashSet<String>,
+    skip_packed: bool,
+}
+
+impl<'a> Writer<'a> {
+    /// When `skip_packed` is set, objects that already live in a pack are left
+    /// out so that only loose objects are gathered up.
+    pub fn new(db: &'a Database, skip_packed: bool) -> Self {
+        Self {
+            db,
+            entries: vec![],
+            seen: HashSet::new(),
+            skip_packed,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn deltas(&self) -> usize {
+        self.entries.iter().filter(|e| e.delta.is_some()).count()
+    }
+
+    pub fn add_reachable(&mut self, oid: &str) -> Result<(), Error> {
+        let mut pending: Vec<(String, Option<PathBuf>)> = vec![(oid.to_owned(), None)];
+        while let Some((oid, path)) = pending.pop() {
+            if !self.seen.insert(oid.clone()) {
+                continue;
+            }
+            let (kind, _, data) = self.db.read_object(&oid)?;
+            match kind {
+                ObjectKind::Commit => {
+                    let commit = Commit::try_from(data.clone())?;
+                    for parent in commit.parents {
+                        pending.push((parent, None));
+                    }
+                    pending.push((commit.tree, None));
+                }
+                ObjectKind::Tree => {
+                    let tree = Tree::try_from(data.clone())?;
+                    for (name, entry) in tree.entries.iter().rev() {
+                        if let TreeEntry::Marker(ref m) = entry {
+                            if m.mode == GITLINK_MODE {
+                                continue;
+                            }
+                        }
+                        let child = match path {
+                            Some(ref p) => p.join(name),
+                            None => PathBuf::from(name),
+                        };
+                        pending.push((entry.oid(), Some(child)));
+                    }
+                }
+                ObjectKind::Blob => {}
+                ObjectKind::Tag => {
+                    let tag = Tag::try_from(data.clone())?;
+                    pending.push((tag.object, None));
+                }
+            }
+            if self.skip_packed && self.db.is_packed(&oid) {
+                continue;
+            }
+            self.entries.push(Entry {
+                oid,
+                kind,
+                path,
+                data,
+                delta: None,
+                depth: 0,
+                offset: None,
+                crc32: 0,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn compress(&mut self, window: usize, depth: usize) {
+        Compressor::new(window, depth).compress(&mut self.entries);
+    }
+
+    /// Writes `pack-<checksum>.pack` and `.idx` into `dir`, returning the
+    /// checksum that names them.
+    pub fn write<P: AsRef<Path>>(&mut self, dir: P) -> Result<String, Error> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+
+        let mut pack = Vec::new();
+        pack.write_all(SIGNATURE)?;
+        pack.write_u32::<BigEndian>(VERSION)?;
+        pack.write_u32::<BigEndian>(self.entries.len() as u32)?;
+        for i in 0..self.entries.len() {
+            self.write_entry(&mut pack, i)?;
+        }
+        let checksum = sha1::Sha1::from(&pack).digest().bytes();
+        pack.write_all(&checksum)?;
+        let name = hex::encode(checksum);
+
+        let index = self.build_index(&checksum)?;
+
+        let stem = dir.join(format!("pack-{}", name));
+        write_file(dir, &stem.with_extension("pack"), &pack)?;
+        write_file(dir, &stem.with_extension("idx"), &index)?;
+        Ok(name)
+    }
+
+    fn write_entry(&mut self, pack: &mut Vec<u8>, i: usize) -> Result<(), Error> {
+        if self.entries[i].offset.is_some() {
+            return Ok(());
+        }
+        if let Some((base, _)) = self.entries[i].delta {
+            self.write_entry(pack, base)?;
+        }
+
+        let offset = pack.len() as u64;
+        let entry = &self.entries[i];
+        let mut record = vec![];
+        let content = match entry.delta {
+            Some((base, ref delta)) => {
+                let base_offset = self.entries[base]
+                    .offset
+                    .ok_or_else(|| format_err!("delta base was not written"))?;
+                record.extend(numbers::write_type_and_size(OFS_DELTA, delta.len() as u64));
+                record.extend(numbers::write_offset(offset - base_offset));
+                delta
+            }
+            None => {
+                let kind = type_for_kind(&entry.kind);
+                record.extend(numbers::write_type_and_size(kind, entry.data.len() as u64));
+                &entry.data
+            }
+        };
+        let mut z = ZlibEncoder::new(Vec::new(), Compression::default());
+        z.write_all(content)?;
+        record.extend(z.finish()?);
+
+        let mut crc = Crc::new();
+        crc.update(&record);
+        pack.extend(record);
+
+        let entry = &mut self.entries[i];
+        entry.offset = Some(offset);
+        entry.crc32 = crc.sum();
+        Ok(())
+    }
+
+    fn build_index(&self, checksum: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut sorted = self.entries.iter().collect::<Vec<_>>();
+        sorted.sort_by(|a, b| a.oid.cmp(&b.oid));
+
+        let mut index = Vec::new();
+        index.write_u32::<BigEndian>(IDX_SIGNATURE)?;
+        index.write_u32::<BigEndian>(IDX_VERSION)?;
+
+        let mut fanout = [0u32; 256];
+        for entry in &sorted {
+            let first = u8::from_str_radix(&entry.oid[0..2], 16)?;
+            for slot in fanout.iter_mut().skip(first as usize) {
+                *slot += 1;
+            }
+        }
+        for count in fanout.iter() {
+            index.write_u32::<BigEndian>(*count)?;
+        }
+
+        for entry in &sorted {
+            index.write_all(&hex::decode(&entry.oid)?)?;
+        }
+        for entry in &sorted {
+            index.write_u32::<BigEndian>(entry.crc32)?;
+        }
+
+        let mut large = vec![];
+        for entry in &sorted {
+            let offset = entry.offset.expect("every entry is written");
+            if offset < u64::from(IDX_MAX_OFFSET) {
+                index.write_u32::<BigEndian>(offset as u32)?;
+            } else {
+                index.write_u32::<BigEndian>(IDX_MAX_OFFSET | large.len() as u32)?;
+                large.push(offset);
+            }
+        }
+        for offset in large {
+            index.write_u64::<BigEndian>(offset)?;
+        }
+
+        index.write_all(checksum)?;
+        let digest = sha1::Sha1::from(&index).digest().bytes();
+        index.write_all(&digest)?;
+        Ok(index)
+    }
+}
+
+fn write_file(dir: &Path, path: &Path, content: &[u8]) -> Result<(), Error> {
+    let tmpnam = dir.join(format!("tmp_pack_{}", uuid::Uuid::new_v4().to_simple()));
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&tmpnam)?;
+    file.write_all(content)?;
+    drop(file);
+    std::fs::rename(tmpnam, path)?;
+    Ok(())
+}
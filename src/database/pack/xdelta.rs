@@ -0,0 +1,150 @@
+use crate::database::pack::numbers;
+use std::collections::HashMap;
+
+const BLOCK_SIZE: usize = 16;
+const MAX_COPY_SIZE: usize = 0x10000;
+const MAX_INSERT_SIZE: usize = 0x7f;
+
+/// Builds git deltas against a single source. The source is indexed in
+/// fixed-size blocks so that it can be reused for several targets.
+pub struct XDelta<'a> {
+    source: &'a [u8],
+    index: HashMap<&'a [u8], Vec<usize>>,
+}
+
+impl<'a> XDelta<'a> {
+    pub fn new(source: &'a [u8]) -> Self {
+        let mut index: HashMap<&[u8], Vec<usize>> = HashMap::new();
+        for offset in (0..source.len() / BLOCK_SIZE).map(|b| b * BLOCK_SIZE) {
+            index
+                .entry(&source[offset..offset + BLOCK_SIZE])
+                .or_default()
+                .push(offset);
+        }
+        Self { source, index }
+    }
+
+    /// Returns the delta that rebuilds `target` from the source, giving up as
+    /// soon as it grows beyond `limit` bytes.
+    pub fn compress(&self, target: &[u8], limit: usize) -> Option<Vec<u8>> {
+        let mut delta = numbers::write_size(self.source.len() as u64);
+        delta.extend(numbers::write_size(target.len() as u64));
+
+        let mut insert: Vec<u8> = vec![];
+        let mut pos = 0;
+        while pos < target.len() {
+            let (start, forward) = self.longest_match(target, pos);
+            if forward < BLOCK_SIZE {
+                insert.push(target[pos]);
+                pos += 1;
+                continue;
+            }
+
+            // pull bytes back out of the pending insert while they still match
+            let mut start = start;
+            let mut len = forward;
+            while start > 0 && insert.last() == Some(&self.source[start - 1]) {
+                insert.pop();
+                start -= 1;
+                len += 1;
+            }
+            flush_insert(&mut delta, &mut insert);
+            emit_copy(&mut delta, start, len);
+            pos += forward;
+            if delta.len() > limit {
+                return None;
+            }
+        }
+        flush_insert(&mut delta, &mut insert);
+        if delta.len() > limit {
+            None
+        } else {
+            Some(delta)
+        }
+    }
+
+    fn longest_match(&self, target: &[u8], pos: usize) -> (usize, usize) {
+        if pos + BLOCK_SIZE > target.len() {
+            return (0, 0);
+        }
+        let mut best = (0, 0);
+        if let Some(offsets) = self.index.get(&target[pos..pos + BLOCK_SIZE]) {
+            for &offset in offsets {
+                let len = self.match_forward(target, pos, offset);
+                if len > best.1 {
+                    best = (offset, len);
+                }
+            }
+        }
+        best
+    }
+
+    fn match_forward(&self, target: &[u8], pos: usize, offset: usize) -> usize {
+        self.source[offset..]
+            .iter()
+            .zip(target[pos..].iter())
+            .take_while(|(a, b)| a == b)
+            .count()
+    }
+}
+
+fn flush_insert(delta: &mut Vec<u8>, insert: &mut Vec<u8>) {
+    for chunk in insert.chunks(MAX_INSERT_SIZE) {
+        delta.push(chunk.len() as u8);
+        delta.extend_from_slice(chunk);
+    }
+    insert.clear();
+}
+
+fn emit_copy(delta: &mut Vec<u8>, offset: usize, len: usize) {
+    let mut offset = offset;
+    let mut len = len;
+    while len > 0 {
+        let size = len.min(MAX_COPY_SIZE);
+        let mut op = 0x80u8;
+        let mut args = vec![];
+        for i in 0..4 {
+            let byte = ((offset >> (8 * i)) & 0xff) as u8;
+            if byte != 0 {
+                op |= 1 << i;
+                args.push(byte);
+            }
+        }
+        for i in 0..3 {
+            let byte = ((size >> (8 * i)) & 0xff) as u8;
+            if byte != 0 {
+                op |= 1 << (4 + i);
+                args.push(byte);
+            }
+        }
+        delta.push(op);
+        delta.extend(args);
+        offset += size;
+        len -= size;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::XDelta;
+    use crate::database::pack::delta::expand;
+
+    #[test]
+    fn test_round_trip() {
+        let source: Vec<u8> = (0..2000).map(|i| (i * 7 % 251) as u8).collect();
+        let mut target = source.clone();
+        target.splice(500..510, b"something new".iter().cloned());
+        target.extend_from_slice(b"trailing bytes");
+
+        let delta = XDelta::new(&source).compress(&target, usize::MAX).unwrap();
+        assert!(delta.len() < 100);
+        assert_eq!(expand(&source, &delta).unwrap(), target);
+    }
+
+    #[test]
+    fn test_respects_limit() {
+        let source = vec![0u8; 64];
+        let target: Vec<u8> = (0..64).map(|i| i as u8).collect();
+        assert!(XDelta::new(&source).compress(&target, 32).is_none());
+    }
+}
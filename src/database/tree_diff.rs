@@ -1,5 +1,6 @@
 use crate::commit::Commit;
 use crate::database::{Database, ObjectKind, Storable};
+use crate::tag::Tag;
 use crate::tree::{Tree, TreeEntry};
 use failure::format_err;
 use failure::Error;
@@ -74,7 +75,7 @@ impl<'a> TreeDiff<'a> {
                 (Some(entry), other)
             } else if a_oid.is_none() {
                 (Some(entry), None)
-            } else if b_oid.is_none() {
+            } else if b_oid.is_none() && other.is_some() {
                 (None, other)
             } else {
                 continue;
@@ -123,6 +124,10 @@ impl<'a> TreeDiff<'a> {
                     self.oid_to_tree(&Some(c.tree))
                 }
                 ObjectKind::Tree => Tree::try_from(data),
+                ObjectKind::Tag => {
+                    let t = Tag::try_from(data)?;
+                    self.oid_to_tree(&Some(t.object))
+                }
                 _ => unreachable!(),
             }
         } else {
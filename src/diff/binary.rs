@@ -0,0 +1,109 @@
+use crate::database::pack::xdelta::XDelta;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::Write;
+
+/// How much of a file is inspected when guessing whether it is binary.
+const FIRST_FEW_BYTES: usize = 8000;
+const BYTES_PER_LINE: usize = 52;
+const BASE85: &[u8; 85] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz!#$%&()*+-;<=>?@^_`{|}~";
+
+/// Git's heuristic: anything with a NUL byte near the start is binary.
+pub fn is_binary(data: &[u8]) -> bool {
+    data.iter().take(FIRST_FEW_BYTES).any(|&b| b == 0)
+}
+
+/// Builds the body of a `GIT binary patch`: a forward hunk turning `a` into
+/// `b` followed by a reverse hunk, each either a literal or a delta,
+/// whichever deflates smaller.
+pub fn patch(a: &[u8], b: &[u8]) -> String {
+    let mut out = String::from("GIT binary patch\n");
+    out.push_str(&hunk(a, b));
+    out.push_str(&hunk(b, a));
+    out
+}
+
+fn hunk(from: &[u8], to: &[u8]) -> String {
+    let literal = deflate(to);
+    let delta = if from.is_empty() || to.is_empty() {
+        None
+    } else {
+        XDelta::new(from)
+            .compress(to, usize::MAX)
+            .map(|d| (d.len(), deflate(&d)))
+            .filter(|(_, d)| d.len() < literal.len())
+    };
+
+    let (header, data) = match delta {
+        Some((size, data)) => (format!("delta {}", size), data),
+        None => (format!("literal {}", to.len()), literal),
+    };
+    let mut out = header;
+    out.push('\n');
+    for line in data.chunks(BYTES_PER_LINE) {
+        out.push(line_length(line.len()));
+        out.push_str(&encode_base85(line));
+        out.push('\n');
+    }
+    out.push('\n');
+    out
+}
+
+fn line_length(len: usize) -> char {
+    if len <= 26 {
+        (b'A' + len as u8 - 1) as char
+    } else {
+        (b'a' + len as u8 - 27) as char
+    }
+}
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut z = ZlibEncoder::new(Vec::new(), Compression::best());
+    z.write_all(data).expect("writing to a Vec cannot fail");
+    z.finish().expect("writing to a Vec cannot fail")
+}
+
+/// Encodes in groups of four bytes, zero-padding the final group.
+pub fn encode_base85(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len() / 4 * 5 + 5);
+    for chunk in data.chunks(4) {
+        let mut word = 0u32;
+        for i in 0..4 {
+            word = (word << 8) | u32::from(*chunk.get(i).unwrap_or(&0));
+        }
+        let mut encoded = [0u8; 5];
+        for slot in encoded.iter_mut().rev() {
+            *slot = BASE85[(word % 85) as usize];
+            word /= 85;
+        }
+        out.push_str(std::str::from_utf8(&encoded).unwrap());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_nul_bytes() {
+        assert!(is_binary(b"PNG\0\x01"));
+        assert!(!is_binary("plain text\nwith lines\n".as_bytes()));
+    }
+
+    #[test]
+    fn test_base85() {
+        assert_eq!(encode_base85(b"\0\0\0\0"), "00000");
+        assert_eq!(encode_base85(&[0xff, 0xff, 0xff, 0xff]), "|NsC0");
+        assert_eq!(encode_base85(b"a"), "VE_OC");
+    }
+
+    #[test]
+    fn test_line_length() {
+        assert_eq!(line_length(1), 'A');
+        assert_eq!(line_length(26), 'Z');
+        assert_eq!(line_length(27), 'a');
+        assert_eq!(line_length(52), 'z');
+    }
+}
@@ -0,0 +1,321 @@
+use crate::diff::edit::Line;
+use crate::diff::myers::Myers;
+use std::collections::HashMap;
+
+const MARKER_SIZE: usize = 7;
+
+/// A line with its ending, as the bytes the file holds.
+type Lines = Vec<Vec<u8>>;
+
+/// A stretch of a merged file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Chunk {
+    /// Lines both sides agree on, or that only one side changed.
+    Clean(Lines),
+    /// Lines the two sides changed differently, along with the base's.
+    Conflict {
+        base: Lines,
+        ours: Lines,
+        theirs: Lines,
+    },
+}
+
+/// Two versions of a file merged line by line against their common base.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Merged {
+    pub chunks: Vec<Chunk>,
+}
+
+impl Merged {
+    pub fn is_clean(&self) -> bool {
+        self.conflicts() == 0
+    }
+
+    pub fn conflicts(&self) -> usize {
+        self.chunks
+            .iter()
+            .filter(|c| matches!(c, Chunk::Conflict { .. }))
+            .count()
+    }
+
+    /// The merged text, with each conflict between markers naming the sides
+    /// it came from. Given a `base` label, conflicts also show the base's
+    /// lines, as git's diff3 style does.
+    pub fn render(&self, ours: &str, theirs: &str, base: Option<&str>) -> Vec<u8> {
+        let mut out = vec![];
+        for chunk in &self.chunks {
+            match chunk {
+                Chunk::Clean(lines) => out.extend(lines.iter().flatten()),
+                Chunk::Conflict {
+                    base: base_lines,
+                    ours: our_lines,
+                    theirs: their_lines,
+                } => {
+                    push_marker(&mut out, b'<', ours);
+                    push_lines(&mut out, our_lines);
+                    if let Some(base) = base {
+                        push_marker(&mut out, b'|', base);
+                        push_lines(&mut out, base_lines);
+                    }
+                    push_marker(&mut out, b'=', "");
+                    push_lines(&mut out, their_lines);
+                    push_marker(&mut out, b'>', theirs);
+                }
+            }
+        }
+        out
+    }
+}
+
+fn push_marker(out: &mut Vec<u8>, marker: u8, label: &str) {
+    out.extend_from_slice(&[marker; MARKER_SIZE]);
+    if !label.is_empty() {
+        out.push(b' ');
+        out.extend_from_slice(label.as_bytes());
+    }
+    out.push(b'\n');
+}
+
+/// Adds a side of a conflict, ending it with a newline so that the marker
+/// after it starts a line.
+fn push_lines(out: &mut Vec<u8>, lines: &[Vec<u8>]) {
+    out.extend(lines.iter().flatten());
+    if !out.ends_with(b"\n") {
+        out.push(b'\n');
+    }
+}
+
+/// Merges the changes `ours` and `theirs` each make to `base`. Lines keep
+/// their endings, so a file's missing final newline survives the merge, and
+/// are compared as bytes, so text in any encoding merges unchanged.
+pub fn merge(base: &[u8], ours: &[u8], theirs: &[u8]) -> Merged {
+    let o = split_lines(base);
+    let a = split_lines(ours);
+    let b = split_lines(theirs);
+    let match_a = matching_lines(&o, &a);
+    let match_b = matching_lines(&o, &b);
+    Diff3 {
+        o,
+        a,
+        b,
+        match_a,
+        match_b,
+        line_o: 0,
+        line_a: 0,
+        line_b: 0,
+        chunks: vec![],
+    }
+    .generate()
+}
+
+fn split_lines(text: &[u8]) -> Lines {
+    text.split_inclusive(|&b| b == b'\n')
+        .map(<[u8]>::to_vec)
+        .collect()
+}
+
+/// The lines of `base` that `other` keeps, mapped to where they are in
+/// `other`. Both are numbered from one.
+fn matching_lines(base: &[Vec<u8>], other: &[Vec<u8>]) -> HashMap<usize, usize> {
+    let numbered = |lines: &[Vec<u8>]| {
+        lines
+            .iter()
+            .enumerate()
+            .map(|(number, content)| Line {
+                content: content.clone(),
+                number,
+            })
+            .collect()
+    };
+    Myers::from_lines(numbered(base), numbered(other))
+        .diff()
+        .into_iter()
+        .filter(|edit| edit.is_equals())
+        .filter_map(|edit| Some((edit.a?.number + 1, edit.b?.number + 1)))
+        .collect()
+}
+
+/// Walks the base and both sides together, cutting them into chunks at the
+/// lines all three share.
+struct Diff3 {
+    o: Lines,
+    a: Lines,
+    b: Lines,
+    match_a: HashMap<usize, usize>,
+    match_b: HashMap<usize, usize>,
+    line_o: usize,
+    line_a: usize,
+    line_b: usize,
+    chunks: Vec<Chunk>,
+}
+
+impl Diff3 {
+    fn generate(mut self) -> Merged {
+        loop {
+            match self.find_next_mismatch() {
+                Some(1) => match self.find_next_match() {
+                    Some((o, a, b)) => self.emit_chunk(o, a, b),
+                    None => break,
+                },
+                Some(i) => self.emit_chunk(self.line_o + i, self.line_a + i, self.line_b + i),
+                None => break,
+            }
+        }
+        self.emit_final_chunk();
+        Merged {
+            chunks: self.chunks,
+        }
+    }
+
+    /// How far past the current lines the first one that is not the same
+    /// in all three is, if there is one.
+    fn find_next_mismatch(&self) -> Option<usize> {
+        let mut i = 1;
+        while self.in_bounds(i)
+            && self.match_a.get(&(self.line_o + i)) == Some(&(self.line_a + i))
+            && self.match_b.get(&(self.line_o + i)) == Some(&(self.line_b + i))
+        {
+            i += 1;
+        }
+        if self.in_bounds(i) {
+            Some(i)
+        } else {
+            None
+        }
+    }
+
+    fn in_bounds(&self, i: usize) -> bool {
+        self.line_o + i <= self.o.len()
+            || self.line_a + i <= self.a.len()
+            || self.line_b + i <= self.b.len()
+    }
+
+    /// The next base line that both sides kept, with where it is in each.
+    fn find_next_match(&self) -> Option<(usize, usize, usize)> {
+        (self.line_o + 1..=self.o.len()).find_map(|o| {
+            let a = self.match_a.get(&o)?;
+            let b = self.match_b.get(&o)?;
+            Some((o, *a, *b))
+        })
+    }
+
+    /// Emits everything before the given lines, which all three share.
+    fn emit_chunk(&mut self, o: usize, a: usize, b: usize) {
+        let chunk = (
+            self.o[self.line_o..o - 1].to_vec(),
+            self.a[self.line_a..a - 1].to_vec(),
+            self.b[self.line_b..b - 1].to_vec(),
+        );
+        self.write_chunk(chunk);
+        self.line_o = o - 1;
+        self.line_a = a - 1;
+        self.line_b = b - 1;
+    }
+
+    fn emit_final_chunk(&mut self) {
+        let chunk = (
+            self.o[self.line_o..].to_vec(),
+            self.a[self.line_a..].to_vec(),
+            self.b[self.line_b..].to_vec(),
+        );
+        self.write_chunk(chunk);
+    }
+
+    fn write_chunk(&mut self, (o, a, b): (Lines, Lines, Lines)) {
+        let chunk = if a == o || a == b {
+            Chunk::Clean(b)
+        } else if b == o {
+            Chunk::Clean(a)
+        } else {
+            Chunk::Conflict {
+                base: o,
+                ours: a,
+                theirs: b,
+            }
+        };
+        if chunk != Chunk::Clean(vec![]) {
+            self.chunks.push(chunk);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge, Merged};
+
+    fn render(merged: &Merged, ours: &str, theirs: &str, base: Option<&str>) -> String {
+        String::from_utf8(merged.render(ours, theirs, base)).unwrap()
+    }
+
+    #[test]
+    fn test_merges_changes_to_different_lines() {
+        let merged = merge(b"a\nb\nc\n", b"d\nb\nc\n", b"a\nb\ne\n");
+        assert!(merged.is_clean());
+        assert_eq!(render(&merged, "ours", "theirs", None), "d\nb\ne\n");
+    }
+
+    #[test]
+    fn test_merges_the_same_change() {
+        let merged = merge(b"a\nb\nc\n", b"d\nb\nc\n", b"d\nb\nc\n");
+        assert!(merged.is_clean());
+        assert_eq!(render(&merged, "ours", "theirs", None), "d\nb\nc\n");
+    }
+
+    #[test]
+    fn test_conflicting_changes() {
+        let merged = merge(b"a\nb\nc\n", b"d\nb\nc\n", b"e\nb\nc\n");
+        assert_eq!(merged.conflicts(), 1);
+        assert_eq!(
+            render(&merged, "left", "right", None),
+            "<<<<<<< left\nd\n=======\ne\n>>>>>>> right\nb\nc\n"
+        );
+        assert_eq!(
+            render(&merged, "left", "right", Some("base")),
+            "<<<<<<< left\nd\n||||||| base\na\n=======\ne\n>>>>>>> right\nb\nc\n"
+        );
+    }
+
+    #[test]
+    fn test_conflicting_additions() {
+        let merged = merge(b"", b"d\n", b"e\n");
+        assert_eq!(
+            render(&merged, "", "", None),
+            "<<<<<<<\nd\n=======\ne\n>>>>>>>\n"
+        );
+    }
+
+    #[test]
+    fn test_counts_separate_conflicts() {
+        let base = "a\nb\nc\nd\ne\n";
+        let merged = merge(base.as_bytes(), b"1\nb\nc\nd\n2\n", b"3\nb\nc\nd\n4\n");
+        assert_eq!(merged.conflicts(), 2);
+
+        let merged = merge(base.as_bytes(), b"a\nb\nx\nd\ne\n", b"a\nb\nc\nd\ne\nf\n");
+        assert_eq!(
+            render(&merged, "ours", "theirs", None),
+            "a\nb\nx\nd\ne\nf\n"
+        );
+    }
+
+    #[test]
+    fn test_keeps_missing_final_newline() {
+        let merged = merge(b"a\nb\nc", b"x\nb\nc", b"a\nb\ny");
+        assert_eq!(render(&merged, "ours", "theirs", None), "x\nb\ny");
+
+        let merged = merge(b"a", b"b", b"c");
+        assert_eq!(
+            render(&merged, "ours", "theirs", None),
+            "<<<<<<< ours\nb\n=======\nc\n>>>>>>> theirs\n"
+        );
+    }
+
+    #[test]
+    fn test_keeps_non_utf8_lines() {
+        let merged = merge(b"caf\xe9\nb\nc\n", b"caf\xe9\nb\nd\n", b"th\xe9\nb\nc\n");
+        assert!(merged.is_clean());
+        assert_eq!(
+            merged.render("ours", "theirs", None),
+            b"th\xe9\nb\nd\n".to_vec()
+        );
+    }
+}
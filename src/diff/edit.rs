@@ -1,5 +1,8 @@
-use colored::Colorize;
+use colored::control::SHOULD_COLORIZE;
+use colored::{Color, Colorize};
+use std::borrow::Cow;
 use std::fmt;
+use std::io::{self, Write};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum EditKind {
@@ -8,12 +11,20 @@ pub enum EditKind {
     Equals,
 }
 
+/// A line of a file, which need not be UTF-8 text.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Line {
-    pub content: String,
+    pub content: Vec<u8>,
     pub number: usize,
 }
 
+impl Line {
+    /// The line as text, for display.
+    pub fn text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.content)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Edit {
     pub kind: EditKind,
@@ -26,17 +37,17 @@ impl fmt::Display for Edit {
         match self.kind {
             EditKind::Equals => {
                 if let Some(ref l) = self.a {
-                    write!(f, " {}", l.content)?;
+                    write!(f, " {}", l.text())?;
                 }
             }
             EditKind::Insert => {
                 if let Some(ref l) = self.b {
-                    write!(f, "{}", format!("+{}", l.content).green())?;
+                    write!(f, "{}", format!("+{}", l.text()).green())?;
                 }
             }
             EditKind::Delete => {
                 if let Some(ref l) = self.a {
-                    write!(f, "{}", format!("-{}", l.content).red())?;
+                    write!(f, "{}", format!("-{}", l.text()).red())?;
                 }
             }
         }
@@ -67,6 +78,30 @@ impl Edit {
         }
     }
 
+    /// Writes the edit as a line of a patch, with the line's bytes as they
+    /// are rather than as text, which would mangle anything not UTF-8.
+    pub fn write_to<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let (sigil, line, color) = match self.kind {
+            EditKind::Equals => (b' ', &self.a, None),
+            EditKind::Insert => (b'+', &self.b, Some(Color::Green)),
+            EditKind::Delete => (b'-', &self.a, Some(Color::Red)),
+        };
+        let line = match line {
+            Some(line) => line,
+            None => return Ok(()),
+        };
+        let color = color.filter(|_| SHOULD_COLORIZE.should_colorize());
+        if let Some(color) = color {
+            write!(out, "\x1B[{}m", color.to_fg_str())?;
+        }
+        out.write_all(&[sigil])?;
+        out.write_all(&line.content)?;
+        if color.is_some() {
+            out.write_all(b"\x1B[0m")?;
+        }
+        out.write_all(b"\n")
+    }
+
     pub fn is_equals(&self) -> bool {
         match self.kind {
             EditKind::Equals => true,
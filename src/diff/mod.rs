@@ -1,3 +1,5 @@
+pub mod binary;
+pub mod diff3;
 pub mod edit;
 pub mod hunk;
 pub mod myers;
@@ -13,23 +13,16 @@ pub(crate) struct Myers {
 }
 
 impl Myers {
-    pub fn from(a: &str, b: &str) -> Self {
-        let a = a
-            .lines()
-            .enumerate()
-            .map(|(num, text)| Line {
-                content: text.to_owned(),
-                number: num,
-            })
-            .collect::<Vec<Line>>();
-        let b = b
-            .lines()
-            .enumerate()
-            .map(|(num, text)| Line {
-                content: text.to_owned(),
-                number: num,
-            })
-            .collect::<Vec<Line>>();
+    pub fn from(a: &[u8], b: &[u8]) -> Self {
+        Myers {
+            a: split_lines(a),
+            b: split_lines(b),
+        }
+    }
+
+    /// Diffs lines that are already split, for callers that need to keep
+    /// their line endings.
+    pub fn from_lines(a: Vec<Line>, b: Vec<Line>) -> Self {
         Myers { a, b }
     }
 
@@ -155,6 +148,22 @@ impl Myers {
     }
 }
 
+/// Splits `data` into lines the way `str::lines` does, without needing it
+/// to be UTF-8.
+fn split_lines(data: &[u8]) -> Vec<Line> {
+    if data.is_empty() {
+        return vec![];
+    }
+    let data = data.strip_suffix(b"\n").unwrap_or(data);
+    data.split(|&b| b == b'\n')
+        .enumerate()
+        .map(|(number, text)| Line {
+            content: text.strip_suffix(b"\r").unwrap_or(text).to_vec(),
+            number,
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::Myers;
@@ -165,8 +174,8 @@ mod tests {
 
     #[test]
     fn test_no_edit() {
-        let a = "A\n";
-        let b = "A\n";
+        let a = b"A\n";
+        let b = b"A\n";
         let algo = Myers::from(a, b);
         let vals = algo.shortest_edit();
         let expected = MyersGraph::from(vec![None, None, None, Some(0), None]);
@@ -178,8 +187,8 @@ mod tests {
 
     #[test]
     fn test_one_edit() {
-        let a = "A\n";
-        let b = "B\n";
+        let a = b"A\n";
+        let b = b"B\n";
         let algo = Myers::from(a, b);
         let vals = algo.shortest_edit();
         let expected = MyersGraph::from(vec![None, Some(0), Some(0), Some(1), None]);
@@ -191,8 +200,8 @@ mod tests {
 
     #[test]
     fn test_an_edit() {
-        let a = "A\nB\nA\n";
-        let b = "B\nB\nB\n";
+        let a = b"A\nB\nA\n";
+        let b = b"B\nB\nB\n";
         let algo = Myers::from(a, b);
         let vals = algo.shortest_edit();
         let expected = vec![
@@ -278,8 +287,8 @@ mod tests {
     }
     #[test]
     fn test_shortest_edit() {
-        let a = "A\nB\nC\nA\nB\nB\nA\n";
-        let b = "C\nB\nA\nB\nA\nC\n";
+        let a = b"A\nB\nC\nA\nB\nB\nA\n";
+        let b = b"C\nB\nA\nB\nA\nC\n";
         let algo = Myers::from(a, b);
         let vals = algo.shortest_edit();
         dbg!(&vals);
@@ -319,8 +328,8 @@ mod tests {
 
     #[test]
     fn test_diff() {
-        let a = "A\nB\nC\nA\nB\nB\nA\n";
-        let b = "C\nB\nA\nB\nA\nC\n";
+        let a = b"A\nB\nC\nA\nB\nB\nA\n";
+        let b = b"C\nB\nA\nB\nA\nC\n";
         let algo = Myers::from(a, b);
         let vals = algo.diff();
 
@@ -328,7 +337,7 @@ mod tests {
             Edit {
                 kind: Delete,
                 a: Some(Line {
-                    content: "A".to_owned(),
+                    content: b"A".to_vec(),
                     number: 0,
                 }),
                 b: None,
@@ -336,7 +345,7 @@ mod tests {
             Edit {
                 kind: Delete,
                 a: Some(Line {
-                    content: "B".to_owned(),
+                    content: b"B".to_vec(),
                     number: 1,
                 }),
                 b: None,
@@ -344,11 +353,11 @@ mod tests {
             Edit {
                 kind: Equals,
                 a: Some(Line {
-                    content: "C".to_owned(),
+                    content: b"C".to_vec(),
                     number: 2,
                 }),
                 b: Some(Line {
-                    content: "C".to_owned(),
+                    content: b"C".to_vec(),
                     number: 0,
                 }),
             },
@@ -356,36 +365,36 @@ mod tests {
                 kind: Insert,
                 a: None,
                 b: Some(Line {
-                    content: "B".to_owned(),
+                    content: b"B".to_vec(),
                     number: 1,
                 }),
             },
             Edit {
                 kind: Equals,
                 a: Some(Line {
-                    content: "A".to_owned(),
+                    content: b"A".to_vec(),
                     number: 3,
                 }),
                 b: Some(Line {
-                    content: "A".to_owned(),
+                    content: b"A".to_vec(),
                     number: 2,
                 }),
             },
             Edit {
                 kind: Equals,
                 a: Some(Line {
-                    content: "B".to_owned(),
+                    content: b"B".to_vec(),
                     number: 4,
                 }),
                 b: Some(Line {
-                    content: "B".to_owned(),
+                    content: b"B".to_vec(),
                     number: 3,
                 }),
             },
             Edit {
                 kind: Delete,
                 a: Some(Line {
-                    content: "B".to_owned(),
+                    content: b"B".to_vec(),
                     number: 5,
                 }),
                 b: None,
@@ -393,11 +402,11 @@ mod tests {
             Edit {
                 kind: Equals,
                 a: Some(Line {
-                    content: "A".to_owned(),
+                    content: b"A".to_vec(),
                     number: 6,
                 }),
                 b: Some(Line {
-                    content: "A".to_owned(),
+                    content: b"A".to_vec(),
                     number: 4,
                 }),
             },
@@ -405,7 +414,7 @@ mod tests {
                 kind: Insert,
                 a: None,
                 b: Some(Line {
-                    content: "C".to_owned(),
+                    content: b"C".to_vec(),
                     number: 5,
                 }),
             },
@@ -415,8 +424,8 @@ mod tests {
 
     #[test]
     fn test_lopsided_diff() {
-        let a = "A\nB\nC\nA\nB\nB\nA\n";
-        let b = "C\nB\nA\nB\nA\nC\nC\nB\nA\nB\nA\nC\nC\nB\nA\nB\nA\nC\nC\nB\nA\nB\nA\nC\nC\nB\nA\nB\nA\nC\n";
+        let a = b"A\nB\nC\nA\nB\nB\nA\n";
+        let b = b"C\nB\nA\nB\nA\nC\nC\nB\nA\nB\nA\nC\nC\nB\nA\nB\nA\nC\nC\nB\nA\nB\nA\nC\nC\nB\nA\nB\nA\nC\n";
         let algo = Myers::from(a, b);
         let vals = algo.diff();
 
@@ -425,7 +434,7 @@ mod tests {
                 kind: Insert,
                 a: None,
                 b: Some(Line {
-                    content: "C".to_owned(),
+                    content: b"C".to_vec(),
                     number: 0,
                 }),
             },
@@ -433,29 +442,29 @@ mod tests {
                 kind: Insert,
                 a: None,
                 b: Some(Line {
-                    content: "B".to_owned(),
+                    content: b"B".to_vec(),
                     number: 1,
                 }),
             },
             Edit {
                 kind: Equals,
                 a: Some(Line {
-                    content: "A".to_owned(),
+                    content: b"A".to_vec(),
                     number: 0,
                 }),
                 b: Some(Line {
-                    content: "A".to_owned(),
+                    content: b"A".to_vec(),
                     number: 2,
                 }),
             },
             Edit {
                 kind: Equals,
                 a: Some(Line {
-                    content: "B".to_owned(),
+                    content: b"B".to_vec(),
                     number: 1,
                 }),
                 b: Some(Line {
-                    content: "B".to_owned(),
+                    content: b"B".to_vec(),
                     number: 3,
                 }),
             },
@@ -463,18 +472,18 @@ mod tests {
                 kind: Insert,
                 a: None,
                 b: Some(Line {
-                    content: "A".to_owned(),
+                    content: b"A".to_vec(),
                     number: 4,
                 }),
             },
             Edit {
                 kind: Equals,
                 a: Some(Line {
-                    content: "C".to_owned(),
+                    content: b"C".to_vec(),
                     number: 2,
                 }),
                 b: Some(Line {
-                    content: "C".to_owned(),
+                    content: b"C".to_vec(),
                     number: 5,
                 }),
             },
@@ -482,7 +491,7 @@ mod tests {
                 kind: Insert,
                 a: None,
                 b: Some(Line {
-                    content: "C".to_owned(),
+                    content: b"C".to_vec(),
                     number: 6,
                 }),
             },
@@ -490,29 +499,29 @@ mod tests {
                 kind: Insert,
                 a: None,
                 b: Some(Line {
-                    content: "B".to_owned(),
+                    content: b"B".to_vec(),
                     number: 7,
                 }),
             },
             Edit {
                 kind: Equals,
                 a: Some(Line {
-                    content: "A".to_owned(),
+                    content: b"A".to_vec(),
                     number: 3,
                 }),
                 b: Some(Line {
-                    content: "A".to_owned(),
+                    content: b"A".to_vec(),
                     number: 8,
                 }),
             },
             Edit {
                 kind: Equals,
                 a: Some(Line {
-                    content: "B".to_owned(),
+                    content: b"B".to_vec(),
                     number: 4,
                 }),
                 b: Some(Line {
-                    content: "B".to_owned(),
+                    content: b"B".to_vec(),
                     number: 9,
                 }),
             },
@@ -520,7 +529,7 @@ mod tests {
                 kind: Insert,
                 a: None,
                 b: Some(Line {
-                    content: "A".to_owned(),
+                    content: b"A".to_vec(),
                     number: 10,
                 }),
             },
@@ -528,7 +537,7 @@ mod tests {
                 kind: Insert,
                 a: None,
                 b: Some(Line {
-                    content: "C".to_owned(),
+                    content: b"C".to_vec(),
                     number: 11,
                 }),
             },
@@ -536,29 +545,29 @@ mod tests {
                 kind: Insert,
                 a: None,
                 b: Some(Line {
-                    content: "C".to_owned(),
+                    content: b"C".to_vec(),
                     number: 12,
                 }),
             },
             Edit {
                 kind: Equals,
                 a: Some(Line {
-                    content: "B".to_owned(),
+                    content: b"B".to_vec(),
                     number: 5,
                 }),
                 b: Some(Line {
-                    content: "B".to_owned(),
+                    content: b"B".to_vec(),
                     number: 13,
                 }),
             },
             Edit {
                 kind: Equals,
                 a: Some(Line {
-                    content: "A".to_owned(),
+                    content: b"A".to_vec(),
                     number: 6,
                 }),
                 b: Some(Line {
-                    content: "A".to_owned(),
+                    content: b"A".to_vec(),
                     number: 14,
                 }),
             },
@@ -566,7 +575,7 @@ mod tests {
                 kind: Insert,
                 a: None,
                 b: Some(Line {
-                    content: "B".to_owned(),
+                    content: b"B".to_vec(),
                     number: 15,
                 }),
             },
@@ -574,7 +583,7 @@ mod tests {
                 kind: Insert,
                 a: None,
                 b: Some(Line {
-                    content: "A".to_owned(),
+                    content: b"A".to_vec(),
                     number: 16,
                 }),
             },
@@ -582,7 +591,7 @@ mod tests {
                 kind: Insert,
                 a: None,
                 b: Some(Line {
-                    content: "C".to_owned(),
+                    content: b"C".to_vec(),
                     number: 17,
                 }),
             },
@@ -590,7 +599,7 @@ mod tests {
                 kind: Insert,
                 a: None,
                 b: Some(Line {
-                    content: "C".to_owned(),
+                    content: b"C".to_vec(),
                     number: 18,
                 }),
             },
@@ -598,7 +607,7 @@ mod tests {
                 kind: Insert,
                 a: None,
                 b: Some(Line {
-                    content: "B".to_owned(),
+                    content: b"B".to_vec(),
                     number: 19,
                 }),
             },
@@ -606,7 +615,7 @@ mod tests {
                 kind: Insert,
                 a: None,
                 b: Some(Line {
-                    content: "A".to_owned(),
+                    content: b"A".to_vec(),
                     number: 20,
                 }),
             },
@@ -614,7 +623,7 @@ mod tests {
                 kind: Insert,
                 a: None,
                 b: Some(Line {
-                    content: "B".to_owned(),
+                    content: b"B".to_vec(),
                     number: 21,
                 }),
             },
@@ -622,7 +631,7 @@ mod tests {
                 kind: Insert,
                 a: None,
                 b: Some(Line {
-                    content: "A".to_owned(),
+                    content: b"A".to_vec(),
                     number: 22,
                 }),
             },
@@ -630,7 +639,7 @@ mod tests {
                 kind: Insert,
                 a: None,
                 b: Some(Line {
-                    content: "C".to_owned(),
+                    content: b"C".to_vec(),
                     number: 23,
                 }),
             },
@@ -638,7 +647,7 @@ mod tests {
                 kind: Insert,
                 a: None,
                 b: Some(Line {
-                    content: "C".to_owned(),
+                    content: b"C".to_vec(),
                     number: 24,
                 }),
             },
@@ -646,7 +655,7 @@ mod tests {
                 kind: Insert,
                 a: None,
                 b: Some(Line {
-                    content: "B".to_owned(),
+                    content: b"B".to_vec(),
                     number: 25,
                 }),
             },
@@ -654,7 +663,7 @@ mod tests {
                 kind: Insert,
                 a: None,
                 b: Some(Line {
-                    content: "A".to_owned(),
+                    content: b"A".to_vec(),
                     number: 26,
                 }),
             },
@@ -662,7 +671,7 @@ mod tests {
                 kind: Insert,
                 a: None,
                 b: Some(Line {
-                    content: "B".to_owned(),
+                    content: b"B".to_vec(),
                     number: 27,
                 }),
             },
@@ -670,7 +679,7 @@ mod tests {
                 kind: Insert,
                 a: None,
                 b: Some(Line {
-                    content: "A".to_owned(),
+                    content: b"A".to_vec(),
                     number: 28,
                 }),
             },
@@ -678,12 +687,11 @@ mod tests {
                 kind: Insert,
                 a: None,
                 b: Some(Line {
-                    content: "C".to_owned(),
+                    content: b"C".to_vec(),
                     number: 29,
                 }),
             },
         ];
         assert_eq!(vals, expected)
     }
-
 }
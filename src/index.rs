@@ -1,12 +1,13 @@
 use crate::index::entry::Entry;
 use crate::lockfile::Lockfile;
 use crate::repository::migration::{Action, MigrationChanges};
+use crate::tree::TreeEntry;
 use crate::workspace::Workspace;
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use failure::Error;
 use fs2::FileExt;
 use sha1::Sha1;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
@@ -15,8 +16,8 @@ pub mod entry;
 
 #[derive(Debug)]
 pub struct Index {
-    entries: BTreeMap<String, Entry>,
-    parents: HashMap<String, Vec<PathBuf>>,
+    entries: BTreeMap<(String, u16), Entry>,
+    parents: HashMap<String, BTreeSet<PathBuf>>,
     changed: bool,
     lock: Lockfile,
 }
@@ -44,6 +45,24 @@ impl Index {
         self.add_entry(entry);
     }
 
+    /// Adds an entry for a blob from the database rather than the workspace.
+    pub fn add_from_db<P: AsRef<Path>>(&mut self, path: P, oid: &str, mode: &str) {
+        self.add_entry(Entry::from_db(path, oid, mode));
+    }
+
+    /// Records a path that did not merge as its base, our and their
+    /// versions, at stages 1 to 3, in place of its merged entry.
+    pub fn add_conflict_set<P: AsRef<Path>>(&mut self, path: P, items: [&Option<TreeEntry>; 3]) {
+        let path = path.as_ref();
+        self.remove_entry(path.to_str().unwrap());
+        for (stage, item) in (1..).zip(items.iter()) {
+            if let Some(item) = item {
+                let entry = Entry::from_db(path, &item.oid(), &item.mode()).with_stage(stage);
+                self.add_entry(entry);
+            }
+        }
+    }
+
     pub fn apply_migration(
         &mut self,
         changes: &MigrationChanges,
@@ -63,12 +82,38 @@ impl Index {
         self.entries.values().cloned().collect()
     }
 
+    /// The merged entry for `key`, which a conflicted path does not have.
     pub fn get_entry(&self, key: &str) -> Option<&Entry> {
-        self.entries.get(key)
+        self.get_stage(key, 0)
+    }
+
+    /// The entry for `key` at `stage`: 0 when merged, otherwise 1 to 3 for
+    /// the base, our and their versions of a conflict.
+    pub fn get_stage(&self, key: &str, stage: u16) -> Option<&Entry> {
+        self.entries.get(&(key.to_owned(), stage))
     }
 
     pub fn has_entry(&self, key: &str) -> bool {
-        self.entries.contains_key(key) || self.parents.contains_key(key)
+        self.stages(key).next().is_some() || self.parents.contains_key(key)
+    }
+
+    pub fn is_conflicted(&self) -> bool {
+        self.entries.values().any(|entry| entry.stage() > 0)
+    }
+
+    /// The paths with unmerged entries, and which stages each has.
+    pub fn conflicts(&self) -> BTreeMap<String, Vec<u16>> {
+        let mut conflicts: BTreeMap<String, Vec<u16>> = BTreeMap::new();
+        for ((path, stage), _) in self.entries.iter().filter(|(key, _)| key.1 > 0) {
+            conflicts.entry(path.clone()).or_default().push(*stage);
+        }
+        conflicts
+    }
+
+    fn stages<'a>(&'a self, key: &str) -> impl Iterator<Item = &'a Entry> {
+        self.entries
+            .range((key.to_owned(), 0)..=(key.to_owned(), 3))
+            .map(|(_, entry)| entry)
     }
 
     pub fn release_lock(self) -> Result<(), Error> {
@@ -97,20 +142,27 @@ impl Index {
         Ok(())
     }
 
+    /// Adds `entry`, replacing anything it clashes with. A merged entry
+    /// resolves any conflict at its path.
     fn add_entry(&mut self, entry: Entry) {
-        let pth = entry.path.to_str().unwrap().to_owned();
+        let key = entry.key();
 
         self.discard_conflicts(&entry);
+        if key.1 == 0 {
+            for stage in 1..=3 {
+                self.entries.remove(&(key.0.clone(), stage));
+            }
+        }
 
         for dir in entry.parent_directories() {
             let dir = dir.to_str().unwrap().to_string();
             self.parents
                 .entry(dir)
-                .and_modify(|e| e.push(entry.path.clone()))
-                .or_insert_with(|| vec![entry.path.clone()]);
+                .or_default()
+                .insert(entry.path.clone());
         }
 
-        self.entries.insert(pth, entry);
+        self.entries.insert(key, entry);
         self.changed = true;
     }
 
@@ -184,9 +236,12 @@ impl Index {
             self.add_entry(e);
         }
 
-        let mut csum = Vec::new();
-        index.read_to_end(&mut csum)?;
-        assert_eq!(digest.digest().bytes(), csum.as_slice());
+        // extensions, such as the cached trees git writes, are skipped
+        let mut rest = Vec::new();
+        index.read_to_end(&mut rest)?;
+        let (extensions, csum) = rest.split_at(rest.len().saturating_sub(20));
+        digest.update(extensions);
+        assert_eq!(digest.digest().bytes(), csum);
         Ok(())
     }
 
@@ -207,7 +262,8 @@ impl Index {
         Ok(res)
     }
 
-    fn remove(&mut self, path: &str) {
+    /// Removes every stage of the entry at `path`, or every entry beneath it.
+    pub fn remove(&mut self, path: &str) {
         if let Some(children) = self.parents.clone().get(path) {
             for child in children {
                 self.remove_entry(child.to_str().unwrap());
@@ -218,20 +274,20 @@ impl Index {
     }
 
     fn remove_entry(&mut self, key: &str) {
-        if let Some(entry) = self.entries.get(key) {
-            for dir in entry.parent_directories() {
-                self.parents
-                    .entry(dir.to_str().unwrap().into())
-                    .and_modify(|f| {
-                        if let Ok(index) = f.binary_search(&PathBuf::from(key)) {
-                            f.remove(index);
-                        }
-                    });
-            }
-        } else {
-            return;
+        let entry = match self.stages(key).next() {
+            Some(entry) => entry.clone(),
+            None => return,
+        };
+        for dir in entry.parent_directories() {
+            self.parents
+                .entry(dir.to_str().unwrap().into())
+                .and_modify(|f| {
+                    f.remove(&entry.path);
+                });
+        }
+        for stage in 0..=3 {
+            self.entries.remove(&(key.to_owned(), stage));
         }
-        self.entries.remove(key);
         self.changed = true;
     }
 
@@ -246,6 +302,7 @@ impl Index {
 mod tests {
     use super::*;
 
+    use crate::database::marker::Marker;
     use lazy_static::lazy_static;
     use std::path::PathBuf;
 
@@ -350,4 +407,34 @@ mod tests {
         assert_eq!(vec![Some("alice.txt"), Some("bob.txt")], entry_paths)
     }
 
+    #[test]
+    fn test_add_conflict_set() {
+        let mut index = Index::new(INDEX.to_path_buf()).unwrap();
+        index.add("alice.txt", &OID, FILE_STAT.clone());
+        index.add("bob.txt", &OID, FILE_STAT.clone());
+        let version = Some(TreeEntry::Marker(Marker::new(
+            "alice.txt",
+            OID.as_str(),
+            "100644",
+        )));
+        index.add_conflict_set("alice.txt", [&version, &None, &version]);
+        let conflicts = index.conflicts();
+        let keys: Vec<(String, u16)> = index.entries.keys().cloned().collect();
+        let unmerged = index.get_entry("alice.txt").is_none() && index.has_entry("alice.txt");
+        index.add("alice.txt", &OID, FILE_STAT.clone());
+        let conflicted = index.is_conflicted();
+        std::fs::remove_file(LOCK.to_path_buf()).unwrap();
+
+        assert_eq!(
+            vec![
+                ("alice.txt".to_string(), 1),
+                ("alice.txt".to_string(), 3),
+                ("bob.txt".to_string(), 0)
+            ],
+            keys
+        );
+        assert_eq!(conflicts.get("alice.txt"), Some(&vec![1, 3]));
+        assert!(unmerged);
+        assert!(!conflicted);
+    }
 }
@@ -8,6 +8,9 @@ use std::io::{Read, Write};
 use std::os::unix::fs::MetadataExt;
 use std::path::{Ancestors, Path, PathBuf};
 
+const STAGE_MASK: u16 = 0x3000;
+const STAGE_SHIFT: u16 = 12;
+
 #[derive(Clone, Debug)]
 pub struct Entry {
     pub path: PathBuf,
@@ -63,6 +66,35 @@ impl Entry {
         }
     }
 
+    /// An entry for a blob taken straight from the database, which has no
+    /// stat information until it is next compared with the workspace.
+    pub fn from_db<P: AsRef<Path>>(path: P, oid: &str, mode: &str) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let flags = path.to_str().unwrap().len().min(0xFFF) as u16;
+        Entry {
+            path,
+            oid: String::from(oid),
+            flags,
+            ctime: 0,
+            ctime_ns: 0,
+            mtime: 0,
+            mtime_ns: 0,
+            dev: 0,
+            ino: 0,
+            mode: u32::from_str_radix(mode, 8).unwrap_or(0o100644),
+            uid: 0,
+            gid: 0,
+            size: 0,
+        }
+    }
+
+    /// The same entry at another stage: 0 for a merged path, or 1 to 3 for
+    /// the base, our and their versions of a conflicted one.
+    pub fn with_stage(mut self, stage: u16) -> Self {
+        self.flags = (self.flags & !STAGE_MASK) | ((stage << STAGE_SHIFT) & STAGE_MASK);
+        self
+    }
+
     pub fn from(entry: &mut Vec<u8>) -> Result<Self, Error> {
         let mut entry = std::io::Cursor::new(entry);
         let ctime = entry.read_u32::<BigEndian>()?;
@@ -123,10 +155,13 @@ impl Entry {
         Ok(data)
     }
 
+    /// Whether the size and mode match. An entry with no recorded size, such
+    /// as one from `from_db`, matches a file of any size so that its contents
+    /// are checked instead.
     pub fn stat_match(&self, stat: Option<&Metadata>) -> bool {
         if let Some(stat) = stat {
             is_executable(stat.mode()) == is_executable(self.mode)
-                && self.size == stat.size() as u32
+                && (self.size == 0 || self.size == stat.size() as u32)
         } else {
             false
         }
@@ -147,6 +182,15 @@ impl Entry {
         Entry::mode_from_stat(self.mode)
     }
 
+    pub fn stage(&self) -> u16 {
+        (self.flags & STAGE_MASK) >> STAGE_SHIFT
+    }
+
+    /// The index key of the entry, its path and stage.
+    pub fn key(&self) -> (String, u16) {
+        (self.path.to_str().unwrap().to_owned(), self.stage())
+    }
+
     pub fn oid(&self) -> &str {
         self.oid.as_str()
     }
@@ -180,7 +224,7 @@ impl Entry {
 
 impl PartialEq for Entry {
     fn eq(&self, other: &Entry) -> bool {
-        self.path == other.path
+        self.path == other.path && self.stage() == other.stage()
     }
 }
 
@@ -188,7 +232,9 @@ impl Eq for Entry {}
 
 impl Ord for Entry {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.path.cmp(&other.path)
+        self.path
+            .cmp(&other.path)
+            .then_with(|| self.stage().cmp(&other.stage()))
     }
 }
 
@@ -2,16 +2,20 @@ use std::error::Error;
 use std::fmt;
 use std::fmt::Formatter;
 
+pub mod attributes;
 pub mod author;
 pub mod commands;
 pub mod commit;
+pub mod config;
 pub mod database;
 pub mod diff;
 pub mod index;
 pub mod lockfile;
+pub mod merge;
 pub mod refs;
 pub mod repository;
 pub mod revision;
+pub mod tag;
 pub mod tree;
 pub mod utilities;
 pub mod workspace;
@@ -4,8 +4,12 @@ use rit::utilities::stat_file;
 use clap::App;
 use clap::ArgMatches;
 use clap::{Arg, SubCommand};
-use rit::commands::{branch, checkout, commit, diff, status};
+use rit::commands::{
+    branch, checkout, commit, diff, gc, log, merge, merge_file, pack_refs, reflog, repack, reset,
+    restore, rev_list, rev_parse, show, status, switch, tag,
+};
 use rit::index::Index;
+use rit::refs::Refs;
 use rit::workspace::Workspace;
 use rit::BoxResult;
 
@@ -25,10 +29,24 @@ fn main() -> BoxResult<()> {
         .subcommand(checkout::cli())
         .subcommand(commit::cli())
         .subcommand(diff::cli())
+        .subcommand(gc::cli())
         .subcommand(
             SubCommand::with_name("init").arg(Arg::with_name("PATH").required(true).index(1)),
         )
+        .subcommand(log::cli())
+        .subcommand(merge::cli())
+        .subcommand(merge_file::cli())
+        .subcommand(pack_refs::cli())
+        .subcommand(reflog::cli())
+        .subcommand(repack::cli())
+        .subcommand(reset::cli())
+        .subcommand(restore::cli())
+        .subcommand(rev_list::cli())
+        .subcommand(rev_parse::cli())
+        .subcommand(show::cli())
         .subcommand(status::cli())
+        .subcommand(switch::cli())
+        .subcommand(tag::cli())
         .get_matches();
 
     match app.subcommand() {
@@ -37,8 +55,22 @@ fn main() -> BoxResult<()> {
         ("checkout", Some(m)) => checkout::exec(m),
         ("commit", Some(m)) => commit::exec(m),
         ("diff", Some(m)) => diff::exec(m),
+        ("gc", Some(m)) => gc::exec(m),
         ("init", Some(m)) => git_init(m),
+        ("log", Some(m)) => log::exec(m),
+        ("merge", Some(m)) => merge::exec(m),
+        ("merge-file", Some(m)) => merge_file::exec(m),
+        ("pack-refs", Some(m)) => pack_refs::exec(m),
+        ("reflog", Some(m)) => reflog::exec(m),
+        ("repack", Some(m)) => repack::exec(m),
+        ("reset", Some(m)) => reset::exec(m),
+        ("restore", Some(m)) => restore::exec(m),
+        ("rev-list", Some(m)) => rev_list::exec(m),
+        ("rev-parse", Some(m)) => rev_parse::exec(m),
+        ("show", Some(m)) => show::exec(m),
         ("status", Some(m)) => status::exec(m),
+        ("switch", Some(m)) => switch::exec(m),
+        ("tag", Some(m)) => tag::exec(m),
         _ => {
             println!("unrecognised command");
             Err(From::from("unrecognised command"))
@@ -84,6 +116,8 @@ fn git_init(matches: &ArgMatches) -> BoxResult<()> {
     let path = std::path::Path::new(matches.value_of("PATH").unwrap());
     let target = path.join(".git");
     std::fs::create_dir_all(target.join("objects"))?;
-    std::fs::create_dir_all(target.join("refs"))?;
+    std::fs::create_dir_all(target.join("refs/heads"))?;
+    let refs = Refs::new(&target);
+    refs.update_ref_file(target.join("HEAD"), "ref: refs/heads/master")?;
     Ok(())
 }
@@ -0,0 +1,157 @@
+use crate::database::marker::Marker;
+use crate::database::tree_diff::TreeDifference;
+use crate::database::{Blob, Database, Storable};
+use crate::diff::binary::is_binary;
+use crate::diff::diff3::{self, Merged};
+use crate::tree::TreeEntry;
+use failure::Error;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// How a path that both sides changed failed to merge.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConflictKind {
+    /// Both sides changed, or added, the file differently.
+    Content,
+    /// We deleted the file and they modified it.
+    DeletedByUs,
+    /// We modified the file and they deleted it.
+    DeletedByThem,
+}
+
+/// A path that could not be merged, with its version in the merge base and
+/// on each side.
+#[derive(Clone, Debug)]
+pub struct Conflict {
+    pub kind: ConflictKind,
+    pub base: Option<TreeEntry>,
+    pub ours: Option<TreeEntry>,
+    pub theirs: Option<TreeEntry>,
+}
+
+/// The outcome of merging their changes into our tree.
+#[derive(Debug, Default)]
+pub struct Resolution {
+    /// Their changes that do not clash with ours, as changes to our tree.
+    pub clean: TreeDifference,
+    /// The paths among `clean` whose contents merged both sides' changes.
+    pub auto_merged: BTreeSet<PathBuf>,
+    pub conflicts: BTreeMap<PathBuf, Conflict>,
+}
+
+impl Resolution {
+    /// The conflicted paths as changes from our version to theirs, which is
+    /// as much as resolving them could touch.
+    pub fn conflict_diff(&self) -> TreeDifference {
+        self.conflicts
+            .iter()
+            .map(|(path, c)| (path.clone(), (c.ours.clone(), c.theirs.clone())))
+            .collect()
+    }
+}
+
+/// Merges the trees of the commits `ours` and `theirs`: a path changed
+/// since `base` on one side only takes that side's version, and a file
+/// changed on both sides has their changes merged line by line. Whatever
+/// will not merge is a conflict.
+pub fn resolve(
+    db: &Database,
+    base: Option<&str>,
+    ours: &str,
+    theirs: &str,
+) -> Result<Resolution, Error> {
+    let base = base.map(String::from);
+    let our_changes = db.tree_diff(base.clone(), Some(ours.to_owned()));
+    let their_changes = db.tree_diff(base, Some(theirs.to_owned()));
+
+    let mut resolution = Resolution::default();
+    for (path, (old, new)) in their_changes {
+        let ours = match our_changes.get(&path) {
+            // untouched by us, so our version is still the base's
+            None => {
+                resolution.clean.insert(path, (old, new));
+                continue;
+            }
+            Some((_, ours)) => ours.clone(),
+        };
+        if same_entry(&ours, &new) {
+            continue;
+        }
+        let kind = match (&ours, &new) {
+            (None, _) => ConflictKind::DeletedByUs,
+            (_, None) => ConflictKind::DeletedByThem,
+            _ => ConflictKind::Content,
+        };
+        if kind == ConflictKind::Content {
+            if let Some(merged) = merge_content(db, &path, &old, &ours, &new)? {
+                resolution.clean.insert(path.clone(), (ours, Some(merged)));
+                resolution.auto_merged.insert(path);
+                continue;
+            }
+        }
+        let conflict = Conflict {
+            kind,
+            base: old,
+            ours,
+            theirs: new,
+        };
+        resolution.conflicts.insert(path, conflict);
+    }
+    Ok(resolution)
+}
+
+/// Merges three versions of a file line by line, a missing one counting as
+/// empty, or gives `None` if any of them is binary.
+pub fn merge_blobs(
+    db: &Database,
+    base: &Option<TreeEntry>,
+    ours: &Option<TreeEntry>,
+    theirs: &Option<TreeEntry>,
+) -> Result<Option<Merged>, Error> {
+    let mut texts = vec![];
+    for entry in &[base, ours, theirs] {
+        let data = match entry {
+            Some(entry) => {
+                let (_, _, data) = db.read_object(&entry.oid())?;
+                Blob::from(data).content().to_vec()
+            }
+            None => vec![],
+        };
+        if is_binary(&data) {
+            return Ok(None);
+        }
+        texts.push(data);
+    }
+    Ok(Some(diff3::merge(&texts[0], &texts[1], &texts[2])))
+}
+
+/// Stores the merge of a file both sides changed, if it merges cleanly,
+/// returning its new entry.
+fn merge_content(
+    db: &Database,
+    path: &Path,
+    base: &Option<TreeEntry>,
+    ours: &Option<TreeEntry>,
+    theirs: &Option<TreeEntry>,
+) -> Result<Option<TreeEntry>, Error> {
+    let merged = match merge_blobs(db, base, ours, theirs)? {
+        Some(merged) if merged.is_clean() => merged,
+        _ => return Ok(None),
+    };
+    let blob = Blob::new(merged.render("", "", None));
+    let oid = blob.oid();
+    db.store(blob)?;
+
+    let (ours, theirs) = (ours.as_ref().unwrap(), theirs.as_ref().unwrap());
+    // a mode change made on one side only is kept
+    let mode = match base {
+        Some(base) if base.mode() == ours.mode() => theirs.mode(),
+        _ => ours.mode(),
+    };
+    Ok(Some(TreeEntry::Marker(Marker::new(path, oid, mode))))
+}
+
+fn same_entry(a: &Option<TreeEntry>, b: &Option<TreeEntry>) -> bool {
+    let key = |e: &Option<TreeEntry>| e.as_ref().map(|e| (e.oid(), e.mode()));
+    key(a) == key(b)
+}
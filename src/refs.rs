@@ -1,15 +1,41 @@
+use crate::config::Config;
 use crate::lockfile::Lockfile;
+use crate::refs::packed::{PackedRef, PackedRefs};
+use crate::refs::reflog::Entry;
 use failure::format_err;
 use failure::Error;
-use std::fs::{File, OpenOptions};
+use std::collections::BTreeMap;
+use std::fs::File;
 use std::io::Read;
 use std::path::{Path, PathBuf};
 
+pub mod packed;
+pub mod reflog;
+
+const HEAD: &str = "HEAD";
+const ORIG_HEAD: &str = "ORIG_HEAD";
+const CHECKOUT_PREFIX: &str = "checkout: moving from ";
+const LOGS: &str = "logs";
+const PACKED_REFS: &str = "packed-refs";
+const SYMREF_PREFIX: &str = "ref: ";
+const TAGS_PREFIX: &str = "refs/tags/";
+const HEADS_PREFIX: &str = "refs/heads/";
+const REMOTES_PREFIX: &str = "refs/remotes/";
+const CONFIG: &str = "config";
+const MAX_SYMREF_DEPTH: usize = 5;
+
 #[derive(Clone, Debug)]
 pub struct Refs {
     path: PathBuf,
 }
 
+/// The contents of a ref file: either an oid, or the name of another ref.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Ref {
+    SymRef(String),
+    Oid(String),
+}
+
 pub enum BranchName {
     Ok,
     InvalidName,
@@ -24,71 +50,537 @@ impl Refs {
     }
 
     pub fn get_head(&self) -> Option<String> {
-        if let Ok(mut fh) = OpenOptions::new().read(true).open(self.head_path()) {
-            let mut ret = String::new();
-            fh.read_to_string(&mut ret).unwrap();
-            let ret = ret.trim_end_matches('\n').to_owned();
-            Some(ret)
+        self.resolve(HEAD)
+    }
+
+    /// Moves HEAD to `oid`. When HEAD points at a branch it is the branch that
+    /// is advanced.
+    pub fn update_head(&self, oid: &str, message: &str) -> Result<(), Error> {
+        self.update_ref(HEAD, oid, message)
+    }
+
+    /// Points HEAD at the branch `revision` if there is one, and otherwise
+    /// detaches it at `oid`. The branch may be given by its short, partial
+    /// or full name.
+    pub fn set_head(&self, revision: &str, oid: &str, message: &str) -> Result<(), Error> {
+        let old_oid = self.get_head();
+        let short = format!("{}{}", HEADS_PREFIX, revision);
+        let name = if self.ref_exists(&short) {
+            Some(short)
         } else {
-            None
+            self.expand_name(revision)
+                .filter(|name| name.starts_with(HEADS_PREFIX))
+        };
+        if let Some(name) = name {
+            let target = format!("{}{}", SYMREF_PREFIX, name);
+            self.update_ref_file(self.head_path(), &target)?;
+        } else {
+            self.update_ref_file(self.head_path(), oid)?;
+        }
+        self.append_reflog(HEAD, &Entry::new(old_oid, oid, message))
+    }
+
+    /// Points HEAD straight at `oid`, even when a branch has that name.
+    pub fn detach_head(&self, oid: &str, message: &str) -> Result<(), Error> {
+        let old_oid = self.get_head();
+        self.update_ref_file(self.head_path(), oid)?;
+        self.append_reflog(HEAD, &Entry::new(old_oid, oid, message))
+    }
+
+    /// Records where HEAD was before a command that moves it a long way, so
+    /// that `ORIG_HEAD` can get back there.
+    pub fn set_orig_head(&self, oid: &str) -> Result<(), Error> {
+        self.update_ref_file(self.path.join(ORIG_HEAD), oid)
+    }
+
+    /// Points HEAD at a branch that does not exist yet, so that the next
+    /// commit starts a new history.
+    pub fn orphan_head(&self, name: &str) -> Result<(), Error> {
+        self.check_branch_name(name)?;
+        let target = format!("{}{}{}", SYMREF_PREFIX, HEADS_PREFIX, name);
+        self.update_ref_file(self.head_path(), &target)
+    }
+
+    /// Checks that a new branch could be called `name`.
+    pub fn check_branch_name(&self, name: &str) -> Result<(), Error> {
+        match self.validate_branch_name(name)? {
+            BranchName::InvalidName => Err(format_err!("'{}' is not a valid branch name.", name)),
+            BranchName::AlreadyExists => {
+                Err(format_err!("A branch named '{}' already exists.", name))
+            }
+            BranchName::Ok => Ok(()),
+        }
+    }
+
+    /// Sets the ref `name` to `oid`, following symbolic refs, and records
+    /// the move in the reflog of every ref along the way.
+    pub fn update_ref(&self, name: &str, oid: &str, message: &str) -> Result<(), Error> {
+        let old_oid = self.resolve(name);
+        let mut names = vec![name.to_owned()];
+        while let Some(Ref::SymRef(target)) = self.read_oid_or_symref(names.last().unwrap()) {
+            if names.len() > MAX_SYMREF_DEPTH {
+                return Err(format_err!("symbolic ref loop at '{}'", name));
+            }
+            names.push(target);
         }
+        self.update_ref_file(self.path.join(names.last().unwrap()), oid)?;
+
+        let entry = Entry::new(old_oid, oid, message);
+        for name in names.iter().rev() {
+            self.append_reflog(name, &entry)?;
+        }
+        Ok(())
     }
 
-    pub fn update_head(&self, oid: &str) -> Result<(), Error> {
-        self.update_ref_file(self.path.join("HEAD"), oid)
+    /// The full name of the ref HEAD points at, or `None` when it is detached.
+    pub fn current_ref(&self) -> Option<String> {
+        match self.read_oid_or_symref(HEAD) {
+            Some(Ref::SymRef(name)) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// The repository's `.git` directory.
+    pub fn git_dir(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_detached(&self) -> bool {
+        self.current_ref().is_none()
+    }
+
+    pub fn short_name(&self, name: &str) -> String {
+        let name = name
+            .strip_prefix("refs/heads/")
+            .or_else(|| name.strip_prefix("refs/remotes/"))
+            .or_else(|| name.strip_prefix("refs/"))
+            .unwrap_or(name);
+        String::from(name)
     }
 
     pub fn update_ref_file<P: AsRef<Path>>(&self, path: P, oid: &str) -> Result<(), Error> {
+        if let Some(parent) = path.as_ref().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
         let lock = Lockfile::new(path)?.try_lock()?;
         lock.write_all(oid.as_bytes())?;
         lock.write_all("\n".as_bytes())?;
         lock.commit()
     }
 
-    pub fn create_branch(&self, name: &str, start: Option<String>) -> Result<(), Error> {
-        match self.validate_branch_name(name)? {
+    pub fn create_branch(
+        &self,
+        name: &str,
+        start: Option<String>,
+        message: &str,
+    ) -> Result<(), Error> {
+        self.check_branch_name(name)?;
+        if let Some(head) = start {
+            self.update_ref(&format!("refs/heads/{}", name), &head, message)
+        } else {
+            Err(format_err!(
+                "failed to get reference for HEAD to branch off"
+            ))
+        }
+    }
+
+    /// Points `refs/tags/<name>` at `oid`, returning what it pointed at
+    /// before. An existing tag is only moved when `force` is set.
+    pub fn create_tag(&self, name: &str, oid: &str, force: bool) -> Result<Option<String>, Error> {
+        if crate::revision::INVALID_NAME.is_match(name) {
+            return Err(format_err!("'{}' is not a valid tag name.", name));
+        }
+        let full_name = format!("{}{}", TAGS_PREFIX, name);
+        let old = self.resolve(&full_name);
+        if old.is_some() && !force {
+            return Err(format_err!("tag '{}' already exists", name));
+        }
+        self.update_ref(&full_name, oid, "")?;
+        Ok(old)
+    }
+
+    /// Every tag as a short name paired with the oid it holds.
+    pub fn list_tags(&self) -> Vec<(String, String)> {
+        self.list_short(TAGS_PREFIX)
+    }
+
+    /// Every local branch as a short name paired with the oid it holds.
+    pub fn list_branches(&self) -> Vec<(String, String)> {
+        self.list_short(HEADS_PREFIX)
+    }
+
+    fn list_short(&self, prefix: &str) -> Vec<(String, String)> {
+        self.list_refs()
+            .into_iter()
+            .filter_map(|(name, oid)| {
+                name.strip_prefix(prefix)
+                    .map(|short| (short.to_owned(), oid))
+            })
+            .collect()
+    }
+
+    /// Removes a branch and its config section, returning the oid it held.
+    pub fn delete_branch(&self, name: &str) -> Result<Option<String>, Error> {
+        let oid = self.delete_ref(&format!("{}{}", HEADS_PREFIX, name))?;
+        if oid.is_some() {
+            self.update_config(|config| config.remove_section(&format!("branch.{}", name)))?;
+        }
+        Ok(oid)
+    }
+
+    /// The full name of the ref a branch tracks, from its
+    /// `branch.<name>.remote` and `branch.<name>.merge` settings.
+    pub fn upstream(&self, branch: &str) -> Option<String> {
+        let config = Config::load(self.path.join(CONFIG)).ok()?;
+        let remote = config.get(&format!("branch.{}.remote", branch))?;
+        let merge = config.get(&format!("branch.{}.merge", branch))?;
+        if remote == "." {
+            Some(merge.to_owned())
+        } else {
+            let merge = merge.strip_prefix(HEADS_PREFIX)?;
+            Some(format!("{}{}/{}", REMOTES_PREFIX, remote, merge))
+        }
+    }
+
+    /// Records `upstream`, the full name of a local or remote-tracking
+    /// branch, as the branch that `branch` tracks.
+    pub fn set_upstream(&self, branch: &str, upstream: &str) -> Result<(), Error> {
+        let (remote, merge) = match upstream.strip_prefix(REMOTES_PREFIX) {
+            Some(rest) => {
+                let (remote, name) = rest
+                    .split_once('/')
+                    .ok_or_else(|| format_err!("'{}' is not a remote-tracking branch", upstream))?;
+                (remote.to_owned(), format!("{}{}", HEADS_PREFIX, name))
+            }
+            None => (String::from("."), upstream.to_owned()),
+        };
+        self.update_config(|config| {
+            config.set(&format!("branch.{}.remote", branch), &remote)?;
+            config.set(&format!("branch.{}.merge", branch), &merge)
+        })
+    }
+
+    /// Forgets the upstream of `branch`, returning whether it had one.
+    pub fn unset_upstream(&self, branch: &str) -> Result<bool, Error> {
+        let mut unset = false;
+        self.update_config(|config| {
+            unset |= config.unset(&format!("branch.{}.remote", branch))?;
+            unset |= config.unset(&format!("branch.{}.merge", branch))?;
+            Ok(())
+        })?;
+        Ok(unset)
+    }
+
+    fn update_config<T, F>(&self, f: F) -> Result<T, Error>
+    where
+        F: FnOnce(&mut Config) -> Result<T, Error>,
+    {
+        let path = self.path.join(CONFIG);
+        let mut config = Config::load(&path)?;
+        let result = f(&mut config)?;
+        config.save(&path)?;
+        Ok(result)
+    }
+
+    /// Renames the branch `old` to `new`, taking its reflog along and
+    /// keeping HEAD on it if it is checked out. An existing branch called
+    /// `new` is only replaced when `force` is set.
+    pub fn rename_branch(&self, old: &str, new: &str, force: bool) -> Result<(), Error> {
+        let old_name = format!("{}{}", HEADS_PREFIX, old);
+        let new_name = format!("{}{}", HEADS_PREFIX, new);
+        let oid = self
+            .resolve(&old_name)
+            .ok_or_else(|| format_err!("branch '{}' not found.", old))?;
+        match self.validate_branch_name(new)? {
             BranchName::InvalidName => {
-                return Err(format_err!("'{}' is not a valid branch name.", name))
+                return Err(format_err!("'{}' is not a valid branch name.", new))
+            }
+            BranchName::AlreadyExists if old == new => return Ok(()),
+            BranchName::AlreadyExists if !force => {
+                return Err(format_err!("A branch named '{}' already exists.", new))
             }
             BranchName::AlreadyExists => {
-                return Err(format_err!("A branch named '{}' already exists.", name))
+                self.delete_ref(&new_name)?;
             }
-            _ => {}
+            BranchName::Ok => {}
         }
-        if let Some(head) = start {
-            std::fs::create_dir_all(self.heads_path())?;
-            let path = self.heads_path().join(name);
-            self.update_ref_file(path, &head)?;
-            Ok(())
+
+        // the old ref goes before the new one is written, so that one name
+        // can be renamed to a path beneath it, or the other way around
+        let old_log = self.reflog_path(&old_name);
+        let log = if old_log.is_file() {
+            Some(std::fs::read(&old_log)?)
         } else {
-            Err(format_err!(
-                "failed to get reference for HEAD to branch off"
-            ))
+            None
+        };
+        self.delete_ref(&old_name)?;
+        if let Some(log) = log {
+            let new_log = self.reflog_path(&new_name);
+            if let Some(parent) = new_log.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&new_log, log)?;
         }
+        self.update_ref_file(self.path.join(&new_name), &oid)?;
+
+        self.update_config(|config| {
+            config.remove_section(&format!("branch.{}", new))?;
+            config.rename_section(&format!("branch.{}", old), &format!("branch.{}", new))
+        })?;
+
+        let message = format!("Branch: renamed {} to {}", old_name, new_name);
+        let entry = Entry::new(Some(oid.clone()), &oid, &message);
+        self.append_reflog(&new_name, &entry)?;
+        if self.current_ref().as_deref() == Some(old_name.as_str()) {
+            let target = format!("{}{}", SYMREF_PREFIX, new_name);
+            self.update_ref_file(self.head_path(), &target)?;
+            self.append_reflog(HEAD, &entry)?;
+        }
+        Ok(())
     }
 
     pub fn read_ref(&self, name: &str) -> Option<String> {
-        if let Some(path) = self.path_for_name(name) {
-            return self.read_ref_file(path);
+        self.resolve(&self.expand_name(name)?)
+    }
+
+    /// The entries of a ref's reflog, oldest first.
+    pub fn reflog(&self, name: &str) -> Result<Vec<Entry>, Error> {
+        reflog::read(self.reflog_path(name))
+    }
+
+    pub fn write_reflog(&self, name: &str, entries: &[Entry]) -> Result<(), Error> {
+        reflog::write(self.reflog_path(name), entries)
+    }
+
+    /// The branch (or detached commit) that was checked out `n` checkouts
+    /// ago, read back from the messages in HEAD's reflog.
+    pub fn previous_checkout(&self, n: usize) -> Option<String> {
+        if n == 0 {
+            return None;
+        }
+        self.reflog(HEAD)
+            .ok()?
+            .iter()
+            .rev()
+            .filter_map(|e| e.message.strip_prefix(CHECKOUT_PREFIX))
+            .filter_map(|m| m.find(" to ").map(|pos| m[..pos].to_owned()))
+            .nth(n - 1)
+    }
+
+    /// The full names of every ref that has a reflog.
+    pub fn list_reflogs(&self) -> Vec<String> {
+        let logs = self.path.join(LOGS);
+        let mut names = vec![];
+        if self.reflog_path(HEAD).is_file() {
+            names.push(String::from(HEAD));
+        }
+        let mut pending = vec![logs.join("refs")];
+        while let Some(dir) = pending.pop() {
+            for entry in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    pending.push(path);
+                } else if path.extension().map(|e| e != "lock").unwrap_or(true) {
+                    let name = path.strip_prefix(&logs).unwrap_or(&path);
+                    names.push(name.to_str().unwrap().to_owned());
+                }
+            }
+        }
+        names.sort();
+        names
+    }
+
+    fn append_reflog(&self, name: &str, entry: &Entry) -> Result<(), Error> {
+        let path = self.reflog_path(name);
+        let logged = name == HEAD
+            || ["refs/heads/", "refs/remotes/", "refs/notes/"]
+                .iter()
+                .any(|p| name.starts_with(p));
+        if logged || path.is_file() {
+            reflog::append(path, entry)?;
+        }
+        Ok(())
+    }
+
+    fn reflog_path(&self, name: &str) -> PathBuf {
+        self.path.join(LOGS).join(name)
+    }
+
+    /// Removes the ref `name` (a full name such as `refs/heads/topic`) from
+    /// both the loose refs and `packed-refs`, returning the oid it held.
+    pub fn delete_ref(&self, name: &str) -> Result<Option<String>, Error> {
+        let oid = self.resolve(name);
+
+        let packed = self.packed_refs()?;
+        if packed.contains(name) {
+            let lock = Lockfile::new(self.packed_refs_path())?.try_lock()?;
+            let mut packed = PackedRefs::load(self.packed_refs_path())?;
+            packed.remove(name);
+            lock.write_all(packed.serialize().as_bytes())?;
+            lock.commit()?;
+        }
+
+        for path in &[self.path.join(name), self.reflog_path(name)] {
+            if path.is_file() {
+                std::fs::remove_file(path)?;
+                self.remove_empty_parents(path);
+            }
+        }
+        Ok(oid)
+    }
+
+    /// Moves loose refs into `packed-refs`. Tags, and refs that are already
+    /// packed, are always included; other refs only when `all` is set. With
+    /// `prune` the loose files are removed afterwards.
+    pub fn pack_refs(&self, all: bool, prune: bool) -> Result<(), Error> {
+        let lock = Lockfile::new(self.packed_refs_path())?.try_lock()?;
+        let mut packed = PackedRefs::load(self.packed_refs_path())?;
+
+        let mut loose = vec![];
+        self.collect_loose(&self.refs_path(), &mut loose);
+        let mut packed_names = vec![];
+        for (name, reference) in loose {
+            let oid = match reference {
+                Ref::Oid(oid) => oid,
+                Ref::SymRef(_) => continue,
+            };
+            if !all && !name.starts_with("refs/tags/") && !packed.contains(&name) {
+                continue;
+            }
+            let peeled = packed
+                .get(&name)
+                .filter(|p| p.oid == oid)
+                .and_then(|p| p.peeled.clone());
+            packed.insert(&name, PackedRef { oid, peeled });
+            packed_names.push(name);
+        }
+        lock.write_all(packed.serialize().as_bytes())?;
+        lock.commit()?;
+
+        if prune {
+            for name in packed_names {
+                let path = self.path.join(&name);
+                std::fs::remove_file(&path)?;
+                self.remove_empty_parents(&path);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn packed_refs(&self) -> Result<PackedRefs, Error> {
+        PackedRefs::load(self.packed_refs_path())
+    }
+
+    fn resolve(&self, name: &str) -> Option<String> {
+        let mut name = name.to_owned();
+        for _ in 0..MAX_SYMREF_DEPTH {
+            match self.read_oid_or_symref(&name)? {
+                Ref::SymRef(target) => name = target,
+                Ref::Oid(oid) => return Some(oid),
+            }
         }
         None
     }
 
-    fn path_for_name(&self, name: &str) -> Option<PathBuf> {
-        let refs = &self.refs_path();
-        let heads = &self.heads_path();
-        let prefixes = vec![&self.path, refs, heads];
-        prefixes
-            .iter()
-            .find(|&p| {
-                let pth = p.join(name);
-                std::fs::metadata(pth).map(|m| m.is_file()).unwrap_or(false)
-            })
-            .map(|p| p.join(name))
+    /// Reads a ref by its full name, preferring the loose file over any
+    /// entry in `packed-refs`.
+    fn read_oid_or_symref(&self, name: &str) -> Option<Ref> {
+        if let Some(content) = self.read_ref_file(self.path.join(name)) {
+            return match content.strip_prefix(SYMREF_PREFIX) {
+                Some(target) => Some(Ref::SymRef(target.trim().to_owned())),
+                None => Some(Ref::Oid(content)),
+            };
+        }
+        self.packed_refs()
+            .ok()?
+            .get(name)
+            .map(|p| Ref::Oid(p.oid.clone()))
+    }
+
+    fn ref_exists(&self, name: &str) -> bool {
+        self.read_oid_or_symref(name).is_some()
+    }
+
+    /// Every ref under `refs/`, as full names paired with the oid they hold.
+    pub fn list_refs(&self) -> Vec<(String, String)> {
+        let mut loose = vec![];
+        self.collect_loose(&self.refs_path(), &mut loose);
+
+        let mut refs = BTreeMap::new();
+        if let Ok(packed) = self.packed_refs() {
+            for (name, packed) in packed.iter() {
+                refs.insert(name.clone(), packed.oid.clone());
+            }
+        }
+        for (name, reference) in loose {
+            let oid = match reference {
+                Ref::Oid(oid) => Some(oid),
+                Ref::SymRef(target) => self.resolve(&target),
+            };
+            if let Some(oid) = oid {
+                refs.insert(name, oid);
+            }
+        }
+        refs.into_iter().collect()
+    }
+
+    fn collect_loose(&self, dir: &Path, refs: &mut Vec<(String, Ref)>) {
+        if let Ok(entries) = std::fs::read_dir(dir) {
+            for entry in entries.filter_map(|e| e.ok()) {
+                let path = entry.path();
+                if path.is_dir() {
+                    self.collect_loose(&path, refs);
+                } else if path.extension().map(|e| e == "lock").unwrap_or(false) {
+                    continue;
+                } else {
+                    let name = path.strip_prefix(&self.path).unwrap_or(&path);
+                    let name = name.to_str().unwrap();
+                    if let Some(reference) = self.read_oid_or_symref(name) {
+                        refs.push((name.to_owned(), reference));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Finds the full name for a short one, trying the same prefixes in the
+    /// same order as git.
+    pub fn expand_name(&self, name: &str) -> Option<String> {
+        let candidates = vec![
+            name.to_owned(),
+            format!("refs/{}", name),
+            format!("refs/tags/{}", name),
+            format!("refs/heads/{}", name),
+        ];
+        candidates.into_iter().find(|c| self.ref_exists(c))
+    }
+
+    /// Removes the now-empty directories a deleted ref or reflog lived in,
+    /// stopping below `refs/heads`, `refs/tags` and friends.
+    fn remove_empty_parents(&self, path: &Path) {
+        let logs = self.path.join(LOGS).join("refs");
+        let refs = if path.starts_with(&logs) {
+            logs
+        } else {
+            self.refs_path()
+        };
+        let mut dir = path.parent();
+        while let Some(d) = dir {
+            if d.parent() == Some(refs.as_path()) || !d.starts_with(&refs) {
+                break;
+            }
+            if std::fs::remove_dir(d).is_err() {
+                break;
+            }
+            dir = d.parent();
+        }
     }
 
     fn read_ref_file(&self, path: PathBuf) -> Option<String> {
         let mut cnt = String::new();
+        if !path.is_file() {
+            return None;
+        }
         if let Ok(mut fh) = File::open(path) {
             fh.read_to_string(&mut cnt)
                 .expect("fatal: Could not read reference");
@@ -103,10 +595,8 @@ impl Refs {
             return Ok(BranchName::InvalidName);
         }
 
-        if let Ok(stat) = std::fs::metadata(self.heads_path().join(name)) {
-            if stat.is_file() {
-                return Ok(BranchName::AlreadyExists);
-            }
+        if self.ref_exists(&format!("refs/heads/{}", name)) {
+            return Ok(BranchName::AlreadyExists);
         }
         Ok(BranchName::Ok)
     }
@@ -115,11 +605,11 @@ impl Refs {
         self.path.join("refs")
     }
 
-    fn head_path(&self) -> PathBuf {
-        self.path.join("HEAD")
+    fn packed_refs_path(&self) -> PathBuf {
+        self.path.join(PACKED_REFS)
     }
 
-    fn heads_path(&self) -> PathBuf {
-        self.refs_path().join("heads")
+    fn head_path(&self) -> PathBuf {
+        self.path.join(HEAD)
     }
 }
@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use failure::format_err;
+use failure::Error;
+
+const HEADER: &str = "# pack-refs with: sorted \n";
+const PEELED_PREFIX: char = '^';
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackedRef {
+    pub oid: String,
+    /// For annotated tags, the object the tag ultimately points at.
+    pub peeled: Option<String>,
+}
+
+/// The contents of `.git/packed-refs`, keyed by full ref name.
+#[derive(Clone, Debug, Default)]
+pub struct PackedRefs {
+    refs: BTreeMap<String, PackedRef>,
+}
+
+impl PackedRefs {
+    /// A missing file is the same as an empty one.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => PackedRefs::parse(&text),
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => Ok(PackedRefs::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut refs: BTreeMap<String, PackedRef> = BTreeMap::new();
+        let mut last: Option<String> = None;
+        for line in text.lines() {
+            if line.starts_with('#') || line.is_empty() {
+                continue;
+            }
+            if let Some(peeled) = line.strip_prefix(PEELED_PREFIX) {
+                let entry = last
+                    .as_ref()
+                    .and_then(|name| refs.get_mut(name))
+                    .ok_or_else(|| format_err!("unexpected line in packed-refs: {}", line))?;
+                entry.peeled = Some(peeled.trim().to_owned());
+                continue;
+            }
+            let mut parts = line.splitn(2, ' ');
+            match (parts.next(), parts.next()) {
+                (Some(oid), Some(name)) if oid.len() == 40 => {
+                    refs.insert(
+                        name.trim().to_owned(),
+                        PackedRef {
+                            oid: oid.to_owned(),
+                            peeled: None,
+                        },
+                    );
+                    last = Some(name.trim().to_owned());
+                }
+                _ => return Err(format_err!("unexpected line in packed-refs: {}", line)),
+            }
+        }
+        Ok(Self { refs })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PackedRef> {
+        self.refs.get(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.refs.contains_key(name)
+    }
+
+    pub fn insert(&mut self, name: &str, packed: PackedRef) {
+        self.refs.insert(name.to_owned(), packed);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<PackedRef> {
+        self.refs.remove(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &PackedRef)> {
+        self.refs.iter()
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut out = String::from(HEADER);
+        for (name, packed) in &self.refs {
+            out.push_str(&format!("{} {}\n", packed.oid, name));
+            if let Some(ref peeled) = packed.peeled {
+                out.push_str(&format!("{}{}\n", PEELED_PREFIX, peeled));
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PackedRef, PackedRefs};
+
+    const TAG: &str = "1111111111111111111111111111111111111111";
+    const COMMIT: &str = "2222222222222222222222222222222222222222";
+
+    #[test]
+    fn test_parse_peeled() {
+        let text = format!(
+            "# pack-refs with: peeled fully-peeled sorted \n{} refs/heads/master\n{} refs/tags/v1\n^{}\n",
+            COMMIT, TAG, COMMIT
+        );
+        let packed = PackedRefs::parse(&text).unwrap();
+        assert_eq!(
+            packed.get("refs/heads/master"),
+            Some(&PackedRef {
+                oid: COMMIT.to_owned(),
+                peeled: None
+            })
+        );
+        assert_eq!(
+            packed.get("refs/tags/v1").unwrap().peeled.as_deref(),
+            Some(COMMIT)
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let text = format!(
+            "# pack-refs with: sorted \n{} refs/heads/master\n{} refs/tags/v1\n^{}\n",
+            COMMIT, TAG, COMMIT
+        );
+        let packed = PackedRefs::parse(&text).unwrap();
+        assert_eq!(packed.serialize(), text);
+    }
+
+    #[test]
+    fn test_rejects_garbage() {
+        assert!(PackedRefs::parse("^1234\n").is_err());
+        assert!(PackedRefs::parse("not a ref\n").is_err());
+    }
+}
@@ -0,0 +1,119 @@
+use crate::author::Author;
+use crate::lockfile::Lockfile;
+use failure::format_err;
+use failure::Error;
+use std::convert::TryFrom;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+pub const ZERO_OID: &str = "0000000000000000000000000000000000000000";
+
+/// One line of a reflog: a ref moving from `old_oid` to `new_oid`.
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub old_oid: String,
+    pub new_oid: String,
+    pub committer: Author,
+    pub message: String,
+}
+
+impl Entry {
+    pub fn new(old_oid: Option<String>, new_oid: &str, message: &str) -> Self {
+        Self {
+            old_oid: old_oid.unwrap_or_else(|| String::from(ZERO_OID)),
+            new_oid: String::from(new_oid),
+            committer: Author::committer(),
+            // the log is line based, so only the first line of a message fits
+            message: message.lines().next().unwrap_or("").to_owned(),
+        }
+    }
+}
+
+impl TryFrom<&str> for Entry {
+    type Error = Error;
+
+    fn try_from(line: &str) -> Result<Self, Self::Error> {
+        let (header, message) = match line.find('\t') {
+            Some(pos) => (&line[..pos], &line[pos + 1..]),
+            None => (line, ""),
+        };
+        let mut parts = header.splitn(3, ' ');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(old_oid), Some(new_oid), Some(committer)) => Ok(Self {
+                old_oid: old_oid.to_owned(),
+                new_oid: new_oid.to_owned(),
+                committer: Author::try_from(committer)?,
+                message: message.to_owned(),
+            }),
+            _ => Err(format_err!("malformed reflog entry: {}", line)),
+        }
+    }
+}
+
+impl std::fmt::Display for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {}\t{}",
+            self.old_oid, self.new_oid, self.committer, self.message
+        )
+    }
+}
+
+/// Reads a reflog, oldest entry first. A missing log is empty.
+pub fn read<P: AsRef<Path>>(path: P) -> Result<Vec<Entry>, Error> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    text.lines()
+        .filter(|l| !l.is_empty())
+        .map(Entry::try_from)
+        .collect()
+}
+
+pub fn append<P: AsRef<Path>>(path: P, entry: &Entry) -> Result<(), Error> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", entry)?;
+    Ok(())
+}
+
+/// Replaces the whole log, as expiring entries does.
+pub fn write<P: AsRef<Path>>(path: P, entries: &[Entry]) -> Result<(), Error> {
+    let lock = Lockfile::new(path)?.try_lock()?;
+    for entry in entries {
+        lock.write_all(format!("{}\n", entry).as_bytes())?;
+    }
+    lock.commit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Entry;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn test_round_trip() {
+        let line = format!(
+            "{} {} A. U. Thor <author@example.com> 1500000000 +0000\tcheckout: moving from master to topic",
+            super::ZERO_OID,
+            "1111111111111111111111111111111111111111"
+        );
+        let entry = Entry::try_from(line.as_str()).unwrap();
+        assert_eq!(entry.old_oid, super::ZERO_OID);
+        assert_eq!(entry.message, "checkout: moving from master to topic");
+        assert_eq!(entry.committer.time().timestamp(), 1_500_000_000);
+    }
+
+    #[test]
+    fn test_message_is_single_line() {
+        let entry = Entry::new(None, super::ZERO_OID, "commit: title\n\nbody");
+        assert_eq!(entry.message, "commit: title");
+    }
+}
@@ -13,6 +13,7 @@ use std::fs::Metadata;
 use std::path::{Path, PathBuf};
 
 pub mod migration;
+pub mod pending_commit;
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Changed {
     Index,
@@ -50,6 +51,8 @@ pub struct Repository {
     pub refs: refs::Refs,
     pub index_changes: BTreeMap<String, Status>,
     pub workspace_changes: BTreeMap<String, Status>,
+    /// Unmerged paths, with the stages the index has for each.
+    pub conflicts: BTreeMap<String, Vec<u16>>,
     pub changed: Vec<String>,
     pub untracked: Vec<String>,
     pub stats: BTreeMap<PathBuf, Metadata>,
@@ -69,6 +72,7 @@ impl Repository {
         let changed = vec![];
         let index_changes = BTreeMap::new();
         let workspace_changes = BTreeMap::new();
+        let conflicts = BTreeMap::new();
         let stats = BTreeMap::new();
         let tree = BTreeMap::new();
 
@@ -81,21 +85,34 @@ impl Repository {
             changed,
             index_changes,
             workspace_changes,
+            conflicts,
             stats,
             tree,
         })
     }
 
     pub fn status(&mut self) -> BoxResult<()> {
+        let head = self.refs.get_head();
+        self.status_against(head)
+    }
+
+    /// Like `status`, but compares the index with the tree of `base` rather
+    /// than that of HEAD.
+    pub fn status_against(&mut self, base: Option<String>) -> BoxResult<()> {
         self.scan_workspace(None)?;
 
         let mut has_tree = false;
-        if let Some(head) = self.refs.get_head() {
+        if let Some(base) = base {
             has_tree = true;
-            self.read_tree(head.as_ref(), "".into())?;
+            self.read_tree(base.as_ref(), "".into())?;
         }
 
+        self.conflicts = self.index.conflicts();
         for entry in self.index.entries() {
+            if entry.stage() > 0 {
+                self.changed.push(entry.path.to_str().unwrap().to_owned());
+                continue;
+            }
             self.check_index_against_workspace(&entry)?;
             if has_tree {
                 self.check_index_against_tree(&entry)?;
@@ -178,28 +195,49 @@ impl Repository {
 
     fn check_index_against_workspace(&mut self, entry: &Entry) -> BoxResult<()> {
         let name = entry.path.to_str().unwrap().to_string();
-        let stat = self.stats.get(&entry.path);
-        if stat.is_none() {
-            self.record_change(name, Changed::Workspace, Status::Deleted);
-            return Ok(());
-        }
-        if entry.stat_match(stat) {
-            if entry.stat_times_match(stat) {
-                return Ok(());
-            }
-            let data = self.workspace.read_file(&entry.path)?;
-            let blob = Blob::new(data);
-            if entry.oid == blob.oid() {
-                self.index
-                    .add(&entry.path, blob.oid().as_ref(), stat.unwrap().clone());
-                return Ok(());
+        let stat = self.stats.get(&entry.path).cloned();
+        match self.compare_index_to_workspace(Some(entry), stat.as_ref())? {
+            Status::None => {
+                // the contents match, so refresh the stale stat information
+                if let Some(stat) = stat {
+                    if !entry.stat_times_match(Some(&stat)) {
+                        self.index.add(&entry.path, entry.oid(), stat);
+                    }
+                }
             }
+            status => self.record_change(name, Changed::Workspace, status),
         }
-        self.record_change(name, Changed::Workspace, Status::Modified);
         Ok(())
     }
 
-    fn trackable_file(&self, path: &Path, stat: std::fs::Metadata) -> bool {
+    /// How the workspace copy of a file differs from its index entry. A file
+    /// with no entry is untracked, which is reported as `Added`.
+    pub(crate) fn compare_index_to_workspace(
+        &self,
+        entry: Option<&Entry>,
+        stat: Option<&Metadata>,
+    ) -> Result<Status, Error> {
+        let entry = match entry {
+            Some(entry) => entry,
+            None => return Ok(Status::Added),
+        };
+        if stat.is_none() {
+            return Ok(Status::Deleted);
+        }
+        if !entry.stat_match(stat) {
+            return Ok(Status::Modified);
+        }
+        if entry.stat_times_match(stat) {
+            return Ok(Status::None);
+        }
+        let data = self.workspace.read_file(&entry.path)?;
+        if entry.oid != Blob::new(data).oid() {
+            return Ok(Status::Modified);
+        }
+        Ok(Status::None)
+    }
+
+    pub(crate) fn trackable_file(&self, path: &Path, stat: std::fs::Metadata) -> bool {
         if stat.is_file() {
             return !self.index.has_entry(path.to_str().unwrap());
         }
@@ -220,6 +258,12 @@ impl Repository {
         false
     }
 
+    /// Loads the files of a commit or tree into `tree`, keyed by path.
+    pub fn load_tree(&mut self, oid: &str) -> BoxResult<()> {
+        self.tree.clear();
+        self.read_tree(oid, PathBuf::new())
+    }
+
     fn read_tree(&mut self, oid: &str, path: PathBuf) -> BoxResult<()> {
         let (kind, _size, data) = self.database.read_object(oid)?;
         match kind {
@@ -1,7 +1,11 @@
 use crate::database::tree_diff::TreeDifference;
+use crate::index::entry::Entry;
+use crate::repository::{Repository, Status};
 use crate::tree::TreeEntry;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use failure::format_err;
+use failure::Error;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Action {
@@ -10,11 +14,69 @@ pub enum Action {
     Update,
 }
 
+/// The ways applying a migration could lose work, in the order git reports
+/// them.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Conflict {
+    StaleFile,
+    StaleDirectory,
+    UntrackedOverwritten,
+    UntrackedRemoved,
+}
+
+impl Conflict {
+    /// The header and footer git prints around the paths, worded for a
+    /// merge when `merging` and for a checkout otherwise.
+    fn messages(self, merging: bool) -> (String, String) {
+        let (command, action) = if merging {
+            ("merge", "merge")
+        } else {
+            ("checkout", "switch branches")
+        };
+        match self {
+            Conflict::StaleFile => (
+                format!(
+                    "Your local changes to the following files would be overwritten by {}:",
+                    command
+                ),
+                format!(
+                    "Please commit your changes or stash them before you {}.",
+                    action
+                ),
+            ),
+            Conflict::StaleDirectory => (
+                String::from(
+                    "Updating the following directories would lose untracked files in them:",
+                ),
+                String::from("\n"),
+            ),
+            Conflict::UntrackedOverwritten => (
+                format!(
+                    "The following untracked working tree files would be overwritten by {}:",
+                    command
+                ),
+                format!("Please move or remove them before you {}.", action),
+            ),
+            Conflict::UntrackedRemoved => (
+                format!(
+                    "The following untracked working tree files would be removed by {}:",
+                    command
+                ),
+                format!("Please move or remove them before you {}.", action),
+            ),
+        }
+    }
+}
+
 pub type MigrationChanges = HashMap<Action, Vec<(PathBuf, Option<TreeEntry>)>>;
 
 #[derive(Clone, Debug)]
 pub struct Migration {
     diff: TreeDifference,
+    /// Changes the caller makes itself, which are checked like the others
+    /// but not planned.
+    guarded: TreeDifference,
+    merging: bool,
     pub(crate) rmdirs: Vec<PathBuf>,
     pub(crate) mkdirs: Vec<PathBuf>,
     pub(crate) changes: MigrationChanges,
@@ -33,18 +95,40 @@ impl Migration {
 
         Migration {
             diff,
+            guarded: TreeDifference::new(),
+            merging: false,
             rmdirs: Vec::new(),
             mkdirs: Vec::new(),
             changes,
         }
     }
 
-    pub fn plan_changes(mut self) -> Migration {
+    /// Also refuses if making the changes in `guarded` would lose work, and
+    /// words any refusal for a merge.
+    pub fn for_merge(mut self, guarded: TreeDifference) -> Migration {
+        self.guarded = guarded;
+        self.merging = true;
+        self
+    }
+
+    /// Works out the changes to make, refusing with git's grouped error
+    /// messages if any of them would lose uncommitted work.
+    pub fn plan_changes(mut self, repo: &Repository) -> Result<Migration, Error> {
+        let mut conflicts: HashMap<Conflict, BTreeSet<String>> = HashMap::new();
+        for (path, (old, new)) in &self.guarded {
+            if let Some((conflict, path)) = check_for_conflict(repo, path, old, new)? {
+                conflicts.entry(conflict).or_default().insert(path);
+            }
+        }
         for (path, (old, new)) in self.diff.clone() {
+            if let Some((conflict, path)) = check_for_conflict(repo, &path, &old, &new)? {
+                conflicts.entry(conflict).or_default().insert(path);
+            }
             let ancestors = path
                 .parent()
                 .unwrap()
                 .ancestors()
+                .filter(|p| !p.as_os_str().is_empty())
                 .map(|p| p.to_path_buf())
                 .collect::<Vec<_>>();
 
@@ -62,6 +146,96 @@ impl Migration {
                 .entry(action)
                 .and_modify(|e| e.push((path, new)));
         }
-        self
+
+        if conflicts.is_empty() {
+            return Ok(self);
+        }
+        let mut errors: Vec<_> = conflicts.into_iter().collect();
+        errors.sort();
+        let mut message = String::new();
+        for (conflict, paths) in errors {
+            let (header, footer) = conflict.messages(self.merging);
+            message.push_str("error: ");
+            message.push_str(&header);
+            message.push('\n');
+            for path in paths {
+                message.push('\t');
+                message.push_str(&path);
+                message.push('\n');
+            }
+            message.push_str(&footer);
+            message.push('\n');
+        }
+        message.push_str("Aborting");
+        Err(format_err!("{}", message))
+    }
+}
+
+/// Whether replacing `old` with `new` at `path` would lose changes to the
+/// index or workspace, and if so which path to blame.
+fn check_for_conflict(
+    repo: &Repository,
+    path: &Path,
+    old: &Option<TreeEntry>,
+    new: &Option<TreeEntry>,
+) -> Result<Option<(Conflict, String)>, Error> {
+    let name = path.to_str().unwrap().to_owned();
+    let entry = repo.index.get_entry(&name);
+    if compare_tree_to_index(old.as_ref(), entry) != Status::None
+        && compare_tree_to_index(new.as_ref(), entry) != Status::None
+    {
+        return Ok(Some((Conflict::StaleFile, name)));
     }
+
+    let stat = repo.workspace.stat_file(&path.to_path_buf()).ok();
+    let conflict = match (entry, &stat) {
+        (Some(_), _) => Conflict::StaleFile,
+        (None, Some(stat)) if stat.is_dir() => Conflict::StaleDirectory,
+        (None, _) if new.is_some() => Conflict::UntrackedOverwritten,
+        (None, _) => Conflict::UntrackedRemoved,
+    };
+    let blamed = match stat {
+        // a file in the way of one of the path's directories
+        None => match untracked_parent(repo, path) {
+            Some(parent) if entry.is_none() => Some(parent),
+            Some(_) => Some(name),
+            None => None,
+        },
+        Some(ref stat) if stat.is_file() => {
+            if repo.compare_index_to_workspace(entry, Some(stat))? != Status::None {
+                Some(name)
+            } else {
+                None
+            }
+        }
+        Some(ref stat) if stat.is_dir() && repo.trackable_file(path, stat.clone()) => Some(name),
+        Some(_) => None,
+    };
+    Ok(blamed.map(|path| (conflict, path)))
+}
+
+fn compare_tree_to_index(item: Option<&TreeEntry>, entry: Option<&Entry>) -> Status {
+    match (item, entry) {
+        (None, None) => Status::None,
+        (None, Some(_)) => Status::Added,
+        (Some(_), None) => Status::Deleted,
+        (Some(item), Some(entry)) if item.oid() != entry.oid() || item.mode() != entry.mode() => {
+            Status::Modified
+        }
+        _ => Status::None,
+    }
+}
+
+/// The nearest parent directory of `path` that is an untracked file.
+fn untracked_parent(repo: &Repository, path: &Path) -> Option<String> {
+    path.parent()?
+        .ancestors()
+        .filter(|p| !p.as_os_str().is_empty())
+        .find(
+            |parent| match repo.workspace.stat_file(&parent.to_path_buf()) {
+                Ok(stat) if stat.is_file() => repo.trackable_file(parent, stat),
+                _ => false,
+            },
+        )
+        .map(|parent| parent.to_str().unwrap().to_owned())
 }
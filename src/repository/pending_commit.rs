@@ -0,0 +1,67 @@
+use crate::lockfile::Lockfile;
+use failure::format_err;
+use failure::Error;
+use std::path::{Path, PathBuf};
+
+const MERGE_HEAD: &str = "MERGE_HEAD";
+const MERGE_MSG: &str = "MERGE_MSG";
+
+/// A merge that stopped to have its conflicts resolved, which the next
+/// commit concludes. It is kept in `MERGE_HEAD` and `MERGE_MSG`, as git
+/// does.
+#[derive(Debug)]
+pub struct PendingCommit {
+    head_path: PathBuf,
+    message_path: PathBuf,
+}
+
+impl PendingCommit {
+    pub fn new<P: AsRef<Path>>(git_dir: P) -> Self {
+        let git_dir = git_dir.as_ref();
+        Self {
+            head_path: git_dir.join(MERGE_HEAD),
+            message_path: git_dir.join(MERGE_MSG),
+        }
+    }
+
+    /// Records that the merge of `oid` is waiting to be committed with
+    /// `message`.
+    pub fn start(&self, oid: &str, message: &str) -> Result<(), Error> {
+        let lock = Lockfile::new(&self.head_path)?.try_lock()?;
+        lock.write_all(format!("{}\n", oid).as_bytes())?;
+        lock.commit()?;
+
+        let lock = Lockfile::new(&self.message_path)?.try_lock()?;
+        lock.write_all(message.as_bytes())?;
+        lock.commit()
+    }
+
+    pub fn in_progress(&self) -> bool {
+        self.head_path.exists()
+    }
+
+    /// The commit being merged in.
+    pub fn merge_oid(&self) -> Result<String, Error> {
+        match std::fs::read_to_string(&self.head_path) {
+            Ok(oid) => Ok(oid.trim().to_owned()),
+            Err(_) => Err(format_err!(
+                "There is no merge in progress ({} missing).",
+                MERGE_HEAD
+            )),
+        }
+    }
+
+    pub fn merge_message(&self) -> Result<String, Error> {
+        Ok(std::fs::read_to_string(&self.message_path)?)
+    }
+
+    /// Forgets the merge, once it is committed or abandoned.
+    pub fn clear(&self) -> Result<(), Error> {
+        for path in &[&self.head_path, &self.message_path] {
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+}
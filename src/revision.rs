@@ -1,24 +1,205 @@
 use crate::commit::Commit;
 use crate::database::{ObjectKind, Storable};
-use crate::{commit, database, refs};
+use crate::refs::reflog;
+use crate::tree::TreeEntry;
+use crate::utilities::parse_date;
+use crate::{commit, database, index, refs, tag, tree};
+use chrono::Local;
 use failure::format_err;
 use failure::Error;
 use lazy_static::lazy_static;
 use regex::{Regex, RegexSet};
 use std::convert::TryFrom;
 
+pub mod walk;
+
+/// A parsed revision expression. The reflog forms are `<name>@{n}` and
+/// `<name>@{date}`, where an empty name means the current branch, and
+/// `@{-n}` for the branch checked out `n` checkouts ago.
+///
+/// The remaining forms name sets of commits rather than a single object:
+/// `A..B`, `A...B`, `^A`, `A^@` (all parents of A) and `A^!` (A without its
+/// parents). An empty side of a range means HEAD.
+///
+/// `A^{type}` peels A until it is an object of that type, with `A^{}`
+/// peeling tags only. `A:path` names an object in A's tree, and `:path` or
+/// `:n:path` an entry in the index.
 #[derive(Debug, PartialEq)]
 pub enum Revision {
-    Ref { name: String },
-    Parent { rev: Box<Revision> },
-    Ancestor { rev: Box<Revision>, n: usize },
+    Ref {
+        name: String,
+    },
+    Parent {
+        rev: Box<Revision>,
+    },
+    NthParent {
+        rev: Box<Revision>,
+        n: usize,
+    },
+    Ancestor {
+        rev: Box<Revision>,
+        n: usize,
+    },
+    ReflogIndex {
+        name: String,
+        n: usize,
+    },
+    ReflogDate {
+        name: String,
+        date: String,
+    },
+    PreviousCheckout {
+        n: usize,
+    },
+    Range {
+        from: Box<Revision>,
+        to: Box<Revision>,
+    },
+    SymmetricDifference {
+        left: Box<Revision>,
+        right: Box<Revision>,
+    },
+    Exclude {
+        rev: Box<Revision>,
+    },
+    AllParents {
+        rev: Box<Revision>,
+    },
+    ExcludeParents {
+        rev: Box<Revision>,
+    },
+    Peel {
+        rev: Box<Revision>,
+        kind: Option<ObjectKind>,
+    },
+    TreePath {
+        rev: Box<Revision>,
+        path: String,
+    },
+    IndexPath {
+        stage: u16,
+        path: String,
+    },
+}
+
+/// One end of a commit set: a commit a walk starts from, or one whose
+/// history it excludes. `left` marks the left side of `A...B`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tip {
+    pub oid: String,
+    pub exclude: bool,
+    pub left: bool,
+}
+
+impl Tip {
+    pub fn include(oid: String) -> Self {
+        Tip {
+            oid,
+            exclude: false,
+            left: false,
+        }
+    }
+
+    pub fn exclude(oid: String) -> Self {
+        Tip {
+            oid,
+            exclude: true,
+            left: false,
+        }
+    }
+}
+
+/// Splits `rev:path` at the first colon outside a reflog selector, so that
+/// `master@{10:00}` is left alone.
+fn split_tree_path(revision: &str) -> Option<(&str, &str)> {
+    let mut depth = 0;
+    for (i, c) in revision.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ':' if depth == 0 => return Some((&revision[..i], &revision[i + 1..])),
+            _ => {}
+        }
+    }
+    None
 }
 
 impl Revision {
+    /// Whether the revision names a set of commits rather than one object.
+    pub fn is_set(&self) -> bool {
+        matches!(
+            self,
+            Revision::Range { .. }
+                | Revision::SymmetricDifference { .. }
+                | Revision::Exclude { .. }
+                | Revision::AllParents { .. }
+                | Revision::ExcludeParents { .. }
+        )
+    }
+
     pub fn from(revision: &str) -> Result<Self, Error> {
-        if let Some(matches) = PARENT_RE.captures(revision) {
+        if let Some(path) = revision.strip_prefix(':') {
+            return Ok(match INDEX_STAGE_RE.captures(path) {
+                Some(matches) => Revision::IndexPath {
+                    stage: matches[1].parse()?,
+                    path: matches[2].to_owned(),
+                },
+                None => Revision::IndexPath {
+                    stage: 0,
+                    path: path.to_owned(),
+                },
+            });
+        } else if let Some((rev, path)) = split_tree_path(revision) {
+            return Ok(Revision::TreePath {
+                rev: Box::new(Revision::from(rev)?),
+                path: path.trim_matches('/').to_owned(),
+            });
+        }
+        let side =
+            |rev: &str| Revision::from(if rev.is_empty() { "HEAD" } else { rev }).map(Box::new);
+        if let Some((left, right)) = revision.split_once("...") {
+            return Ok(Revision::SymmetricDifference {
+                left: side(left)?,
+                right: side(right)?,
+            });
+        } else if let Some((from, to)) = revision.split_once("..") {
+            return Ok(Revision::Range {
+                from: side(from)?,
+                to: side(to)?,
+            });
+        } else if let Some(rev) = revision.strip_prefix('^') {
+            let rev = Revision::from(rev)?;
+            return Ok(Revision::Exclude { rev: Box::new(rev) });
+        } else if let Some(matches) = PEEL_RE.captures(revision) {
+            let rev = Revision::from(&matches[1])?;
+            let kind = match &matches[2] {
+                "" => None,
+                "object" => return Ok(rev),
+                "commit" => Some(ObjectKind::Commit),
+                "tree" => Some(ObjectKind::Tree),
+                "blob" => Some(ObjectKind::Blob),
+                "tag" => Some(ObjectKind::Tag),
+                _ => return Err(format_err!("fatal: Could not parse revision")),
+            };
+            return Ok(Revision::Peel {
+                rev: Box::new(rev),
+                kind,
+            });
+        } else if let Some(rev) = revision.strip_suffix("^@") {
+            let rev = Revision::from(rev)?;
+            return Ok(Revision::AllParents { rev: Box::new(rev) });
+        } else if let Some(rev) = revision.strip_suffix("^!") {
+            let rev = Revision::from(rev)?;
+            return Ok(Revision::ExcludeParents { rev: Box::new(rev) });
+        } else if let Some(matches) = PARENT_RE.captures(revision) {
             let rev = Revision::from(&matches[1])?;
             return Ok(Revision::Parent { rev: Box::new(rev) });
+        } else if let Some(matches) = NTH_PARENT_RE.captures(revision) {
+            let rev = Revision::from(&matches[1])?;
+            return Ok(Revision::NthParent {
+                rev: Box::new(rev),
+                n: matches[2].parse()?,
+            });
         } else if let Some(matches) = ANCESTOR_RE.captures(revision) {
             let rev = Revision::from(&matches[1])?;
             let n: &usize = &matches[2].parse()?;
@@ -26,6 +207,26 @@ impl Revision {
                 rev: Box::new(rev),
                 n: n.to_owned(),
             });
+        } else if let Some(matches) = REFLOG_RE.captures(revision) {
+            let name = &matches[1];
+            let selector = &matches[2];
+            if !name.is_empty() && INVALID_NAME.is_match(name) {
+                return Err(format_err!("fatal: Could not parse revision"));
+            }
+            if let Some(n) = selector.strip_prefix('-') {
+                if name.is_empty() {
+                    return Ok(Revision::PreviousCheckout { n: n.parse()? });
+                }
+                return Err(format_err!("fatal: Could not parse revision"));
+            }
+            let name = if name == "@" { "HEAD" } else { name }.to_owned();
+            return Ok(match selector.parse() {
+                Ok(n) => Revision::ReflogIndex { name, n },
+                Err(_) => Revision::ReflogDate {
+                    name,
+                    date: selector.to_owned(),
+                },
+            });
         } else if !INVALID_NAME.is_match(revision) {
             let name = if revision == "@" { "HEAD" } else { revision };
             return Ok(Revision::Ref {
@@ -71,7 +272,10 @@ impl<'a> RevisionResolver<'a> {
 
     pub fn resolver(&mut self, kind: ObjectKind) -> Result<String, Error> {
         let rev = Revision::from(self.expr.as_ref())?;
-        if let Some(oid) = self.resolv(rev) {
+        if let Some(mut oid) = self.resolv(rev) {
+            if kind != ObjectKind::Tag {
+                oid = self.peel_tags(oid);
+            }
             let (k, _, _) = self.db.read_object(oid.as_ref())?;
             if kind == k {
                 return Ok(oid);
@@ -91,24 +295,287 @@ impl<'a> RevisionResolver<'a> {
             Revision::Ref { name } => self.read_ref(name.as_ref()),
             Revision::Parent { rev } => {
                 let oid = self.resolv(*rev);
-                self.commit_parent(oid)
+                self.commit_parent(oid, 1)
+            }
+            Revision::NthParent { rev, n } => {
+                let oid = self.resolv(*rev);
+                self.commit_parent(oid, n)
             }
             Revision::Ancestor { rev, n } => {
                 let mut oid = self.resolv(*rev);
                 for _ in 1..=n {
-                    oid = self.commit_parent(oid);
+                    oid = self.commit_parent(oid, 1);
                 }
                 oid
             }
+            Revision::ReflogIndex { name, n } => self.reflog_index(&name, n),
+            Revision::ReflogDate { name, date } => self.reflog_date(&name, &date),
+            Revision::PreviousCheckout { n } => {
+                let name = self.refs.previous_checkout(n);
+                if name.is_none() {
+                    self.push_error(format!("{} checkouts ago is not in the reflog", n));
+                }
+                self.read_ref(&name?)
+            }
+            Revision::Peel { rev, kind } => {
+                let oid = self.resolv(*rev)?;
+                self.peel(oid, kind)
+            }
+            Revision::TreePath { rev, path } => {
+                let oid = self.resolv(*rev)?;
+                let tree = self.peel(oid, Some(ObjectKind::Tree))?;
+                self.tree_path(tree, &path)
+            }
+            Revision::IndexPath { stage, path } => self.index_path(stage, &path),
+            Revision::Range { .. }
+            | Revision::SymmetricDifference { .. }
+            | Revision::Exclude { .. }
+            | Revision::AllParents { .. }
+            | Revision::ExcludeParents { .. } => {
+                self.push_error(format!("'{}' names more than one commit", self.expr));
+                None
+            }
         }
     }
 
-    fn commit_parent(&self, rev: Option<String>) -> Option<String> {
-        if let Some(rev) = rev {
+    /// Resolves the expression to an object of any type.
+    pub fn resolve_object(&mut self) -> Result<String, Error> {
+        let rev = Revision::from(self.expr.as_ref())?;
+        self.resolv(rev)
+            .ok_or_else(|| format_err!("Not a valid object name: '{}'", self.expr))
+    }
+
+    /// Resolves an expression that may name a set of commits, such as a
+    /// range, into the tips a walk should start from and stop at.
+    pub fn resolve_tips(&mut self) -> Result<Vec<Tip>, Error> {
+        let rev = Revision::from(self.expr.as_ref())?;
+        self.tips(rev)
+            .ok_or_else(|| format_err!("Not a valid object name: '{}'", self.expr))
+    }
+
+    fn tips(&mut self, rev: Revision) -> Option<Vec<Tip>> {
+        match rev {
+            Revision::Range { from, to } => {
+                let from = self.commit_oid(*from)?;
+                let to = self.commit_oid(*to)?;
+                Some(vec![Tip::include(to), Tip::exclude(from)])
+            }
+            Revision::SymmetricDifference { left, right } => {
+                let left = self.commit_oid(*left)?;
+                let right = self.commit_oid(*right)?;
+                let bases = walk::merge_bases(self.db, &left, &right).ok()?;
+                let mut tips = vec![
+                    Tip {
+                        left: true,
+                        ..Tip::include(left)
+                    },
+                    Tip::include(right),
+                ];
+                tips.extend(bases.into_iter().map(Tip::exclude));
+                Some(tips)
+            }
+            Revision::Exclude { rev } => Some(vec![Tip::exclude(self.commit_oid(*rev)?)]),
+            Revision::AllParents { rev } => {
+                let oid = self.commit_oid(*rev)?;
+                let commit = self.load_commit(&oid)?;
+                Some(commit.parents.into_iter().map(Tip::include).collect())
+            }
+            Revision::ExcludeParents { rev } => {
+                let oid = self.commit_oid(*rev)?;
+                let commit = self.load_commit(&oid)?;
+                let mut tips = vec![Tip::include(oid)];
+                tips.extend(commit.parents.into_iter().map(Tip::exclude));
+                Some(tips)
+            }
+            rev => Some(vec![Tip::include(self.commit_oid(rev)?)]),
+        }
+    }
+
+    fn commit_oid(&mut self, rev: Revision) -> Option<String> {
+        let oid = self.resolv(rev)?;
+        let oid = self.peel_tags(oid);
+        match self.db.read_object(&oid) {
+            Ok((kind, _, _)) if kind.is_commit() => Some(oid),
+            Ok((kind, _, _)) => {
+                self.push_error(format!("object {} is a {}, not a commit", oid, kind));
+                None
+            }
+            Err(_) => None,
+        }
+    }
+
+    fn load_commit(&self, oid: &str) -> Option<Commit> {
+        let (_, _, data) = self.db.read_object(oid).ok()?;
+        Commit::try_from(data).ok()
+    }
+
+    /// The full name of the ref whose reflog `name@{...}` reads.
+    fn reflog_name(&mut self, name: &str) -> Option<String> {
+        if name.is_empty() {
+            return Some(self.refs.current_ref().unwrap_or_else(|| "HEAD".to_owned()));
+        }
+        let full_name = self.refs.expand_name(name);
+        if full_name.is_none() {
+            self.push_error(format!("no reflog for '{}'", name));
+        }
+        full_name
+    }
+
+    /// The reflog of `full_name`, which `name` asked for, provided it has
+    /// at least one entry.
+    fn reflog_entries(&mut self, name: &str, full_name: &str) -> Option<Vec<reflog::Entry>> {
+        let entries = self.refs.reflog(full_name).ok()?;
+        if entries.is_empty() {
+            let name = if name.is_empty() {
+                self.refs.short_name(full_name)
+            } else {
+                name.to_owned()
+            };
+            self.push_error(format!("log for '{}' is empty", name));
+            return None;
+        }
+        Some(entries)
+    }
+
+    fn reflog_index(&mut self, name: &str, n: usize) -> Option<String> {
+        let full_name = self.reflog_name(name)?;
+        let entries = self.reflog_entries(name, &full_name)?;
+        if n < entries.len() {
+            Some(entries[entries.len() - 1 - n].new_oid.clone())
+        } else if n == entries.len() && entries[0].old_oid != reflog::ZERO_OID {
+            Some(entries[0].old_oid.clone())
+        } else {
+            let message = format!(
+                "log for '{}' only has {} entries",
+                self.refs.short_name(&full_name),
+                entries.len()
+            );
+            self.push_error(message);
+            None
+        }
+    }
+
+    fn reflog_date(&mut self, name: &str, date: &str) -> Option<String> {
+        let full_name = self.reflog_name(name)?;
+        let time = match parse_date(date, Local::now()) {
+            Some(time) => time,
+            None => {
+                self.push_error(format!("invalid date '{}'", date));
+                return None;
+            }
+        };
+        let entries = self.reflog_entries(name, &full_name)?;
+        if let Some(entry) = entries.iter().rev().find(|e| e.committer.time() <= time) {
+            return Some(entry.new_oid.clone());
+        }
+        // older than the whole log: the best answer is where the log began
+        let first = entries.first()?;
+        if first.old_oid != reflog::ZERO_OID {
+            Some(first.old_oid.clone())
+        } else {
+            Some(first.new_oid.clone())
+        }
+    }
+
+    /// Dereferences `oid` until it is an object of type `kind`: a tag peels
+    /// to the object it tags and a commit to its tree. With no kind only
+    /// tags are peeled.
+    fn peel(&mut self, oid: String, kind: Option<ObjectKind>) -> Option<String> {
+        let (found, _, data) = self.db.read_object(&oid).ok()?;
+        let kind = match kind {
+            Some(kind) if kind == found => return Some(oid),
+            Some(kind) => kind,
+            None if found == ObjectKind::Tag => return Some(self.peel_tags(oid)),
+            None => return Some(oid),
+        };
+        if found == ObjectKind::Tag {
+            let target = tag::Tag::try_from(data).ok()?.object;
+            return self.peel(target, Some(kind));
+        }
+        if found == ObjectKind::Commit && kind == ObjectKind::Tree {
+            return Commit::try_from(data).ok().map(|c| c.tree);
+        }
+        let message = format!(
+            "{}: expected {} type, but the object dereferences to {} type",
+            self.expr, kind, found
+        );
+        self.push_error(message);
+        None
+    }
+
+    /// Follows annotated tags to the first object that is not a tag.
+    fn peel_tags(&self, mut oid: String) -> String {
+        while let Ok((ObjectKind::Tag, _, data)) = self.db.read_object(&oid) {
+            match tag::Tag::try_from(data) {
+                Ok(tag) => oid = tag.object,
+                Err(_) => break,
+            }
+        }
+        oid
+    }
+
+    fn tree_path(&mut self, tree: String, path: &str) -> Option<String> {
+        let mut oid = tree;
+        for name in path.split('/').filter(|n| !n.is_empty()) {
+            let entry = match self.db.read_object(&oid) {
+                Ok((ObjectKind::Tree, _, data)) => tree::Tree::try_from(data)
+                    .ok()?
+                    .get_entry(name)
+                    .map(TreeEntry::oid),
+                _ => None,
+            };
+            oid = match entry {
+                Some(oid) => oid,
+                None => {
+                    let rev = split_tree_path(&self.expr).map_or("", |(rev, _)| rev);
+                    self.push_error(format!("path '{}' does not exist in '{}'", path, rev));
+                    return None;
+                }
+            };
+        }
+        Some(oid)
+    }
+
+    fn index_path(&mut self, stage: u16, path: &str) -> Option<String> {
+        let index = index::Index::from(self.refs.git_dir().join("index")).ok()?;
+        let oid = index.get_stage(path, stage).map(|e| e.oid.clone());
+        let tracked = (0..=3).any(|n| index.get_stage(path, n).is_some());
+        index.release_lock().ok()?;
+        match oid {
+            Some(oid) => Some(oid),
+            None if tracked => {
+                let message = format!(
+                    "path '{}' is in the index, but not at stage {}",
+                    path, stage
+                );
+                self.push_error(message);
+                None
+            }
+            None => {
+                self.push_error(format!("path '{}' does not exist in the index", path));
+                None
+            }
+        }
+    }
+
+    fn push_error(&mut self, message: String) {
+        self.errors.push(HintedError {
+            message,
+            hints: vec![],
+        });
+    }
+
+    /// The `n`th parent of a commit, counting from one; `^0` is the commit
+    /// itself.
+    fn commit_parent(&self, rev: Option<String>, n: usize) -> Option<String> {
+        if let Some(rev) = rev.map(|oid| self.peel_tags(oid)) {
             if let Ok((kind, _size, data)) = self.db.read_object(rev.as_ref()) {
                 if kind.is_commit() {
+                    if n == 0 {
+                        return Some(rev);
+                    }
                     if let Ok(commit) = commit::Commit::try_from(data) {
-                        return commit.parent;
+                        return commit.parents.get(n - 1).cloned();
                     }
                 }
             }
@@ -166,7 +633,11 @@ impl<'a> RevisionResolver<'a> {
 
 lazy_static! {
     static ref PARENT_RE: Regex = Regex::new(r"^(.+)\^$").unwrap();
+    static ref NTH_PARENT_RE: Regex = Regex::new(r"^(.+)\^(\d+)$").unwrap();
     static ref ANCESTOR_RE: Regex = Regex::new(r"^(.+)~(\d+)$").unwrap();
+    static ref PEEL_RE: Regex = Regex::new(r"^(.+)\^\{(\w*)\}$").unwrap();
+    static ref INDEX_STAGE_RE: Regex = Regex::new(r"^([0-3]):(.*)$").unwrap();
+    static ref REFLOG_RE: Regex = Regex::new(r"^(.*)@\{([^}]+)\}$").unwrap();
     pub static ref INVALID_NAME: RegexSet = RegexSet::new(&[
         r"^\.",
         r"/\.",
@@ -182,6 +653,7 @@ lazy_static! {
 #[cfg(test)]
 mod test {
     use super::Revision;
+    use crate::database::ObjectKind;
 
     #[test]
     fn parse_head_parent() {
@@ -245,4 +717,149 @@ mod test {
         )
     }
 
+    #[test]
+    fn parse_nth_parent() {
+        let parsed = Revision::from("HEAD^2~3").unwrap();
+        assert_eq!(
+            parsed,
+            Revision::Ancestor {
+                rev: Box::new(Revision::NthParent {
+                    rev: Box::new(Revision::Ref {
+                        name: "HEAD".to_owned()
+                    }),
+                    n: 2,
+                }),
+                n: 3,
+            }
+        )
+    }
+
+    #[test]
+    fn parse_reflog_index() {
+        let parsed = Revision::from("master@{2}^").unwrap();
+        assert_eq!(
+            parsed,
+            Revision::Parent {
+                rev: Box::new(Revision::ReflogIndex {
+                    name: "master".to_owned(),
+                    n: 2,
+                }),
+            }
+        );
+        assert_eq!(
+            Revision::from("@{1}").unwrap(),
+            Revision::ReflogIndex {
+                name: "".to_owned(),
+                n: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_reflog_date() {
+        assert_eq!(
+            Revision::from("HEAD@{2.days.ago}").unwrap(),
+            Revision::ReflogDate {
+                name: "HEAD".to_owned(),
+                date: "2.days.ago".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_previous_checkout() {
+        assert_eq!(
+            Revision::from("@{-1}~2").unwrap(),
+            Revision::Ancestor {
+                rev: Box::new(Revision::PreviousCheckout { n: 1 }),
+                n: 2,
+            }
+        );
+        assert!(Revision::from("master@{-1}").is_err());
+    }
+
+    #[test]
+    fn parse_ranges() {
+        let r = |name: &str| {
+            Box::new(Revision::Ref {
+                name: name.to_owned(),
+            })
+        };
+        assert_eq!(
+            Revision::from("master..topic").unwrap(),
+            Revision::Range {
+                from: r("master"),
+                to: r("topic"),
+            }
+        );
+        assert_eq!(
+            Revision::from("...topic").unwrap(),
+            Revision::SymmetricDifference {
+                left: r("HEAD"),
+                right: r("topic"),
+            }
+        );
+        assert_eq!(
+            Revision::from("^topic~2").unwrap(),
+            Revision::Exclude {
+                rev: Box::new(Revision::Ancestor {
+                    rev: r("topic"),
+                    n: 2,
+                }),
+            }
+        );
+        assert_eq!(
+            Revision::from("HEAD^@").unwrap(),
+            Revision::AllParents { rev: r("HEAD") }
+        );
+        assert_eq!(
+            Revision::from("HEAD^!").unwrap(),
+            Revision::ExcludeParents { rev: r("HEAD") }
+        );
+    }
+
+    #[test]
+    fn parse_peel_and_paths() {
+        let head = || {
+            Box::new(Revision::Ref {
+                name: "HEAD".to_owned(),
+            })
+        };
+        assert_eq!(
+            Revision::from("HEAD^{tree}").unwrap(),
+            Revision::Peel {
+                rev: head(),
+                kind: Some(ObjectKind::Tree),
+            }
+        );
+        assert_eq!(
+            Revision::from("HEAD^{}").unwrap(),
+            Revision::Peel {
+                rev: head(),
+                kind: None,
+            }
+        );
+        assert!(Revision::from("HEAD^{nonsense}").is_err());
+        assert_eq!(
+            Revision::from("HEAD:src/main.rs").unwrap(),
+            Revision::TreePath {
+                rev: head(),
+                path: "src/main.rs".to_owned(),
+            }
+        );
+        assert_eq!(
+            Revision::from(":2:a..b").unwrap(),
+            Revision::IndexPath {
+                stage: 2,
+                path: "a..b".to_owned(),
+            }
+        );
+        assert_eq!(
+            Revision::from("master@{10:00}").unwrap(),
+            Revision::ReflogDate {
+                name: "master".to_owned(),
+                date: "10:00".to_owned(),
+            }
+        );
+    }
 }
@@ -0,0 +1,582 @@
+use crate::commit::Commit;
+use crate::database::{Database, ObjectKind};
+use crate::revision::Tip;
+use failure::format_err;
+use failure::Error;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+
+/// How many more commits to look at once only uninteresting ones are left,
+/// in case clock skew hides an interesting commit behind them.
+const SLOP: usize = 5;
+
+const PARENT1: u8 = 1;
+const PARENT2: u8 = 2;
+const STALE: u8 = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Order {
+    /// Newest commit first.
+    Date,
+    /// No parent before all of its children, keeping lines of history
+    /// together.
+    Topo,
+}
+
+/// A commit waiting in the walk's queue. Newer commits come out first, and
+/// commits with the same date in the order they were queued.
+struct Pending {
+    time: i64,
+    seq: Reverse<usize>,
+    oid: String,
+}
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Pending {}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.time, self.seq).cmp(&(other.time, other.seq))
+    }
+}
+
+/// Walks history from a set of starting commits, excluding everything
+/// reachable from the hidden ones.
+///
+/// When limited to paths, commits that leave those paths as they were in a
+/// parent are skipped, and history is followed through that parent only.
+/// With an ancestry path only commits descending from a hidden one are
+/// shown.
+///
+/// Commits are read once and kept for the rest of the walk. Date order
+/// without hidden commits streams; anything else walks the whole range on
+/// the first call to `next_commit`.
+pub struct RevWalk<'a> {
+    db: &'a Database,
+    commits: HashMap<String, Commit>,
+    queue: BinaryHeap<Pending>,
+    seen: HashSet<String>,
+    uninteresting: HashSet<String>,
+    bottoms: Vec<String>,
+    output: Option<VecDeque<String>>,
+    paths: Vec<PathBuf>,
+    seq: usize,
+    order: Order,
+    reverse: bool,
+    first_parent: bool,
+    ancestry_path: bool,
+}
+
+impl<'a> RevWalk<'a> {
+    pub fn new(db: &'a Database) -> Self {
+        Self {
+            db,
+            commits: HashMap::new(),
+            queue: BinaryHeap::new(),
+            seen: HashSet::new(),
+            uninteresting: HashSet::new(),
+            bottoms: vec![],
+            output: None,
+            paths: vec![],
+            seq: 0,
+            order: Order::Date,
+            reverse: false,
+            first_parent: false,
+            ancestry_path: false,
+        }
+    }
+
+    /// Starts the walk from `oid`.
+    pub fn push(&mut self, oid: &str) -> Result<(), Error> {
+        self.enqueue(oid)
+    }
+
+    /// Excludes `oid` and all of its ancestors from the walk.
+    pub fn hide(&mut self, oid: &str) -> Result<(), Error> {
+        self.enqueue(oid)?;
+        self.mark_uninteresting(oid);
+        self.bottoms.push(oid.to_owned());
+        Ok(())
+    }
+
+    pub fn push_tip(&mut self, tip: &Tip) -> Result<(), Error> {
+        if tip.exclude {
+            self.hide(&tip.oid)
+        } else {
+            self.push(&tip.oid)
+        }
+    }
+
+    pub fn set_paths(&mut self, paths: Vec<PathBuf>) {
+        self.paths = paths;
+    }
+
+    pub fn set_order(&mut self, order: Order) {
+        self.order = order;
+    }
+
+    /// Yields the oldest commits first.
+    pub fn set_reverse(&mut self, reverse: bool) {
+        self.reverse = reverse;
+    }
+
+    /// Follows only the first parent of merge commits.
+    pub fn set_first_parent(&mut self, first_parent: bool) {
+        self.first_parent = first_parent;
+    }
+
+    /// Shows only commits that are descendants of a hidden commit as well
+    /// as ancestors of a starting one.
+    pub fn set_ancestry_path(&mut self, ancestry_path: bool) {
+        self.ancestry_path = ancestry_path;
+    }
+
+    /// The next commit in the walk, with its oid.
+    pub fn next_commit(&mut self) -> Result<Option<(String, Commit)>, Error> {
+        if self.is_limited() {
+            if self.output.is_none() {
+                let list = self.limit()?;
+                self.output = Some(list.into());
+            }
+            let next = self.output.as_mut().and_then(VecDeque::pop_front);
+            return Ok(next.map(|oid| {
+                let commit = self.commits[&oid].clone();
+                (oid, commit)
+            }));
+        }
+
+        while let Some(Pending { oid, .. }) = self.queue.pop() {
+            if self.process(&oid)? {
+                let commit = self.commits[&oid].clone();
+                return Ok(Some((oid, commit)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn is_limited(&self) -> bool {
+        !self.uninteresting.is_empty() || self.order == Order::Topo || self.reverse
+    }
+
+    /// Walks until only uninteresting commits are left, returning the
+    /// interesting ones in the order they should be shown.
+    fn limit(&mut self) -> Result<Vec<String>, Error> {
+        let mut list = vec![];
+        let mut slop = SLOP;
+        while let Some(Pending { oid, .. }) = self.queue.pop() {
+            if self.process(&oid)? {
+                list.push(oid);
+            }
+            if self.everybody_uninteresting() {
+                if slop == 0 {
+                    break;
+                }
+                slop -= 1;
+            } else {
+                slop = SLOP;
+            }
+        }
+        list.retain(|oid| !self.uninteresting.contains(oid));
+        if self.ancestry_path && !self.bottoms.is_empty() {
+            list = self.limit_to_ancestry(list);
+        }
+
+        if self.order == Order::Topo {
+            list = self.topo_sort(list);
+        }
+        if self.reverse {
+            list.reverse();
+        }
+        Ok(list)
+    }
+
+    /// Queues the parents of a commit taken off the queue, and says whether
+    /// the commit itself belongs in the output.
+    fn process(&mut self, oid: &str) -> Result<bool, Error> {
+        let mut parents = self.commits[oid].parents.clone();
+        if self.uninteresting.contains(oid) {
+            for parent in &parents {
+                self.enqueue(parent)?;
+                self.mark_uninteresting(parent);
+            }
+            return Ok(false);
+        }
+        if self.first_parent {
+            parents.truncate(1);
+        }
+
+        if self.paths.is_empty() {
+            for parent in &parents {
+                self.enqueue(parent)?;
+            }
+            return Ok(true);
+        }
+
+        if parents.is_empty() {
+            return Ok(self.touches_paths(None, oid));
+        }
+        let same = parents
+            .iter()
+            .find(|p| !self.touches_paths(Some(p.to_string()), oid));
+        match same {
+            Some(parent) => {
+                self.enqueue(parent)?;
+                Ok(false)
+            }
+            None => {
+                for parent in &parents {
+                    self.enqueue(parent)?;
+                }
+                Ok(true)
+            }
+        }
+    }
+
+    fn enqueue(&mut self, oid: &str) -> Result<(), Error> {
+        if self.seen.insert(oid.to_owned()) {
+            let pending = self.pending(oid)?;
+            self.queue.push(pending);
+        }
+        Ok(())
+    }
+
+    /// Reads `oid` if it has not been read yet and makes a queue entry for
+    /// it.
+    fn pending(&mut self, oid: &str) -> Result<Pending, Error> {
+        if !self.commits.contains_key(oid) {
+            let commit = self.load(oid)?;
+            self.commits.insert(oid.to_owned(), commit);
+        }
+        self.seq += 1;
+        Ok(Pending {
+            time: self.commits[oid].committer.time().timestamp(),
+            seq: Reverse(self.seq),
+            oid: oid.to_owned(),
+        })
+    }
+
+    /// Marks `oid` uninteresting, along with every ancestor already read.
+    /// Ancestors not read yet pick the mark up when they are processed.
+    fn mark_uninteresting(&mut self, oid: &str) {
+        let mut stack = vec![oid.to_owned()];
+        while let Some(oid) = stack.pop() {
+            self.uninteresting.insert(oid.clone());
+            if let Some(commit) = self.commits.get(&oid) {
+                stack.extend(
+                    commit
+                        .parents
+                        .iter()
+                        .filter(|p| !self.uninteresting.contains(*p))
+                        .cloned(),
+                );
+            }
+        }
+    }
+
+    fn everybody_uninteresting(&self) -> bool {
+        self.queue
+            .iter()
+            .all(|pending| self.uninteresting.contains(&pending.oid))
+    }
+
+    /// The parents the walk follows from `oid`.
+    fn walked_parents(&self, oid: &str) -> &[String] {
+        let parents = &self.commits[oid].parents;
+        let count = if self.first_parent { 1 } else { parents.len() };
+        &parents[..count.min(parents.len())]
+    }
+
+    /// Drops the commits in `list` that no hidden commit is an ancestor of.
+    fn limit_to_ancestry(&self, list: Vec<String>) -> Vec<String> {
+        let mut descendants: HashSet<&str> = self.bottoms.iter().map(String::as_str).collect();
+        // parents come after their children, so work up from the oldest
+        // until nothing new is found, in case of clock skew
+        loop {
+            let mut changed = false;
+            for oid in list.iter().rev() {
+                if !descendants.contains(oid.as_str())
+                    && self
+                        .walked_parents(oid)
+                        .iter()
+                        .any(|p| descendants.contains(p.as_str()))
+                {
+                    descendants.insert(oid);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        list.iter()
+            .filter(|oid| descendants.contains(oid.as_str()))
+            .cloned()
+            .collect()
+    }
+
+    fn topo_sort(&self, list: Vec<String>) -> Vec<String> {
+        let parents = |oid: &str| self.walked_parents(oid).to_vec();
+
+        let mut indegree: HashMap<String, usize> =
+            list.iter().map(|oid| (oid.clone(), 0)).collect();
+        for oid in &list {
+            for parent in parents(oid) {
+                if let Some(n) = indegree.get_mut(&parent) {
+                    *n += 1;
+                }
+            }
+        }
+
+        let mut stack: Vec<String> = list
+            .iter()
+            .rev()
+            .filter(|oid| indegree[*oid] == 0)
+            .cloned()
+            .collect();
+        let mut sorted = Vec::with_capacity(list.len());
+        while let Some(oid) = stack.pop() {
+            for parent in parents(&oid) {
+                if let Some(n) = indegree.get_mut(&parent) {
+                    *n -= 1;
+                    if *n == 0 {
+                        stack.push(parent);
+                    }
+                }
+            }
+            sorted.push(oid);
+        }
+        sorted
+    }
+
+    /// Whether `oid` changes anything under the walk's paths compared with
+    /// `parent`.
+    fn touches_paths(&self, parent: Option<String>, oid: &str) -> bool {
+        self.db
+            .tree_diff(parent, Some(oid.to_owned()))
+            .keys()
+            .any(|path| matches_paths(&self.paths, path))
+    }
+
+    fn load(&self, oid: &str) -> Result<Commit, Error> {
+        let (kind, _, data) = self.db.read_object(oid)?;
+        if kind != ObjectKind::Commit {
+            return Err(format_err!("object {} is a {}, not a commit", oid, kind));
+        }
+        Commit::try_from(data)
+    }
+}
+
+/// The best common ancestors of two commits: those reachable from both that
+/// are not ancestors of another such commit.
+pub fn merge_bases(db: &Database, one: &str, two: &str) -> Result<Vec<String>, Error> {
+    if one == two {
+        return Ok(vec![one.to_owned()]);
+    }
+
+    // Paint everything reachable from each side, newest first, and stop
+    // once every queued commit is below a common ancestor already found.
+    let mut walk = RevWalk::new(db);
+    let mut flags: HashMap<String, u8> = HashMap::new();
+    let mut queue = BinaryHeap::new();
+    flags.insert(one.to_owned(), PARENT1);
+    queue.push(walk.pending(one)?);
+    *flags.entry(two.to_owned()).or_default() |= PARENT2;
+    queue.push(walk.pending(two)?);
+
+    let mut bases = vec![];
+    while queue.iter().any(|p| flags[&p.oid] & STALE == 0) {
+        let oid = match queue.pop() {
+            Some(pending) => pending.oid,
+            None => break,
+        };
+        let mut flag = flags[&oid];
+        if flag & (PARENT1 | PARENT2) == PARENT1 | PARENT2 {
+            if !bases.contains(&oid) {
+                bases.push(oid.clone());
+            }
+            flag |= STALE;
+        }
+        for parent in walk.commits[&oid].parents.clone() {
+            let parent_flag = flags.entry(parent.clone()).or_default();
+            if *parent_flag & flag == flag {
+                continue;
+            }
+            *parent_flag |= flag;
+            queue.push(walk.pending(&parent)?);
+        }
+    }
+    bases.retain(|oid| flags[oid] & STALE == 0);
+
+    let mut redundant = HashSet::new();
+    for base in &bases {
+        for other in &bases {
+            if base != other && !redundant.contains(other) && is_ancestor(db, base, other)? {
+                redundant.insert(base.clone());
+            }
+        }
+    }
+    bases.retain(|oid| !redundant.contains(oid));
+    Ok(bases)
+}
+
+/// Whether `ancestor` is reachable from `descendant`. A commit is its own
+/// ancestor.
+pub fn is_ancestor(db: &Database, ancestor: &str, descendant: &str) -> Result<bool, Error> {
+    let mut walk = RevWalk::new(db);
+    walk.push(descendant)?;
+    while let Some((oid, _)) = walk.next_commit()? {
+        if oid == ancestor {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// How many commits are reachable from `one` but not `two`, and from `two`
+/// but not `one`.
+pub fn ahead_behind(db: &Database, one: &str, two: &str) -> Result<(usize, usize), Error> {
+    let count = |from: &str, hide: &str| -> Result<usize, Error> {
+        let mut walk = RevWalk::new(db);
+        walk.push(from)?;
+        walk.hide(hide)?;
+        let mut count = 0;
+        while walk.next_commit()?.is_some() {
+            count += 1;
+        }
+        Ok(count)
+    };
+    Ok((count(one, two)?, count(two, one)?))
+}
+
+/// Whether `path` is one of `paths` or lies inside one of them. An empty
+/// list matches everything.
+pub fn matches_paths<P: AsRef<Path>>(paths: &[PathBuf], path: P) -> bool {
+    paths.is_empty() || paths.iter().any(|p| path.as_ref().starts_with(p))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_ancestor, merge_bases, Order, RevWalk};
+    use crate::author::Author;
+    use crate::commit::Commit;
+    use crate::database::{Database, Storable};
+    use chrono::{Local, TimeZone};
+    use tempdir::TempDir;
+
+    const TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+    fn commit(db: &Database, parents: &[&str], time: i64) -> String {
+        let time = Local.timestamp_opt(time, 0).unwrap();
+        let author = Author::new("A".into(), "a@example.com".into(), time);
+        let parents = parents.iter().map(|p| p.to_string()).collect();
+        let commit = Commit::new(parents, TREE, author.clone(), author, "c\n");
+        db.store(commit.clone()).unwrap();
+        commit.oid()
+    }
+
+    /// root - main - merge
+    ///    \         /
+    ///     side ----
+    fn history(db: &Database) -> Vec<String> {
+        let root = commit(db, &[], 100);
+        let side = commit(db, &[&root], 200);
+        let main = commit(db, &[&root], 300);
+        let merge = commit(db, &[&main, &side], 400);
+        vec![root, side, main, merge]
+    }
+
+    /// The walk's output as positions in `history`.
+    fn walk(walk: &mut RevWalk, history: &[String]) -> Vec<usize> {
+        let mut order = vec![];
+        while let Some((oid, _)) = walk.next_commit().unwrap() {
+            order.push(history.iter().position(|h| *h == oid).unwrap());
+        }
+        order
+    }
+
+    #[test]
+    fn test_orders() {
+        let dir = TempDir::new("walk").unwrap();
+        let db = Database::new(dir.path());
+        let h = history(&db);
+
+        let mut w = RevWalk::new(&db);
+        w.push(&h[3]).unwrap();
+        assert_eq!(walk(&mut w, &h), [3, 2, 1, 0]);
+
+        let mut w = RevWalk::new(&db);
+        w.push(&h[3]).unwrap();
+        w.set_order(Order::Topo);
+        assert_eq!(walk(&mut w, &h), [3, 1, 2, 0]);
+
+        let mut w = RevWalk::new(&db);
+        w.push(&h[3]).unwrap();
+        w.set_first_parent(true);
+        w.set_reverse(true);
+        assert_eq!(walk(&mut w, &h), [0, 2, 3]);
+    }
+
+    #[test]
+    fn test_hide() {
+        let dir = TempDir::new("walk").unwrap();
+        let db = Database::new(dir.path());
+        let h = history(&db);
+
+        let mut w = RevWalk::new(&db);
+        w.push(&h[3]).unwrap();
+        w.hide(&h[2]).unwrap();
+        assert_eq!(walk(&mut w, &h), [3, 1]);
+
+        let mut w = RevWalk::new(&db);
+        w.push(&h[2]).unwrap();
+        w.hide(&h[3]).unwrap();
+        assert!(walk(&mut w, &h).is_empty());
+    }
+
+    #[test]
+    fn test_ancestry_path() {
+        let dir = TempDir::new("walk").unwrap();
+        let db = Database::new(dir.path());
+        let h = history(&db);
+
+        let mut w = RevWalk::new(&db);
+        w.push(&h[3]).unwrap();
+        w.hide(&h[1]).unwrap();
+        w.set_ancestry_path(true);
+        assert_eq!(walk(&mut w, &h), [3]);
+
+        let mut w = RevWalk::new(&db);
+        w.push(&h[3]).unwrap();
+        w.hide(&h[0]).unwrap();
+        w.set_ancestry_path(true);
+        w.set_first_parent(true);
+        assert_eq!(walk(&mut w, &h), [3, 2]);
+    }
+
+    #[test]
+    fn test_merge_bases() {
+        let dir = TempDir::new("walk").unwrap();
+        let db = Database::new(dir.path());
+        let h = history(&db);
+        let later = commit(&db, &[&h[1]], 500);
+
+        assert_eq!(merge_bases(&db, &h[1], &h[2]).unwrap(), [h[0].clone()]);
+        assert_eq!(merge_bases(&db, &h[3], &later).unwrap(), [h[1].clone()]);
+        assert_eq!(merge_bases(&db, &h[0], &h[3]).unwrap(), [h[0].clone()]);
+        assert!(is_ancestor(&db, &h[2], &h[3]).unwrap());
+        assert!(!is_ancestor(&db, &h[3], &h[2]).unwrap());
+    }
+}
@@ -0,0 +1,158 @@
+use crate::author::Author;
+use crate::commit::{parse_headers, write_header, Headers};
+use crate::database::{ObjectKind, Storable};
+use failure::format_err;
+use std::borrow::Cow;
+use std::convert::TryFrom;
+
+const SIGNATURE_START: &[u8] = b"-----BEGIN PGP SIGNATURE-----";
+
+/// An annotated tag object. As with commits, headers rit does not use are
+/// kept so that a parsed tag serializes to the same bytes.
+#[derive(Clone, Debug)]
+pub struct Tag {
+    pub object: String,
+    pub kind: ObjectKind,
+    pub name: String,
+    pub(crate) tagger: Option<Author>,
+    headers: Headers,
+    message: Vec<u8>,
+}
+
+impl Tag {
+    pub fn new(object: &str, kind: ObjectKind, name: &str, tagger: Author, message: &str) -> Self {
+        Self {
+            object: object.to_owned(),
+            kind,
+            name: name.to_owned(),
+            tagger: Some(tagger),
+            headers: vec![],
+            message: message.as_bytes().to_vec(),
+        }
+    }
+
+    pub fn message(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.message)
+    }
+
+    /// The signed part of the tag and its detached PGP signature, which git
+    /// appends to the message.
+    pub fn signature(&self) -> Option<(Vec<u8>, Vec<u8>)> {
+        let start = if self.message.starts_with(SIGNATURE_START) {
+            0
+        } else {
+            let pos = self
+                .message
+                .windows(SIGNATURE_START.len() + 1)
+                .position(|w| w[0] == b'\n' && &w[1..] == SIGNATURE_START)?;
+            pos + 1
+        };
+        let mut payload = self.content();
+        let message_start = payload.len() - self.message.len();
+        payload.truncate(message_start + start);
+        Some((payload, self.message[start..].to_vec()))
+    }
+
+    fn content(&self) -> Vec<u8> {
+        let mut content = vec![];
+        write_header(&mut content, "object", self.object.as_bytes());
+        write_header(&mut content, "type", self.kind.to_string().as_bytes());
+        write_header(&mut content, "tag", self.name.as_bytes());
+        if let Some(ref tagger) = self.tagger {
+            write_header(&mut content, "tagger", &tagger.to_bytes());
+        }
+        for (key, value) in &self.headers {
+            write_header(&mut content, key, value);
+        }
+        content.push(b'\n');
+        content.extend_from_slice(&self.message);
+        content
+    }
+}
+
+impl TryFrom<Vec<u8>> for Tag {
+    type Error = failure::Error;
+
+    fn try_from(data: Vec<u8>) -> Result<Self, Self::Error> {
+        let (headers, message) = parse_headers(&data)?;
+        let mut headers = headers.into_iter().peekable();
+        let mut header = |key: &str| match headers.next_if(|(k, _)| k == key) {
+            Some((_, value)) => Ok(value),
+            None => Err(format_err!("failed to read {} from tag", key)),
+        };
+        let object = String::from_utf8(header("object")?)?;
+        let kind = ObjectKind::parse(&String::from_utf8(header("type")?)?)?;
+        let name = String::from_utf8(header("tag")?)?;
+        let tagger = match header("tagger") {
+            Ok(tagger) => Some(Author::try_from(tagger.as_slice())?),
+            Err(_) => None,
+        };
+
+        Ok(Self {
+            object,
+            kind,
+            name,
+            tagger,
+            headers: headers.collect(),
+            message,
+        })
+    }
+}
+
+impl Storable for Tag {
+    fn serialize(&self) -> Vec<u8> {
+        let content = self.content();
+        let mut object = format!("tag {}\0", content.len()).into_bytes();
+        object.extend(content);
+        object
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Tag;
+    use crate::database::{ObjectKind, Storable};
+    use std::convert::TryFrom;
+
+    const SIGNED: &str = concat!(
+        "object 4b825dc642cb6eb9a060e54bf8d69288fbee4904\n",
+        "type tree\n",
+        "tag v1.0\n",
+        "tagger C. O. Mitter <committer@example.com> 1500003600 +0530\n",
+        "\n",
+        "Release 1.0\n",
+        "-----BEGIN PGP SIGNATURE-----\n",
+        "\n",
+        "iQEzBAABCAAdFiEE\n",
+        "-----END PGP SIGNATURE-----\n",
+    );
+
+    #[test]
+    fn test_round_trip() {
+        let tag = Tag::try_from(SIGNED.as_bytes().to_vec()).unwrap();
+        assert_eq!(tag.kind, ObjectKind::Tree);
+        assert_eq!(tag.name, "v1.0");
+        assert_eq!(tag.tagger.as_ref().unwrap().name(), "C. O. Mitter");
+
+        let expected = format!("tag {}\0{}", SIGNED.len(), SIGNED);
+        assert_eq!(tag.serialize(), expected.as_bytes());
+    }
+
+    #[test]
+    fn test_signature() {
+        let tag = Tag::try_from(SIGNED.as_bytes().to_vec()).unwrap();
+        let (payload, signature) = tag.signature().unwrap();
+        let split = SIGNED.find("-----BEGIN").unwrap();
+        assert_eq!(payload, &SIGNED.as_bytes()[..split]);
+        assert_eq!(signature, &SIGNED.as_bytes()[split..]);
+
+        let unsigned = SIGNED.as_bytes()[..split].to_vec();
+        assert!(Tag::try_from(unsigned).unwrap().signature().is_none());
+    }
+
+    #[test]
+    fn test_unknown_type() {
+        let data = SIGNED.replace("type tree", "type blub").into_bytes();
+        assert!(Tag::try_from(data).is_err());
+    }
+}
@@ -38,6 +38,14 @@ impl TreeEntry {
             TreeEntry::Marker(m) => m.clone().oid,
         }
     }
+
+    pub fn mode(&self) -> String {
+        match self {
+            TreeEntry::Tree(t) => t.mode(),
+            TreeEntry::Entry(e) => e.mode(),
+            TreeEntry::Marker(m) => m.mode.clone(),
+        }
+    }
 }
 
 impl Tree {
@@ -1,10 +1,12 @@
 use byteorder::WriteBytesExt;
+use chrono::prelude::*;
+use chrono::Duration;
 use failure::format_err;
 use failure::Error;
 use std::fs::Metadata;
 use std::io;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 pub fn pack_data(mode: &str, name: &str, oid: &str) -> Result<Vec<u8>, Error> {
     let mut w = Vec::new();
@@ -31,7 +33,145 @@ pub fn stat_file(path: &Path) -> io::Result<Metadata> {
     std::fs::metadata(path)
 }
 
+/// The top of the working tree `dir` is in: the nearest directory at or
+/// above it that holds a `.git` directory.
+pub fn find_toplevel(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|d| d.join(".git").is_dir())
+        .map(Path::to_path_buf)
+}
+
 pub fn is_executable(mode: u32) -> bool {
     let xugo: u32 = (libc::S_IXUSR | libc::S_IXGRP | libc::S_IXOTH).into();
     (mode & xugo) > 0
 }
+
+/// Understands the date formats people reach for on the command line:
+/// `now`, `yesterday`, `3.days.ago` (or `3 days ago`), `@<timestamp>` and
+/// ISO dates with an optional time.
+pub fn parse_date(text: &str, now: DateTime<Local>) -> Option<DateTime<Local>> {
+    let text = text.trim();
+    match text {
+        "now" => return Some(now),
+        "yesterday" => return Some(now - Duration::days(1)),
+        _ => {}
+    }
+    let stamp = text.strip_prefix('@').unwrap_or(text);
+    if !stamp.is_empty() && stamp.chars().all(|c| c.is_ascii_digit()) {
+        return Local.timestamp_opt(stamp.parse().ok()?, 0).single();
+    }
+
+    let words = text
+        .split(|c: char| c == '.' || c.is_whitespace())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>();
+    if let [n, unit, "ago"] = words.as_slice() {
+        let n: i64 = n.parse().ok()?;
+        let seconds = match unit.trim_end_matches('s') {
+            "second" => 1,
+            "minute" => 60,
+            "hour" => 60 * 60,
+            "day" => 24 * 60 * 60,
+            "week" => 7 * 24 * 60 * 60,
+            "month" => 30 * 24 * 60 * 60,
+            "year" => 365 * 24 * 60 * 60,
+            _ => return None,
+        };
+        return Some(now - Duration::seconds(n * seconds));
+    }
+
+    for format in &["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(time) = NaiveDateTime::parse_from_str(text, format) {
+            return Local.from_local_datetime(&time).earliest();
+        }
+    }
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .and_then(|t| Local.from_local_datetime(&t).earliest())
+}
+
+/// Matches `text` against a shell-style pattern, where `*` matches any run
+/// of characters, `?` any one character and `[...]` one of a set.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    glob_match_chars(&pattern, &text)
+}
+
+fn glob_match_chars(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => (0..=text.len()).any(|i| glob_match_chars(rest, &text[i..])),
+        Some(('?', rest)) => !text.is_empty() && glob_match_chars(rest, &text[1..]),
+        Some(('[', rest)) if rest.contains(&']') => {
+            let close = rest.iter().position(|&c| c == ']').unwrap();
+            let (set, rest) = (&rest[..close], &rest[close + 1..]);
+            let (negate, set) = match set.split_first() {
+                Some(('!', set)) | Some(('^', set)) => (true, set),
+                _ => (false, set),
+            };
+            let c = match text.first() {
+                Some(c) => *c,
+                None => return false,
+            };
+            let mut found = false;
+            let mut i = 0;
+            while i < set.len() {
+                if i + 2 < set.len() && set[i + 1] == '-' {
+                    found |= set[i] <= c && c <= set[i + 2];
+                    i += 3;
+                } else {
+                    found |= set[i] == c;
+                    i += 1;
+                }
+            }
+            found != negate && glob_match_chars(rest, &text[1..])
+        }
+        Some((c, rest)) => text.first() == Some(c) && glob_match_chars(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{glob_match, parse_date};
+    use chrono::prelude::*;
+
+    #[test]
+    fn test_relative_dates() {
+        let now = Local.timestamp_opt(1_500_000_000, 0).unwrap();
+        assert_eq!(parse_date("now", now), Some(now));
+        assert_eq!(
+            parse_date("2.days.ago", now).map(|t| t.timestamp()),
+            Some(1_500_000_000 - 2 * 86400)
+        );
+        assert_eq!(
+            parse_date("1 hour ago", now).map(|t| t.timestamp()),
+            Some(1_500_000_000 - 3600)
+        );
+        assert_eq!(parse_date("3 fortnights ago", now), None);
+    }
+
+    #[test]
+    fn test_absolute_dates() {
+        let now = Local::now();
+        assert_eq!(
+            parse_date("@1500000000", now).map(|t| t.timestamp()),
+            Some(1_500_000_000)
+        );
+        let date = parse_date("2017-07-14", now).unwrap();
+        assert_eq!((date.year(), date.month(), date.day()), (2017, 7, 14));
+        let time = parse_date("2017-07-14 02:40:00", now).unwrap();
+        assert_eq!((time.hour(), time.minute()), (2, 40));
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("v1.*", "v1.0"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("v?.[0-9]", "v2.3"));
+        assert!(!glob_match("v?.[!0-9]", "v2.3"));
+        assert!(!glob_match("v1.*", "v2.0"));
+        assert!(!glob_match("v1", "v1.0"));
+    }
+}
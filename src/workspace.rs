@@ -4,7 +4,6 @@ use crate::repository::migration::{Action, Migration, MigrationChanges};
 use crate::tree::TreeEntry;
 use failure::Error;
 use std::collections::BTreeMap;
-use std::convert::TryFrom;
 use std::fs::{File, Metadata, OpenOptions, Permissions};
 use std::io;
 use std::io::{Read, Write};
@@ -67,26 +66,33 @@ impl Workspace {
         }
     }
 
-    pub fn read_file<P: AsRef<Path>>(&self, path: P) -> Result<String, Error> {
+    pub fn read_file<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>, Error> {
         let path = self.workspace_path(path);
         let mut file = File::open(path)?;
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
         Ok(contents)
     }
 
     fn create_dir(&self, path: &PathBuf) -> Result<(), std::io::Error> {
         let path = self.workspace_path(path);
 
-        if path.metadata()?.is_file() {
-            std::fs::remove_dir(&path)?;
+        match path.symlink_metadata() {
+            Ok(ref stat) if stat.is_dir() => return Ok(()),
+            Ok(_) => std::fs::remove_file(&path)?,
+            Err(_) => {}
         }
         std::fs::create_dir(&path)
     }
 
     fn remove_dir(&self, path: &PathBuf) -> Result<(), std::io::Error> {
         let path = self.workspace_path(path);
-        std::fs::remove_dir(path)
+        // directories that still hold other files are left in place
+        match std::fs::remove_dir(path) {
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(ref e) if e.raw_os_error() == Some(libc::ENOTEMPTY) => Ok(()),
+            res => res,
+        }
     }
     pub fn stat_file(&self, path: &PathBuf) -> Result<Metadata, std::io::Error> {
         let path = self.workspace_path(path);
@@ -130,9 +136,11 @@ impl Workspace {
             Some(l) => l,
         };
         for (path, entry) in list {
-            let path = self.workspace_path(&path);
-            std::fs::remove_file(&path)?;
             if action == Action::Remove {
+                let path = self.workspace_path(&path);
+                if path.symlink_metadata().is_ok() {
+                    std::fs::remove_file(&path)?;
+                }
                 continue;
             }
 
@@ -143,15 +151,61 @@ impl Workspace {
                 TreeEntry::Marker(m) => (m.oid, m.mode),
             };
             let (_, _, data) = db.read_object(oid.as_str())?;
-            let blob = Blob::try_from(data)?;
-            let mut file = OpenOptions::new()
-                .write(true)
-                .create_new(true)
-                .open(&path)?;
-            file.write_all(blob.data.as_bytes())?;
-            let mode = mode.parse::<u32>()?;
-            let perms = Permissions::from_mode(mode);
-            file.set_permissions(perms)?;
+            self.write_file(path, Blob::from(data).content(), &mode)?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the file at `path` with `data`, creating any missing parent
+    /// directories. `mode` is an octal file mode such as `100644`. Whatever
+    /// is in the way, such as a directory at `path` or a file where one of
+    /// its parents should be, is removed.
+    pub fn write_file<P: AsRef<Path>>(
+        &self,
+        path: P,
+        data: &[u8],
+        mode: &str,
+    ) -> Result<(), Error> {
+        let relative = path.as_ref();
+        for parent in relative.ancestors().skip(1) {
+            let parent = self.workspace_path(parent);
+            match parent.symlink_metadata() {
+                Ok(ref stat) if !stat.is_dir() => std::fs::remove_file(&parent)?,
+                _ => {}
+            }
+        }
+        let path = self.workspace_path(relative);
+        match path.symlink_metadata() {
+            Ok(ref stat) if stat.is_dir() => std::fs::remove_dir_all(&path)?,
+            Ok(_) => std::fs::remove_file(&path)?,
+            Err(_) => {}
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        file.write_all(data)?;
+        let mode = u32::from_str_radix(mode, 8)?;
+        file.set_permissions(Permissions::from_mode(mode))?;
+        Ok(())
+    }
+
+    /// Removes the file at `path` along with any parent directories that
+    /// are left empty.
+    pub fn remove_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        match std::fs::remove_file(self.workspace_path(path)) {
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+            res => res?,
+        }
+        for parent in path.ancestors().skip(1) {
+            if parent.as_os_str().is_empty() {
+                break;
+            }
+            self.remove_dir(&parent.to_path_buf())?;
         }
         Ok(())
     }
@@ -41,3 +41,27 @@ fn add_missing_file() -> BoxResult<()> {
         .stderr(predicate::str::contains("pathspec 'derp.txt'"));
     Ok(())
 }
+
+#[test]
+fn add_binary_file() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    let mut image = File::create(repo.path().join("image.bin"))?;
+    image.write_all(&[0x89, b'P', b'N', b'G', 0x00, 0xff, 0xfe, 0x80])?;
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("add")
+        .arg("image.bin")
+        .assert()
+        .success();
+
+    let mut image = File::create(repo.path().join("image.bin"))?;
+    image.write_all(&[0x89, b'P', b'N', b'G', 0x00, 0xff, 0xfe, 0x81])?;
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("status")
+        .arg("--porcelain")
+        .assert()
+        .success()
+        .stdout(" M image.bin\n");
+    Ok(())
+}
@@ -0,0 +1,191 @@
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use std::process::Command;
+
+mod helpers;
+use helpers::*;
+
+use rit::BoxResult;
+
+fn rit_branch(repo: &tempdir::TempDir, args: &[&str]) -> BoxResult<assert_cmd::assert::Assert> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    Ok(cmd
+        .current_dir(repo.path())
+        .env("CLICOLOR", "0")
+        .arg("branch")
+        .args(args)
+        .assert())
+}
+
+fn git_config(repo: &tempdir::TempDir, name: &str) -> BoxResult<String> {
+    let output = Command::new("git")
+        .current_dir(repo.path())
+        .args(["config", "--get", name])
+        .output()?;
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// `master` is one commit ahead of `feature`.
+fn prepare_branches(repo: &tempdir::TempDir) -> BoxResult<(String, String)> {
+    prepare_commits(repo, vec!["1.txt"])?;
+    branch(repo, "feature")?;
+    let first = read_file(repo, ".git/refs/heads/master")?;
+    write_file(repo, "2.txt", "2", true)?;
+    commit(repo, "second")?;
+    let second = read_file(repo, ".git/refs/heads/master")?;
+    Ok((first.trim().to_owned(), second.trim().to_owned()))
+}
+
+#[test]
+fn lists_branches_marking_the_current_one() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    let (first, second) = prepare_branches(&repo)?;
+
+    rit_branch(&repo, &[])?
+        .success()
+        .stdout("  feature\n* master\n");
+    rit_branch(&repo, &["-v"])?.success().stdout(format!(
+        "  feature {} commit\n* master  {} second\n",
+        &first[..7],
+        &second[..7]
+    ));
+    rit_branch(&repo, &["--contains", "master"])?
+        .success()
+        .stdout("* master\n");
+    rit_branch(&repo, &["--list", "feat*"])?
+        .success()
+        .stdout("  feature\n");
+
+    checkout(&repo, &first)?;
+    rit_branch(&repo, &[])?.success().stdout(format!(
+        "* (HEAD detached at {})\n  feature\n  master\n",
+        &first[..7]
+    ));
+    Ok(())
+}
+
+#[test]
+fn delete_checks_branches_are_merged() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    let (first, second) = prepare_branches(&repo)?;
+    branch(&repo, "ahead")?;
+
+    rit_branch(&repo, &["-d", "master"])?
+        .code(1)
+        .stderr(predicate::str::contains("currently on"));
+
+    checkout(&repo, "feature")?;
+    rit_branch(&repo, &["-d", "ahead"])?
+        .code(1)
+        .stderr(predicate::str::starts_with(
+            "error: The branch 'ahead' is not fully merged.\n",
+        ));
+    rit_branch(&repo, &["-D", "ahead"])?
+        .success()
+        .stdout(format!("Deleted branch ahead (was {}).\n", &second[..7]));
+
+    checkout(&repo, "master")?;
+    rit_branch(&repo, &["-d", "feature", "nope"])?
+        .code(1)
+        .stdout(format!("Deleted branch feature (was {}).\n", &first[..7]))
+        .stderr("error: branch 'nope' not found.\n");
+    rit_branch(&repo, &[])?.success().stdout("* master\n");
+    Ok(())
+}
+
+#[test]
+fn rename_moves_ref_reflog_and_head() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    let (first, second) = prepare_branches(&repo)?;
+
+    rit_branch(&repo, &["-m", "feature", "topic/one"])?.success();
+    assert_eq!(read_file(&repo, ".git/refs/heads/topic/one")?.trim(), first);
+    assert!(!repo.path().join(".git/refs/heads/feature").exists());
+    assert!(!repo.path().join(".git/logs/refs/heads/feature").exists());
+    assert!(read_file(&repo, ".git/logs/refs/heads/topic/one")?
+        .ends_with("\tBranch: renamed refs/heads/feature to refs/heads/topic/one\n"));
+
+    rit_branch(&repo, &["-m", "topic/one"])?
+        .failure()
+        .stderr("fatal: A branch named 'topic/one' already exists.\n");
+    rit_branch(&repo, &["-M", "topic/one"])?.success();
+    assert_eq!(
+        read_file(&repo, ".git/HEAD")?,
+        "ref: refs/heads/topic/one\n"
+    );
+    assert_eq!(
+        read_file(&repo, ".git/refs/heads/topic/one")?.trim(),
+        second
+    );
+    rit_branch(&repo, &[])?.success().stdout("* topic/one\n");
+    Ok(())
+}
+
+#[test]
+fn rename_into_a_nested_name() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    let (first, _) = prepare_branches(&repo)?;
+
+    rit_branch(&repo, &["-m", "feature", "feature/one"])?.success();
+    assert_eq!(
+        read_file(&repo, ".git/refs/heads/feature/one")?.trim(),
+        first
+    );
+    assert!(read_file(&repo, ".git/logs/refs/heads/feature/one")?
+        .ends_with("\tBranch: renamed refs/heads/feature to refs/heads/feature/one\n"));
+
+    rit_branch(&repo, &["-m", "feature/one", "feature"])?.success();
+    assert_eq!(read_file(&repo, ".git/refs/heads/feature")?.trim(), first);
+    assert_eq!(
+        read_file(&repo, ".git/logs/refs/heads/feature")?
+            .lines()
+            .count(),
+        3
+    );
+    rit_branch(&repo, &[])?
+        .success()
+        .stdout("  feature\n* master\n");
+    Ok(())
+}
+
+#[test]
+fn upstream_is_recorded_in_config() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    let (first, _) = prepare_branches(&repo)?;
+
+    rit_branch(&repo, &["-u", "master", "feature"])?
+        .success()
+        .stdout("branch 'feature' set up to track 'master'.\n");
+    assert_eq!(git_config(&repo, "branch.feature.remote")?, ".\n");
+    assert_eq!(
+        git_config(&repo, "branch.feature.merge")?,
+        "refs/heads/master\n"
+    );
+
+    rit_branch(&repo, &["-vv", "--list", "feature"])?
+        .success()
+        .stdout(format!(
+            "  feature {} [master: behind 1] commit\n",
+            &first[..7]
+        ));
+    rit_branch(&repo, &["-v", "--list", "feature"])?
+        .success()
+        .stdout(format!("  feature {} [behind 1] commit\n", &first[..7]));
+
+    rit_branch(&repo, &["-m", "feature", "renamed"])?.success();
+    assert_eq!(
+        git_config(&repo, "branch.renamed.merge")?,
+        "refs/heads/master\n"
+    );
+    assert_eq!(git_config(&repo, "branch.feature.merge")?, "");
+
+    rit_branch(&repo, &["--unset-upstream", "renamed"])?.success();
+    assert_eq!(git_config(&repo, "branch.renamed.merge")?, "");
+    rit_branch(&repo, &["--unset-upstream", "renamed"])?
+        .failure()
+        .stderr("fatal: branch 'renamed' has no upstream information\n");
+    rit_branch(&repo, &["-u", "nowhere"])?
+        .failure()
+        .stderr("fatal: the requested upstream branch 'nowhere' does not exist\n");
+    Ok(())
+}
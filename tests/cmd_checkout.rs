@@ -0,0 +1,351 @@
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use std::process::Command;
+
+mod helpers;
+use helpers::*;
+
+use rit::BoxResult;
+
+#[test]
+fn init_points_head_at_master() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    assert_eq!(read_file(&repo, ".git/HEAD")?, "ref: refs/heads/master\n");
+    Ok(())
+}
+
+#[test]
+fn commit_advances_checked_out_branch() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    let master = read_file(&repo, ".git/refs/heads/master")?;
+    branch(&repo, "feature")?;
+    checkout(&repo, "feature")?;
+    assert_eq!(read_file(&repo, ".git/HEAD")?, "ref: refs/heads/feature\n");
+
+    write_file(&repo, "2.txt", "2", true)?;
+    commit(&repo, "on feature")?;
+    assert_eq!(read_file(&repo, ".git/refs/heads/master")?, master);
+    assert_ne!(read_file(&repo, ".git/refs/heads/feature")?, master);
+    Ok(())
+}
+
+#[test]
+fn switching_branches_updates_workspace() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt", "a/2.txt"])?;
+    branch(&repo, "feature")?;
+    checkout(&repo, "feature")?;
+    write_file(&repo, "1.txt", "changed", true)?;
+    write_file(&repo, "a/b/3.txt", "3", true)?;
+    commit(&repo, "on feature")?;
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("checkout")
+        .arg("master")
+        .assert()
+        .success()
+        .stderr("Switched to branch 'master'\n");
+    assert_eq!(read_file(&repo, "1.txt")?, "1.txt");
+    assert!(!repo.path().join("a/b").exists());
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("status")
+        .arg("--porcelain")
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+#[test]
+fn checking_out_a_commit_detaches_head() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    write_file(&repo, "2.txt", "2", true)?;
+    commit(&repo, "second")?;
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("checkout")
+        .arg("@^")
+        .assert()
+        .success()
+        .stderr(predicate::str::starts_with(
+            "Note: checking out '@^'.\n\nYou are in 'detached HEAD' state.",
+        ))
+        .stderr(predicate::str::contains("HEAD is now at"));
+    assert!(!read_file(&repo, ".git/HEAD")?.starts_with("ref:"));
+    Ok(())
+}
+
+#[test]
+fn checking_out_a_full_branch_name_attaches_head() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    branch(&repo, "feature")?;
+
+    checkout(&repo, "refs/heads/feature")?;
+    assert_eq!(read_file(&repo, ".git/HEAD")?, "ref: refs/heads/feature\n");
+    checkout(&repo, "heads/master")?;
+    assert_eq!(read_file(&repo, ".git/HEAD")?, "ref: refs/heads/master\n");
+    Ok(())
+}
+
+#[test]
+fn checkout_previous_branch() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    branch(&repo, "feature")?;
+    checkout(&repo, "feature")?;
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("checkout")
+        .arg("@{-1}")
+        .assert()
+        .success()
+        .stderr("Switched to branch 'master'\n");
+    assert_eq!(read_file(&repo, ".git/HEAD")?, "ref: refs/heads/master\n");
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("checkout")
+        .arg("@{-1}")
+        .assert()
+        .success()
+        .stderr("Switched to branch 'feature'\n");
+    Ok(())
+}
+
+#[test]
+fn checkout_reflog_entry() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    write_file(&repo, "2.txt", "2", true)?;
+    commit(&repo, "second")?;
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("checkout")
+        .arg("master@{1}")
+        .assert()
+        .success();
+    assert!(!repo.path().join("2.txt").exists());
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("checkout")
+        .arg("master@{5}")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "log for 'master' only has 2 entries",
+        ));
+    Ok(())
+}
+
+fn checkout_fails(repo: &tempdir::TempDir, revision: &str, stderr: &str) -> BoxResult<()> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("checkout")
+        .arg(revision)
+        .assert()
+        .code(1)
+        .stderr(format!("{}Aborting\n", stderr));
+    Ok(())
+}
+
+#[test]
+fn checkout_keeps_local_changes() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    branch(&repo, "feature")?;
+    checkout(&repo, "feature")?;
+    write_file(&repo, "1.txt", "feature", true)?;
+    write_file(&repo, "2.txt", "2", true)?;
+    commit(&repo, "on feature")?;
+    checkout(&repo, "master")?;
+
+    write_file(&repo, "1.txt", "unstaged", false)?;
+    write_file(&repo, "2.txt", "untracked", false)?;
+    checkout_fails(
+        &repo,
+        "feature",
+        concat!(
+            "error: Your local changes to the following files would be overwritten by checkout:\n",
+            "\t1.txt\n",
+            "Please commit your changes or stash them before you switch branches.\n",
+            "error: The following untracked working tree files would be overwritten by checkout:\n",
+            "\t2.txt\n",
+            "Please move or remove them before you switch branches.\n",
+        ),
+    )?;
+    assert_eq!(read_file(&repo, "1.txt")?, "unstaged");
+    assert_eq!(read_file(&repo, "2.txt")?, "untracked");
+    assert_eq!(read_file(&repo, ".git/HEAD")?, "ref: refs/heads/master\n");
+
+    // changes to files the checkout does not touch are carried over
+    delete(&repo, "2.txt")?;
+    write_file(&repo, "1.txt", "1.txt", false)?;
+    write_file(&repo, "3.txt", "untracked", false)?;
+    checkout(&repo, "feature")?;
+    assert_eq!(read_file(&repo, "3.txt")?, "untracked");
+    Ok(())
+}
+
+#[test]
+fn checkout_keeps_untracked_directories() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    branch(&repo, "feature")?;
+    checkout(&repo, "feature")?;
+    write_file(&repo, "dir", "a file", true)?;
+    commit(&repo, "on feature")?;
+    checkout(&repo, "master")?;
+
+    write_file(&repo, "dir/untracked.txt", "untracked", false)?;
+    checkout_fails(
+        &repo,
+        "feature",
+        concat!(
+            "error: Updating the following directories would lose untracked files in them:\n",
+            "\tdir\n",
+            "\n\n",
+        ),
+    )?;
+    assert_eq!(read_file(&repo, "dir/untracked.txt")?, "untracked");
+    Ok(())
+}
+
+#[test]
+fn checkout_paths_from_index_and_commits() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt", "a/2.txt"])?;
+    write_file(&repo, "1.txt", "second", true)?;
+    commit(&repo, "second")?;
+
+    write_file(&repo, "1.txt", "unstaged", false)?;
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .args(["checkout", "--", "1.txt"])
+        .assert()
+        .success()
+        .stderr("Updated 1 path from the index\n");
+    assert_eq!(read_file(&repo, "1.txt")?, "second");
+
+    write_file(&repo, "a/2.txt", "unstaged", false)?;
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .args(["checkout", "a"])
+        .assert()
+        .success();
+    assert_eq!(read_file(&repo, "a/2.txt")?, "a/2.txt");
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .args(["checkout", "HEAD^", "1.txt"])
+        .assert()
+        .success();
+    assert_eq!(read_file(&repo, "1.txt")?, "1.txt");
+    assert_eq!(read_file(&repo, ".git/HEAD")?, "ref: refs/heads/master\n");
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .args(["status", "--porcelain"])
+        .assert()
+        .success()
+        .stdout("M  1.txt\n");
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .args(["checkout", "HEAD", "--", "nope.txt"])
+        .assert()
+        .code(1)
+        .stderr("error: pathspec 'nope.txt' did not match any file(s) known to rit\n");
+    Ok(())
+}
+
+fn assert_clean(repo: &tempdir::TempDir) -> BoxResult<()> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("status")
+        .arg("--porcelain")
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+#[test]
+fn checkout_adds_and_removes_whole_directories() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt", "a/2.txt"])?;
+    branch(&repo, "feature")?;
+    checkout(&repo, "feature")?;
+    write_file(&repo, "a/3.txt", "3", true)?;
+    write_file(&repo, "b/c/4.txt", "4", true)?;
+    write_file(&repo, "5.txt", "5", true)?;
+    commit(&repo, "on feature")?;
+
+    checkout(&repo, "master")?;
+    assert!(!repo.path().join("b").exists());
+    assert!(!repo.path().join("a/3.txt").exists());
+    assert!(!repo.path().join("5.txt").exists());
+    assert_eq!(read_file(&repo, "a/2.txt")?, "a/2.txt");
+    assert_clean(&repo)?;
+
+    checkout(&repo, "feature")?;
+    assert_eq!(read_file(&repo, "a/3.txt")?, "3");
+    assert_eq!(read_file(&repo, "b/c/4.txt")?, "4");
+    assert_eq!(read_file(&repo, "5.txt")?, "5");
+    assert_clean(&repo)?;
+    Ok(())
+}
+
+#[test]
+fn checkout_removes_files_already_deleted_locally() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    branch(&repo, "feature")?;
+    checkout(&repo, "feature")?;
+    write_file(&repo, "a/2.txt", "2", true)?;
+    commit(&repo, "on feature")?;
+
+    delete(&repo, "a/2.txt")?;
+    checkout(&repo, "master")?;
+    assert!(!repo.path().join("a").exists());
+    assert_clean(&repo)?;
+    Ok(())
+}
+
+#[test]
+fn checkout_writes_file_modes_and_binary_content() -> BoxResult<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    branch(&repo, "feature")?;
+    checkout(&repo, "feature")?;
+    let binary = b"PNG\0\x01\xff\xfe\ncaf\xe9\n";
+    std::fs::write(repo.path().join("image.bin"), binary)?;
+    add_file(&repo, "image.bin")?;
+    write_file(&repo, "run.sh", "#!/bin/sh\n", false)?;
+    make_executable(&repo, "run.sh")?;
+    add_file(&repo, "run.sh")?;
+    commit(&repo, "on feature")?;
+
+    checkout(&repo, "master")?;
+    assert!(!repo.path().join("image.bin").exists());
+    checkout(&repo, "feature")?;
+    assert_eq!(std::fs::read(repo.path().join("image.bin"))?, binary);
+    let mode = |path: &str| -> BoxResult<u32> {
+        let stat = std::fs::metadata(repo.path().join(path))?;
+        Ok(stat.permissions().mode() & 0o777)
+    };
+    assert_eq!(mode("image.bin")?, 0o644);
+    assert_eq!(mode("run.sh")?, 0o755);
+    assert_clean(&repo)?;
+    Ok(())
+}
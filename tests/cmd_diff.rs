@@ -0,0 +1,125 @@
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use std::process::Command;
+
+mod helpers;
+use helpers::*;
+
+use rit::BoxResult;
+
+#[test]
+fn reports_binary_files_differ() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    write_file(&repo, "image.bin", "PNG\0\u{1}\u{2}", true)?;
+    commit(&repo, "binary")?;
+    write_file(&repo, "image.bin", "PNG\0\u{1}\u{3}", false)?;
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("diff")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Binary files a/image.bin and b/image.bin differ",
+        ))
+        .stdout(predicate::str::contains("@@").not());
+    Ok(())
+}
+
+#[test]
+fn binary_attribute_overrides_detection() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    write_file(&repo, ".gitattributes", "*.dat binary\n", true)?;
+    write_file(&repo, "plain.dat", "one\n", true)?;
+    commit(&repo, "text")?;
+    write_file(&repo, "plain.dat", "two\n", false)?;
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("diff")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Binary files a/plain.dat and b/plain.dat differ",
+        ));
+    Ok(())
+}
+
+#[test]
+fn emits_git_binary_patch() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    write_file(&repo, "image.bin", "PNG\0\u{1}\u{2}", true)?;
+    commit(&repo, "binary")?;
+    write_file(&repo, "image.bin", "PNG\0\u{1}\u{3}", false)?;
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("diff")
+        .arg("--binary")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("GIT binary patch\nliteral 6\n"));
+
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))?
+        .current_dir(repo.path())
+        .arg("diff")
+        .arg("--binary")
+        .output()?;
+    std::fs::write(repo.path().join("image.patch"), output.stdout)?;
+    write_file(&repo, "image.bin", "PNG\0\u{1}\u{2}", false)?;
+    git(&repo, &["apply", "--check", "image.patch"])?;
+    Ok(())
+}
+
+#[test]
+fn diff_against_reflog_entry() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    write_file(&repo, "1.txt", "one\n", true)?;
+    write_file(&repo, "2.txt", "two\n", true)?;
+    commit(&repo, "second")?;
+    write_file(&repo, "2.txt", "three\n", false)?;
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .env("CLICOLOR", "0")
+        .arg("diff")
+        .arg("HEAD@{1}")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("diff --git a/1.txt b/1.txt"))
+        .stdout(predicate::str::contains("new file mode 100644"))
+        .stdout(predicate::str::contains("+three"));
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .env("CLICOLOR", "0")
+        .arg("diff")
+        .arg("--cached")
+        .arg("HEAD@{1}")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("+two"));
+    Ok(())
+}
+
+#[test]
+fn diffs_latin1_text_as_bytes() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    std::fs::write(repo.path().join("latin1.txt"), b"caf\xe9\nna\xefve\n")?;
+    add_file(&repo, "latin1.txt")?;
+    commit(&repo, "latin-1")?;
+    std::fs::write(repo.path().join("latin1.txt"), b"caf\xe9s\nna\xefve\n")?;
+
+    let output = Command::cargo_bin(env!("CARGO_PKG_NAME"))?
+        .current_dir(repo.path())
+        .env("CLICOLOR", "0")
+        .arg("diff")
+        .output()?;
+    let expected = Command::new("git")
+        .current_dir(repo.path())
+        .arg("diff")
+        .output()?;
+    assert_eq!(output.stdout, expected.stdout);
+
+    std::fs::write(repo.path().join("latin1.patch"), output.stdout)?;
+    git(&repo, &["apply", "--check", "-R", "latin1.patch"])?;
+    Ok(())
+}
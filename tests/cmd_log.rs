@@ -0,0 +1,214 @@
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use std::process::Command;
+
+mod helpers;
+use helpers::*;
+
+use rit::BoxResult;
+
+fn log(repo: &tempdir::TempDir, args: &[&str]) -> BoxResult<String> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    let output = cmd
+        .current_dir(repo.path())
+        .env("CLICOLOR", "0")
+        .arg("log")
+        .args(args)
+        .output()?;
+    assert!(output.status.success());
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+fn prepare_history(repo: &tempdir::TempDir) -> BoxResult<()> {
+    prepare_commits(repo, vec!["1.txt"])?;
+    write_file(repo, "2.txt", "two\n", true)?;
+    commit(repo, "second")?;
+    write_file(repo, "1.txt", "one\nmore\n", true)?;
+    commit(repo, "third")?;
+    Ok(())
+}
+
+#[test]
+fn oneline_lists_newest_first() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_history(&repo)?;
+
+    let titles = log(&repo, &["--format=%s"])?;
+    assert_eq!(titles, "third\nsecond\ncommit\n");
+
+    let oneline = log(&repo, &["--oneline", "-n", "2"])?;
+    let lines: Vec<&str> = oneline.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].ends_with(" third"));
+    assert_eq!(lines[0].split(' ').next().unwrap().len(), 7);
+    Ok(())
+}
+
+#[test]
+fn medium_format_shows_author_and_indented_message() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .env("CLICOLOR", "0")
+        .arg("log")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("commit "))
+        .stdout(predicate::str::contains(
+            "\nAuthor: A. U. Thor <author@example.com>\nDate:   ",
+        ))
+        .stdout(predicate::str::ends_with("\n\n    commit\n"));
+    Ok(())
+}
+
+#[test]
+fn format_placeholders_are_expanded() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+
+    let out = log(&repo, &["--format=format:%an <%ae>%n%H %%"])?;
+    let head = read_file(&repo, ".git/refs/heads/master")?;
+    assert_eq!(
+        out,
+        format!("A. U. Thor <author@example.com>\n{} %\n", head.trim())
+    );
+    Ok(())
+}
+
+#[test]
+fn paths_limit_history() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_history(&repo)?;
+
+    assert_eq!(log(&repo, &["--format=%s", "--", "2.txt"])?, "second\n");
+    assert_eq!(log(&repo, &["--format=%s", "1.txt"])?, "third\ncommit\n");
+    Ok(())
+}
+
+#[test]
+fn author_filter_matches_identity() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_history(&repo)?;
+
+    assert_eq!(log(&repo, &["--format=%s", "--author=nobody"])?, "");
+    assert_eq!(
+        log(&repo, &["--format=%s", "--author=example\\.com", "-n", "1"])?,
+        "third\n"
+    );
+    assert_eq!(log(&repo, &["--format=%s", "--until=yesterday"])?, "");
+    Ok(())
+}
+
+#[test]
+fn patch_and_stat_show_changes() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_history(&repo)?;
+
+    let patch = log(&repo, &["--format=%s", "-p", "-n", "1"])?;
+    assert!(patch.starts_with("third\ndiff --git a/1.txt b/1.txt\n"));
+    assert!(patch.contains("\n+more\n"));
+
+    let stat = log(&repo, &["--format=%s", "--stat", "-n", "2"])?;
+    assert_eq!(
+        stat,
+        concat!(
+            "third\n",
+            " 1.txt | 3 ++-\n",
+            " 1 file changed, 2 insertions(+), 1 deletion(-)\n",
+            "second\n",
+            " 2.txt | 1 +\n",
+            " 1 file changed, 1 insertion(+)\n",
+        )
+    );
+    Ok(())
+}
+
+#[test]
+fn unknown_revision_fails() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("log")
+        .arg("nope")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("ambiguous argument 'nope'"));
+    Ok(())
+}
+
+/// `master` merges `side` into `main`, each made from the root commit.
+fn prepare_merge(repo: &tempdir::TempDir) -> BoxResult<()> {
+    prepare_commits(repo, vec!["1.txt"])?;
+    branch(repo, "side")?;
+    checkout(repo, "side")?;
+    write_file(repo, "side.txt", "side", true)?;
+    commit(repo, "side")?;
+    checkout(repo, "master")?;
+    write_file(repo, "main.txt", "main", true)?;
+    commit(repo, "main")?;
+
+    let git_output = |args: &[&str]| -> BoxResult<String> {
+        let output = Command::new("git")
+            .env("GIT_AUTHOR_NAME", "A. U. Thor")
+            .env("GIT_AUTHOR_EMAIL", "author@example.com")
+            .env("GIT_COMMITTER_NAME", "A. U. Thor")
+            .env("GIT_COMMITTER_EMAIL", "author@example.com")
+            .current_dir(repo.path())
+            .args(args)
+            .output()?;
+        Ok(String::from_utf8(output.stdout)?.trim().to_owned())
+    };
+    let tree = git_output(&["rev-parse", "master^{tree}"])?;
+    let merge = git_output(&[
+        "commit-tree",
+        &tree,
+        "-p",
+        "master",
+        "-p",
+        "side",
+        "-m",
+        "merge",
+    ])?;
+    git(repo, &["update-ref", "refs/heads/master", &merge])?;
+    Ok(())
+}
+
+#[test]
+fn ordering_options() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_merge(&repo)?;
+
+    assert_eq!(
+        log(&repo, &["--format=%s", "--topo-order"])?,
+        "merge\nside\nmain\ncommit\n"
+    );
+    assert_eq!(
+        log(&repo, &["--format=%s", "--first-parent"])?,
+        "merge\nmain\ncommit\n"
+    );
+    assert_eq!(
+        log(&repo, &["--format=%s", "--reverse", "-n", "2"])?,
+        "main\nmerge\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn ranges_limit_log() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_merge(&repo)?;
+
+    assert_eq!(
+        log(&repo, &["--format=%s", "master^..master"])?,
+        "merge\nside\n"
+    );
+    assert_eq!(
+        log(&repo, &["--format=%s", "master^1...side"])?,
+        "main\nside\n"
+    );
+    Ok(())
+}
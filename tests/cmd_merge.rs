@@ -0,0 +1,282 @@
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use std::process::Command;
+
+mod helpers;
+use helpers::*;
+
+use rit::BoxResult;
+
+fn rit(repo: &tempdir::TempDir, args: &[&str]) -> BoxResult<assert_cmd::assert::Assert> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    Ok(cmd
+        .current_dir(repo.path())
+        .env("GIT_AUTHOR_EMAIL", "author@example.com")
+        .env("GIT_AUTHOR_NAME", "A. U. Thor")
+        .args(args)
+        .assert())
+}
+
+fn status(repo: &tempdir::TempDir) -> BoxResult<String> {
+    let output = rit(repo, &["status", "--porcelain"])?.success();
+    Ok(String::from_utf8(output.get_output().stdout.clone())?)
+}
+
+fn cat_head(repo: &tempdir::TempDir) -> BoxResult<String> {
+    let output = Command::new("git")
+        .current_dir(repo.path())
+        .args(["cat-file", "-p", "HEAD"])
+        .output()?;
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// `master` and `topic` commit `ours` and `theirs` on top of a shared first
+/// commit of `1.txt` and `2.txt`, returning the tip of `topic`.
+fn diverge(
+    repo: &tempdir::TempDir,
+    ours: &[(&str, &str)],
+    theirs: &[(&str, &str)],
+) -> BoxResult<String> {
+    prepare_commits(repo, vec!["1.txt", "2.txt"])?;
+    branch(repo, "topic")?;
+    checkout(repo, "topic")?;
+    for (path, content) in theirs {
+        write_file(repo, path, content, true)?;
+    }
+    commit(repo, "theirs")?;
+    let topic = read_file(repo, ".git/refs/heads/topic")?;
+
+    checkout(repo, "master")?;
+    for (path, content) in ours {
+        write_file(repo, path, content, true)?;
+    }
+    commit(repo, "ours")?;
+    Ok(topic.trim().to_owned())
+}
+
+#[test]
+fn fast_forwards_when_possible() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    let master = read_file(&repo, ".git/refs/heads/master")?;
+    branch(&repo, "topic")?;
+    checkout(&repo, "topic")?;
+    write_file(&repo, "2.txt", "2", true)?;
+    commit(&repo, "second")?;
+    let topic = read_file(&repo, ".git/refs/heads/topic")?;
+    checkout(&repo, "master")?;
+
+    rit(&repo, &["merge", "topic"])?.success().stdout(format!(
+        "Updating {}..{}\nFast-forward\n",
+        &master[..7],
+        &topic[..7]
+    ));
+    assert_eq!(read_file(&repo, ".git/refs/heads/master")?, topic);
+    assert_eq!(read_file(&repo, ".git/ORIG_HEAD")?, master);
+    assert_eq!(read_file(&repo, "2.txt")?, "2");
+    assert_eq!(status(&repo)?, "");
+
+    rit(&repo, &["merge", "topic"])?
+        .success()
+        .stdout("Already up to date.\n");
+    Ok(())
+}
+
+#[test]
+fn merges_independent_changes() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    let topic = diverge(&repo, &[("1.txt", "ours")], &[("2.txt", "theirs")])?;
+    let master = read_file(&repo, ".git/refs/heads/master")?;
+
+    rit(&repo, &["merge", "topic"])?
+        .success()
+        .stdout("Merge made by the 'resolve' strategy.\n");
+    assert_eq!(read_file(&repo, "1.txt")?, "ours");
+    assert_eq!(read_file(&repo, "2.txt")?, "theirs");
+    assert_eq!(status(&repo)?, "");
+
+    let commit = cat_head(&repo)?;
+    assert!(commit.contains(&format!("parent {}parent {}\n", master, topic)));
+    assert!(commit.ends_with("\n\nMerge branch 'topic'\n"));
+    assert!(!repo.path().join(".git/MERGE_HEAD").exists());
+    Ok(())
+}
+
+#[test]
+fn merges_changes_to_the_same_file() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    write_file(&repo, "1.txt", "a\nb\nc\n", true)?;
+    commit(&repo, "lines")?;
+    branch(&repo, "topic")?;
+    checkout(&repo, "topic")?;
+    write_file(&repo, "1.txt", "a\nb\nthey\n", true)?;
+    commit(&repo, "theirs")?;
+    checkout(&repo, "master")?;
+    write_file(&repo, "1.txt", "we\nb\nc\n", true)?;
+    commit(&repo, "ours")?;
+
+    rit(&repo, &["merge", "topic"])?.success().stdout(concat!(
+        "Auto-merging 1.txt\n",
+        "Merge made by the 'resolve' strategy.\n",
+    ));
+    assert_eq!(read_file(&repo, "1.txt")?, "we\nb\nthey\n");
+    assert_eq!(status(&repo)?, "");
+    Ok(())
+}
+
+#[test]
+fn stops_for_conflicts_until_committed() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    let topic = diverge(
+        &repo,
+        &[("1.txt", "ours"), ("2.txt", "ours")],
+        &[("1.txt", "theirs\n"), ("3.txt", "3")],
+    )?;
+    let master = read_file(&repo, ".git/refs/heads/master")?;
+
+    rit(&repo, &["merge", "topic"])?.code(1).stdout(concat!(
+        "Auto-merging 1.txt\n",
+        "CONFLICT (content): Merge conflict in 1.txt\n",
+        "Automatic merge failed; fix conflicts and then commit the result.\n",
+    ));
+    assert_eq!(
+        read_file(&repo, "1.txt")?,
+        "<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> topic\n"
+    );
+    assert_eq!(read_file(&repo, "3.txt")?, "3");
+    assert_eq!(read_file(&repo, ".git/MERGE_HEAD")?.trim(), topic);
+    assert_eq!(
+        read_file(&repo, ".git/MERGE_MSG")?,
+        "Merge branch 'topic'\n"
+    );
+
+    rit(&repo, &["merge", "topic"])?
+        .code(128)
+        .stderr(predicate::str::contains("MERGE_HEAD exists"));
+    assert_eq!(status(&repo)?, "UU 1.txt\nA  3.txt\n");
+    rit(&repo, &["commit"])?
+        .code(128)
+        .stderr(predicate::str::starts_with(
+            "error: Committing is not possible because you have unmerged files.\n",
+        ));
+
+    write_file(&repo, "1.txt", "both", true)?;
+    rit(&repo, &["commit"])?
+        .success()
+        .stdout(predicate::str::ends_with("] Merge branch 'topic'\n"));
+    let commit = cat_head(&repo)?;
+    assert!(commit.contains(&format!("parent {}parent {}\n", master, topic)));
+    assert!(!repo.path().join(".git/MERGE_HEAD").exists());
+    assert!(!repo.path().join(".git/MERGE_MSG").exists());
+    assert!(
+        read_file(&repo, ".git/logs/HEAD")?.ends_with("\tcommit (merge): Merge branch 'topic'\n")
+    );
+    Ok(())
+}
+
+#[test]
+fn reports_modify_delete_conflicts() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt", "2.txt"])?;
+    branch(&repo, "topic")?;
+    checkout(&repo, "topic")?;
+    git(&repo, &["rm", "-q", "1.txt"])?;
+    write_file(&repo, "2.txt", "theirs", true)?;
+    commit(&repo, "theirs")?;
+    checkout(&repo, "master")?;
+    write_file(&repo, "1.txt", "ours", true)?;
+    commit(&repo, "ours")?;
+
+    rit(&repo, &["merge", "topic"])?.code(1).stdout(concat!(
+        "CONFLICT (modify/delete): 1.txt deleted in topic and modified in HEAD. ",
+        "Version HEAD of 1.txt left in tree.\n",
+        "Automatic merge failed; fix conflicts and then commit the result.\n",
+    ));
+    assert_eq!(read_file(&repo, "1.txt")?, "ours");
+    assert_eq!(read_file(&repo, "2.txt")?, "theirs");
+    assert_eq!(status(&repo)?, "UD 1.txt\nM  2.txt\n");
+    Ok(())
+}
+
+#[test]
+fn refuses_to_overwrite_local_changes() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    diverge(&repo, &[("1.txt", "ours")], &[("2.txt", "theirs")])?;
+    write_file(&repo, "2.txt", "local", false)?;
+
+    rit(&repo, &["merge", "topic"])?.code(1).stderr(concat!(
+        "error: Your local changes to the following files would be overwritten by merge:\n",
+        "\t2.txt\n",
+        "Please commit your changes or stash them before you merge.\n",
+        "Aborting\n",
+    ));
+    assert_eq!(read_file(&repo, "2.txt")?, "local");
+    assert!(!repo.path().join(".git/MERGE_HEAD").exists());
+
+    rit(&repo, &["merge", "nope"])?
+        .code(1)
+        .stderr("merge: nope - not something we can merge\n");
+    Ok(())
+}
+
+#[test]
+fn refuses_to_merge_with_staged_changes() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    diverge(&repo, &[("1.txt", "ours")], &[("3.txt", "theirs")])?;
+    let master = read_file(&repo, ".git/refs/heads/master")?;
+    write_file(&repo, "2.txt", "staged", true)?;
+
+    rit(&repo, &["merge", "topic"])?.code(1).stderr(concat!(
+        "error: Your local changes to the following files would be overwritten by merge:\n",
+        "\t2.txt\n",
+        "Please commit your changes or stash them before you merge.\n",
+        "Aborting\n",
+    ));
+    assert_eq!(read_file(&repo, ".git/refs/heads/master")?, master);
+    assert!(!repo.path().join("3.txt").exists());
+    assert_eq!(status(&repo)?, "M  2.txt\n");
+    Ok(())
+}
+
+#[test]
+fn switching_branches_abandons_a_merge() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    diverge(&repo, &[("1.txt", "ours")], &[("1.txt", "theirs")])?;
+    rit(&repo, &["merge", "topic"])?.code(1);
+    assert!(repo.path().join(".git/MERGE_HEAD").exists());
+
+    rit(&repo, &["reset", "--hard"])?.success();
+    branch(&repo, "other")?;
+    checkout(&repo, "other")?;
+    assert!(!repo.path().join(".git/MERGE_HEAD").exists());
+    assert!(!repo.path().join(".git/MERGE_MSG").exists());
+    Ok(())
+}
+
+#[test]
+fn merges_text_that_is_not_utf8() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    std::fs::write(repo.path().join("1.txt"), b"caf\xe9\nb\nc\n")?;
+    add_file(&repo, "1.txt")?;
+    commit(&repo, "latin-1")?;
+    branch(&repo, "topic")?;
+    checkout(&repo, "topic")?;
+    std::fs::write(repo.path().join("1.txt"), b"th\xe9\nb\nc\n")?;
+    add_file(&repo, "1.txt")?;
+    commit(&repo, "theirs")?;
+    checkout(&repo, "master")?;
+    std::fs::write(repo.path().join("1.txt"), b"caf\xe9\nb\nd\n")?;
+    add_file(&repo, "1.txt")?;
+    commit(&repo, "ours")?;
+
+    rit(&repo, &["merge", "topic"])?.success();
+    assert_eq!(std::fs::read(repo.path().join("1.txt"))?, b"th\xe9\nb\nd\n");
+    let output = Command::new("git")
+        .current_dir(repo.path())
+        .args(["cat-file", "blob", "HEAD:1.txt"])
+        .output()?;
+    assert_eq!(output.stdout, b"th\xe9\nb\nd\n");
+    assert_eq!(status(&repo)?, "");
+    Ok(())
+}
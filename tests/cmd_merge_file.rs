@@ -0,0 +1,103 @@
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+mod helpers;
+use helpers::*;
+
+use rit::BoxResult;
+
+fn merge_file(repo: &tempdir::TempDir, args: &[&str]) -> BoxResult<assert_cmd::assert::Assert> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    Ok(cmd
+        .current_dir(repo.path())
+        .arg("merge-file")
+        .args(args)
+        .assert())
+}
+
+fn prepare_versions(repo: &tempdir::TempDir, ours: &str, theirs: &str) -> BoxResult<()> {
+    write_file(repo, "base.txt", "a\nb\nc\nd\ne\n", false)?;
+    write_file(repo, "ours.txt", ours, false)?;
+    write_file(repo, "theirs.txt", theirs, false)?;
+    Ok(())
+}
+
+#[test]
+fn merges_clean_changes_into_current() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_versions(&repo, "a\nB\nc\nd\ne\n", "a\nb\nc\nD\ne\n")?;
+
+    merge_file(&repo, &["ours.txt", "base.txt", "theirs.txt"])?
+        .success()
+        .stdout("");
+    assert_eq!(read_file(&repo, "ours.txt")?, "a\nB\nc\nD\ne\n");
+    Ok(())
+}
+
+#[test]
+fn marks_conflicts_and_counts_them() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_versions(&repo, "1\nb\nc\nd\n2\n", "3\nb\nc\nd\n4\n")?;
+
+    merge_file(&repo, &["-p", "ours.txt", "base.txt", "theirs.txt"])?
+        .code(2)
+        .stdout(concat!(
+            "<<<<<<< ours.txt\n1\n=======\n3\n>>>>>>> theirs.txt\n",
+            "b\nc\nd\n",
+            "<<<<<<< ours.txt\n2\n=======\n4\n>>>>>>> theirs.txt\n",
+        ));
+    assert_eq!(read_file(&repo, "ours.txt")?, "1\nb\nc\nd\n2\n");
+
+    merge_file(
+        &repo,
+        &[
+            "--diff3",
+            "-L",
+            "mine",
+            "-L",
+            "old",
+            "ours.txt",
+            "base.txt",
+            "theirs.txt",
+        ],
+    )?
+    .code(2);
+    assert_eq!(
+        read_file(&repo, "ours.txt")?,
+        concat!(
+            "<<<<<<< mine\n1\n||||||| old\na\n=======\n3\n>>>>>>> theirs.txt\n",
+            "b\nc\nd\n",
+            "<<<<<<< mine\n2\n||||||| old\ne\n=======\n4\n>>>>>>> theirs.txt\n",
+        )
+    );
+    Ok(())
+}
+
+#[test]
+fn refuses_missing_and_binary_files() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_versions(&repo, "a\n", "b\0\n")?;
+
+    merge_file(&repo, &["ours.txt", "base.txt", "nope.txt"])?
+        .code(255)
+        .stderr("error: Could not stat nope.txt\n");
+    merge_file(&repo, &["ours.txt", "base.txt", "theirs.txt"])?
+        .code(255)
+        .stderr("error: Cannot merge binary files: theirs.txt\n");
+    Ok(())
+}
+
+#[test]
+fn keeps_text_that_is_not_utf8() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    std::fs::write(repo.path().join("base.txt"), b"caf\xe9\nb\nc\n")?;
+    std::fs::write(repo.path().join("ours.txt"), b"caf\xe9\nb\nd\n")?;
+    std::fs::write(repo.path().join("theirs.txt"), b"th\xe9\nb\nc\n")?;
+
+    merge_file(&repo, &["ours.txt", "base.txt", "theirs.txt"])?.success();
+    assert_eq!(
+        std::fs::read(repo.path().join("ours.txt"))?,
+        b"th\xe9\nb\nd\n"
+    );
+    Ok(())
+}
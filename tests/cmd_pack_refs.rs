@@ -0,0 +1,71 @@
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use std::process::Command;
+
+mod helpers;
+use helpers::*;
+
+use rit::BoxResult;
+
+#[test]
+fn pack_refs_moves_loose_refs_into_packed_refs() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    branch(&repo, "topic/one")?;
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("pack-refs")
+        .arg("--all")
+        .assert()
+        .success();
+
+    assert!(!repo.path().join(".git/refs/heads/master").exists());
+    assert!(!repo.path().join(".git/refs/heads/topic").exists());
+    assert!(repo.path().join(".git/refs/heads").is_dir());
+    let packed = read_file(&repo, ".git/packed-refs")?;
+    assert!(packed.contains(" refs/heads/master\n"));
+    assert!(packed.contains(" refs/heads/topic/one\n"));
+    git(&repo, &["show-ref", "--verify", "refs/heads/topic/one"])?;
+    Ok(())
+}
+
+#[test]
+fn loose_refs_shadow_packed_refs() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    git(&repo, &["pack-refs", "--all"])?;
+    let packed = read_file(&repo, ".git/packed-refs")?;
+
+    write_file(&repo, "2.txt", "2", true)?;
+    commit(&repo, "second")?;
+    let loose = read_file(&repo, ".git/refs/heads/master")?;
+    assert!(!packed.contains(loose.trim()));
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("checkout")
+        .arg("master^")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("HEAD is now at"));
+    assert!(!repo.path().join("2.txt").exists());
+    Ok(())
+}
+
+#[test]
+fn branches_packed_by_git_can_be_checked_out() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    branch(&repo, "feature")?;
+    git(&repo, &["pack-refs", "--all", "--prune"])?;
+    assert!(!repo.path().join(".git/refs/heads/feature").exists());
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("checkout")
+        .arg("feature")
+        .assert()
+        .success()
+        .stderr("Switched to branch 'feature'\n");
+    Ok(())
+}
@@ -0,0 +1,97 @@
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use std::process::Command;
+
+mod helpers;
+use helpers::*;
+
+use rit::BoxResult;
+
+fn reflog_messages(repo: &tempdir::TempDir, name: &str) -> BoxResult<Vec<String>> {
+    let log = read_file(repo, &format!(".git/logs/{}", name))?;
+    Ok(log
+        .lines()
+        .map(|l| l.split_once('\t').unwrap().1.to_owned())
+        .collect())
+}
+
+#[test]
+fn commits_branches_and_checkouts_are_logged() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    branch(&repo, "feature")?;
+    checkout(&repo, "feature")?;
+    write_file(&repo, "2.txt", "2", true)?;
+    commit(&repo, "second")?;
+
+    assert_eq!(
+        reflog_messages(&repo, "HEAD")?,
+        vec![
+            "commit (initial): commit",
+            "checkout: moving from master to feature",
+            "commit: second",
+        ]
+    );
+    assert_eq!(
+        reflog_messages(&repo, "refs/heads/feature")?,
+        vec!["branch: Created from HEAD", "commit: second"]
+    );
+    assert_eq!(
+        reflog_messages(&repo, "refs/heads/master")?,
+        vec!["commit (initial): commit"]
+    );
+
+    let log = read_file(&repo, ".git/logs/refs/heads/master")?;
+    assert!(log.starts_with("0000000000000000000000000000000000000000 "));
+    assert!(log.contains(" A. U. Thor <author@example.com> "));
+    Ok(())
+}
+
+#[test]
+fn reflog_shows_newest_first() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    write_file(&repo, "2.txt", "2", true)?;
+    commit(&repo, "second")?;
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .env("CLICOLOR", "0")
+        .arg("reflog")
+        .arg("show")
+        .arg("master")
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(
+            "^[0-9a-f]{7} master@\\{0\\}: commit: second\n[0-9a-f]{7} master@\\{1\\}: commit \\(initial\\): commit\n$",
+        )?);
+    git(&repo, &["reflog", "show", "master"])?;
+    Ok(())
+}
+
+#[test]
+fn reflog_expire_drops_old_entries() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("reflog")
+        .arg("expire")
+        .arg("--expire=1.day.ago")
+        .arg("--all")
+        .assert()
+        .success();
+    assert_eq!(reflog_messages(&repo, "HEAD")?.len(), 1);
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("reflog")
+        .arg("expire")
+        .arg("--expire=all")
+        .arg("master")
+        .assert()
+        .success();
+    assert!(reflog_messages(&repo, "refs/heads/master")?.is_empty());
+    assert_eq!(reflog_messages(&repo, "HEAD")?.len(), 1);
+    Ok(())
+}
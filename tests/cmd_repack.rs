@@ -0,0 +1,88 @@
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+mod helpers;
+use helpers::*;
+
+use rit::BoxResult;
+
+fn loose_objects(repo: &tempdir::TempDir) -> BoxResult<usize> {
+    let mut count = 0;
+    for shard in std::fs::read_dir(repo.path().join(".git/objects"))? {
+        let shard = shard?.path();
+        if shard.file_name().unwrap().len() == 2 {
+            count += std::fs::read_dir(shard)?.count();
+        }
+    }
+    Ok(count)
+}
+
+fn prepare_history(repo: &tempdir::TempDir) -> BoxResult<()> {
+    for i in 0..3 {
+        let content: String = (0..100 + i)
+            .map(|n| format!("line {} of revision {}\n", n, i % 2))
+            .collect();
+        write_file(repo, "long.txt", &content, true)?;
+        write_file(repo, &format!("dir/{}.txt", i), "small", true)?;
+        commit(repo, "commit")?;
+    }
+    Ok(())
+}
+
+#[test]
+fn gc_packs_and_prunes_loose_objects() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_history(&repo)?;
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path()).arg("gc").assert().success();
+    assert_eq!(loose_objects(&repo)?, 0);
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("status")
+        .arg("--porcelain")
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+#[test]
+fn repacked_objects_are_readable_by_git() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_history(&repo)?;
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("repack")
+        .arg("-a")
+        .arg("-d")
+        .arg("--window=5")
+        .assert()
+        .success();
+    git(&repo, &["fsck", "--full", "--strict"])?;
+    Ok(())
+}
+
+#[test]
+fn gc_keeps_staged_objects() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    branch(&repo, "topic")?;
+    checkout(&repo, "topic")?;
+    write_file(&repo, "staged.txt", "only on topic", true)?;
+    commit(&repo, "topic")?;
+    checkout(&repo, "master")?;
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path()).arg("gc").assert().success();
+
+    // stage the packed blob, then drop everything else that refers to it
+    git(&repo, &["checkout", "topic", "--", "staged.txt"])?;
+    git(&repo, &["branch", "-D", "topic"])?;
+    git(&repo, &["reflog", "expire", "--expire=now", "--all"])?;
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path()).arg("gc").assert().success();
+
+    git(&repo, &["cat-file", "-e", ":staged.txt"])?;
+    git(&repo, &["fsck", "--full", "--strict"])?;
+    Ok(())
+}
@@ -0,0 +1,218 @@
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use std::process::Command;
+
+mod helpers;
+use helpers::*;
+
+use rit::BoxResult;
+
+fn rit(repo: &tempdir::TempDir, args: &[&str]) -> BoxResult<assert_cmd::assert::Assert> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    Ok(cmd.current_dir(repo.path()).args(args).assert())
+}
+
+fn status(repo: &tempdir::TempDir) -> BoxResult<String> {
+    let output = rit(repo, &["status", "--porcelain"])?.success();
+    Ok(String::from_utf8(output.get_output().stdout.clone())?)
+}
+
+/// Two commits: the second changes `1.txt` and adds `2.txt`.
+fn prepare_history(repo: &tempdir::TempDir) -> BoxResult<(String, String)> {
+    prepare_commits(repo, vec!["1.txt"])?;
+    let first = read_file(repo, ".git/refs/heads/master")?;
+    write_file(repo, "1.txt", "second", true)?;
+    write_file(repo, "2.txt", "2", true)?;
+    commit(repo, "second")?;
+    let second = read_file(repo, ".git/refs/heads/master")?;
+    Ok((first, second))
+}
+
+#[test]
+fn soft_reset_only_moves_head() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    let (first, second) = prepare_history(&repo)?;
+
+    rit(&repo, &["reset", "--soft", "HEAD^"])?
+        .success()
+        .stdout("");
+    assert_eq!(read_file(&repo, ".git/refs/heads/master")?, first);
+    assert_eq!(read_file(&repo, ".git/ORIG_HEAD")?, second);
+    assert_eq!(status(&repo)?, "M  1.txt\nA  2.txt\n");
+    assert!(read_file(&repo, ".git/logs/HEAD")?.ends_with("\treset: moving to HEAD^\n"));
+    Ok(())
+}
+
+#[test]
+fn mixed_reset_rebuilds_the_index() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    let (first, _) = prepare_history(&repo)?;
+
+    rit(&repo, &["reset", "HEAD~1"])?
+        .success()
+        .stdout("Unstaged changes after reset:\nM\t1.txt\n");
+    assert_eq!(read_file(&repo, ".git/refs/heads/master")?, first);
+    assert_eq!(status(&repo)?, " M 1.txt\n?? 2.txt\n");
+    assert_eq!(read_file(&repo, "1.txt")?, "second");
+
+    rit(&repo, &["reset", "ORIG_HEAD"])?.success().stdout("");
+    assert_eq!(status(&repo)?, "");
+    Ok(())
+}
+
+#[test]
+fn hard_reset_discards_local_changes() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    let (first, second) = prepare_history(&repo)?;
+    write_file(&repo, "1.txt", "local", false)?;
+    write_file(&repo, "3.txt", "staged", true)?;
+    write_file(&repo, "untracked.txt", "keep", false)?;
+
+    rit(&repo, &["reset", "--hard", "HEAD^"])?
+        .success()
+        .stdout(format!("HEAD is now at {} commit\n", &first[..7]));
+    assert_eq!(read_file(&repo, "1.txt")?, "1.txt");
+    assert!(!repo.path().join("2.txt").exists());
+    assert!(!repo.path().join("3.txt").exists());
+    assert_eq!(status(&repo)?, "?? untracked.txt\n");
+
+    rit(&repo, &["reset", "--hard", "ORIG_HEAD"])?
+        .success()
+        .stdout(format!("HEAD is now at {} second\n", &second[..7]));
+    assert_eq!(read_file(&repo, "2.txt")?, "2");
+    assert_eq!(status(&repo)?, "?? untracked.txt\n");
+    Ok(())
+}
+
+#[test]
+fn hard_reset_overwrites_untracked_files() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    let (first, second) = prepare_history(&repo)?;
+    rit(&repo, &["reset", "--hard", "HEAD^"])?.success();
+    write_file(&repo, "2.txt", "untracked", false)?;
+
+    rit(&repo, &["reset", "--hard", "ORIG_HEAD"])?
+        .success()
+        .stdout(format!("HEAD is now at {} second\n", &second[..7]));
+    assert_eq!(read_file(&repo, "2.txt")?, "2");
+    assert_eq!(read_file(&repo, ".git/ORIG_HEAD")?, first);
+    assert_eq!(status(&repo)?, "");
+
+    rit(&repo, &["reset", "--hard", "HEAD^"])?.success();
+    mkdir(&repo, "2.txt")?;
+    write_file(&repo, "2.txt/untracked.txt", "untracked", false)?;
+    rit(&repo, &["reset", "--hard", "ORIG_HEAD"])?.success();
+    assert_eq!(read_file(&repo, "2.txt")?, "2");
+    assert_eq!(status(&repo)?, "");
+    Ok(())
+}
+
+#[test]
+fn failed_hard_reset_keeps_orig_head() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    let (first, second) = prepare_history(&repo)?;
+    rit(&repo, &["reset", "--hard", "HEAD^"])?.success();
+    let output = Command::new("git")
+        .current_dir(repo.path())
+        .args(["rev-parse", "ORIG_HEAD:2.txt"])
+        .output()?;
+    let blob = String::from_utf8(output.stdout)?;
+    std::fs::remove_file(
+        repo.path()
+            .join(".git/objects")
+            .join(&blob[..2])
+            .join(blob[2..].trim()),
+    )?;
+
+    rit(&repo, &["reset", "--hard", "ORIG_HEAD"])?.failure();
+    assert_eq!(read_file(&repo, ".git/refs/heads/master")?, first);
+    assert_eq!(read_file(&repo, ".git/ORIG_HEAD")?, second);
+    Ok(())
+}
+
+#[test]
+fn reset_paths_unstages_them() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    let (_, second) = prepare_history(&repo)?;
+    write_file(&repo, "1.txt", "changed", true)?;
+    write_file(&repo, "2.txt", "changed", true)?;
+
+    rit(&repo, &["reset", "1.txt"])?
+        .success()
+        .stdout("Unstaged changes after reset:\nM\t1.txt\n");
+    assert_eq!(status(&repo)?, " M 1.txt\nM  2.txt\n");
+    assert_eq!(read_file(&repo, ".git/refs/heads/master")?, second);
+
+    rit(&repo, &["reset", "HEAD^", "--", "2.txt"])?.success();
+    assert_eq!(status(&repo)?, " M 1.txt\nD  2.txt\n?? 2.txt\n");
+
+    rit(&repo, &["reset", "--hard", "HEAD", "--", "1.txt"])?
+        .failure()
+        .stderr("fatal: Cannot do hard reset with paths.\n");
+    Ok(())
+}
+
+#[test]
+fn reset_before_the_first_commit() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    write_file(&repo, "1.txt", "1", true)?;
+    write_file(&repo, "2.txt", "2", true)?;
+
+    rit(&repo, &["reset", "1.txt"])?.success();
+    let output = Command::new("git")
+        .current_dir(repo.path())
+        .args(["ls-files"])
+        .output()?;
+    assert_eq!(String::from_utf8(output.stdout)?, "2.txt\n");
+    rit(&repo, &["reset", "nope"])?
+        .failure()
+        .stderr(predicate::str::contains("nope"));
+    Ok(())
+}
+
+#[test]
+fn reset_abandons_a_conflicted_merge() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt", "2.txt"])?;
+    branch(&repo, "topic")?;
+    checkout(&repo, "topic")?;
+    write_file(&repo, "1.txt", "theirs", true)?;
+    commit(&repo, "theirs")?;
+    checkout(&repo, "master")?;
+    write_file(&repo, "1.txt", "ours", true)?;
+    commit(&repo, "ours")?;
+    let merge = || -> BoxResult<assert_cmd::assert::Assert> {
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+        Ok(cmd
+            .current_dir(repo.path())
+            .env("GIT_AUTHOR_EMAIL", "author@example.com")
+            .env("GIT_AUTHOR_NAME", "A. U. Thor")
+            .args(["merge", "topic"])
+            .assert())
+    };
+    merge()?.code(1);
+
+    rit(&repo, &["reset", "--soft"])?
+        .code(128)
+        .stderr("fatal: Cannot do a soft reset in the middle of a merge.\n");
+    rit(&repo, &["reset", "--hard"])?.success();
+    assert!(!repo.path().join(".git/MERGE_HEAD").exists());
+    assert!(!repo.path().join(".git/MERGE_MSG").exists());
+    assert_eq!(status(&repo)?, "");
+
+    write_file(&repo, "2.txt", "unrelated", true)?;
+    commit(&repo, "unrelated")?;
+    let output = Command::new("git")
+        .current_dir(repo.path())
+        .args(["rev-list", "--parents", "-n", "1", "HEAD"])
+        .output()?;
+    assert_eq!(
+        String::from_utf8(output.stdout)?.split_whitespace().count(),
+        2
+    );
+
+    merge()?
+        .code(1)
+        .stdout(predicate::str::contains("Merge conflict in 1.txt"));
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+mod helpers;
+use helpers::*;
+
+use rit::BoxResult;
+
+fn rit(repo: &tempdir::TempDir, args: &[&str]) -> BoxResult<assert_cmd::assert::Assert> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    Ok(cmd.current_dir(repo.path()).args(args).assert())
+}
+
+fn status(repo: &tempdir::TempDir) -> BoxResult<String> {
+    let output = rit(repo, &["status", "--porcelain"])?.success();
+    Ok(String::from_utf8(output.get_output().stdout.clone())?)
+}
+
+#[test]
+fn restore_worktree_from_index() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt", "a/2.txt", "a/b/3.txt"])?;
+
+    write_file(&repo, "a/2.txt", "changed", false)?;
+    delete(&repo, "a/b/3.txt")?;
+    write_file(&repo, "1.txt", "changed", false)?;
+    rit(&repo, &["restore", "a"])?.success();
+    assert_eq!(read_file(&repo, "a/2.txt")?, "a/2.txt");
+    assert_eq!(read_file(&repo, "a/b/3.txt")?, "a/b/3.txt");
+    assert_eq!(status(&repo)?, " M 1.txt\n");
+    Ok(())
+}
+
+#[test]
+fn restore_staged_unstages_changes() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+
+    write_file(&repo, "1.txt", "changed", true)?;
+    write_file(&repo, "new.txt", "new", true)?;
+    assert_eq!(status(&repo)?, "M  1.txt\nA  new.txt\n");
+
+    rit(&repo, &["restore", "--staged", "."])?.success();
+    assert_eq!(status(&repo)?, " M 1.txt\n?? new.txt\n");
+    assert_eq!(read_file(&repo, "1.txt")?, "changed");
+    Ok(())
+}
+
+#[test]
+fn restore_from_source() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    write_file(&repo, "1.txt", "second", true)?;
+    write_file(&repo, "2.txt", "2", true)?;
+    commit(&repo, "second")?;
+
+    rit(&repo, &["restore", "--source=HEAD~1", "1.txt"])?.success();
+    assert_eq!(read_file(&repo, "1.txt")?, "1.txt");
+    assert_eq!(status(&repo)?, " M 1.txt\n");
+
+    // without overlay, files missing from the source are removed
+    rit(
+        &repo,
+        &["restore", "-s", "HEAD^", "--staged", "--worktree", "."],
+    )?
+    .success();
+    assert!(!repo.path().join("2.txt").exists());
+    assert_eq!(status(&repo)?, "M  1.txt\nD  2.txt\n");
+
+    rit(&repo, &["restore", "nope"])?
+        .code(1)
+        .stderr("error: pathspec 'nope' did not match any file(s) known to rit\n");
+    Ok(())
+}
@@ -0,0 +1,127 @@
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use std::process::Command;
+
+mod helpers;
+use helpers::*;
+
+use rit::BoxResult;
+
+fn rev_list(repo: &tempdir::TempDir, args: &[&str]) -> BoxResult<Vec<String>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    let output = cmd
+        .current_dir(repo.path())
+        .arg("rev-list")
+        .args(args)
+        .output()?;
+    assert!(output.status.success());
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .map(String::from)
+        .collect())
+}
+
+/// `master` has two commits and `side` one since they forked.
+fn prepare_fork(repo: &tempdir::TempDir) -> BoxResult<(String, String, String)> {
+    prepare_commits(repo, vec!["1.txt"])?;
+    branch(repo, "side")?;
+    checkout(repo, "side")?;
+    write_file(repo, "side.txt", "side", true)?;
+    commit(repo, "side")?;
+    checkout(repo, "master")?;
+    write_file(repo, "2.txt", "2", true)?;
+    commit(repo, "two")?;
+    let two = read_file(repo, ".git/refs/heads/master")?.trim().to_owned();
+    write_file(repo, "3.txt", "3", true)?;
+    commit(repo, "three")?;
+
+    let side = read_file(repo, ".git/refs/heads/side")?.trim().to_owned();
+    let three = read_file(repo, ".git/refs/heads/master")?.trim().to_owned();
+    Ok((side, two, three))
+}
+
+#[test]
+fn ranges_exclude_history() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    let (side, two, three) = prepare_fork(&repo)?;
+
+    assert_eq!(rev_list(&repo, &["master..side"])?, [side.as_str()]);
+    assert_eq!(rev_list(&repo, &["side", "^master"])?, [side.as_str()]);
+    assert_eq!(
+        rev_list(&repo, &["side..master"])?,
+        [three.as_str(), two.as_str()]
+    );
+    assert_eq!(rev_list(&repo, &["master^!"])?, [three.as_str()]);
+    assert_eq!(rev_list(&repo, &["master^@"])?.len(), 2);
+    assert_eq!(rev_list(&repo, &["master", "--count"])?, ["3"]);
+    Ok(())
+}
+
+#[test]
+fn symmetric_difference_with_left_right() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    let (side, two, three) = prepare_fork(&repo)?;
+
+    // commits made in the same second come out in the order they were
+    // reached, so compare the order with git's rather than fixing it here
+    let listed = rev_list(&repo, &["--left-right", "master...side"])?;
+    let output = Command::new("git")
+        .current_dir(repo.path())
+        .args(["rev-list", "--left-right", "master...side"])
+        .output()?;
+    let expected: Vec<String> = String::from_utf8(output.stdout)?
+        .lines()
+        .map(String::from)
+        .collect();
+    assert_eq!(listed, expected);
+    assert_eq!(listed[0], format!("<{}", three));
+    assert!(listed.contains(&format!("<{}", two)));
+    assert!(listed.contains(&format!(">{}", side)));
+    assert_eq!(
+        rev_list(&repo, &["--left-right", "--count", "master...side"])?,
+        ["2\t1"]
+    );
+    Ok(())
+}
+
+#[test]
+fn ancestry_path_keeps_descendants_of_the_bottom() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    let (side, two, three) = prepare_fork(&repo)?;
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .env("GIT_AUTHOR_EMAIL", "author@example.com")
+        .env("GIT_AUTHOR_NAME", "A. U. Thor")
+        .args(["merge", "side"])
+        .assert()
+        .success();
+    let merge = read_file(&repo, ".git/refs/heads/master")?
+        .trim()
+        .to_owned();
+
+    assert_eq!(rev_list(&repo, &["side..master"])?.len(), 3);
+    assert_eq!(
+        rev_list(&repo, &["--ancestry-path", "side..master"])?,
+        [merge.as_str()]
+    );
+    assert_eq!(
+        rev_list(&repo, &["--ancestry-path", &format!("{}..master", two)])?,
+        [merge.as_str(), three.as_str()]
+    );
+    assert!(rev_list(&repo, &["--ancestry-path", &format!("{}..{}", side, three)])?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn rev_list_requires_a_revision() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("rev-list")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("usage: rit rev-list"));
+    Ok(())
+}
@@ -0,0 +1,167 @@
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use std::process::Command;
+
+mod helpers;
+use helpers::*;
+
+use rit::BoxResult;
+
+fn rev_parse(repo: &tempdir::TempDir, args: &[&str]) -> BoxResult<assert_cmd::assert::Assert> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    Ok(cmd
+        .current_dir(repo.path())
+        .arg("rev-parse")
+        .args(args)
+        .assert())
+}
+
+#[test]
+fn resolves_revisions() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    let first = read_file(&repo, ".git/refs/heads/master")?;
+    write_file(&repo, "2.txt", "2", true)?;
+    commit(&repo, "second")?;
+    let second = read_file(&repo, ".git/refs/heads/master")?;
+
+    rev_parse(&repo, &["HEAD", "HEAD^"])?
+        .success()
+        .stdout(format!("{}{}", second, first));
+    rev_parse(&repo, &["HEAD^..HEAD"])?
+        .success()
+        .stdout(format!("{}^{}", second, first));
+    rev_parse(&repo, &["--short", "HEAD"])?
+        .success()
+        .stdout(format!("{}\n", &second[..7]));
+    rev_parse(&repo, &["--short=12", "HEAD"])?
+        .success()
+        .stdout(format!("{}\n", &second[..12]));
+    Ok(())
+}
+
+#[test]
+fn verify_needs_a_single_revision() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+
+    rev_parse(&repo, &["--verify", "nope"])?
+        .failure()
+        .stderr(predicate::str::contains("fatal: Needed a single revision"));
+    rev_parse(&repo, &["--verify", "HEAD", "HEAD"])?.failure();
+    rev_parse(&repo, &["--verify", "-q", "nope"])?
+        .code(1)
+        .stderr("");
+    rev_parse(&repo, &["nope"])?
+        .failure()
+        .stderr(predicate::str::contains("ambiguous argument 'nope'"));
+    Ok(())
+}
+
+#[test]
+fn symbolic_names() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+
+    rev_parse(&repo, &["--abbrev-ref", "HEAD"])?
+        .success()
+        .stdout("master\n");
+    rev_parse(&repo, &["--symbolic-full-name", "HEAD", "master"])?
+        .success()
+        .stdout("refs/heads/master\nrefs/heads/master\n");
+    rev_parse(&repo, &["--symbolic-full-name", "HEAD~0"])?
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+#[test]
+fn repository_layout() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    let toplevel = std::fs::canonicalize(repo.path())?;
+
+    rev_parse(
+        &repo,
+        &["--git-dir", "--show-toplevel", "--is-inside-work-tree"],
+    )?
+    .success()
+    .stdout(format!(".git\n{}\ntrue\n", toplevel.display()));
+
+    mkdir(&repo, "d")?;
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path().join("d"))
+        .args([
+            "rev-parse",
+            "--git-dir",
+            "--show-toplevel",
+            "--is-inside-work-tree",
+        ])
+        .assert()
+        .success()
+        .stdout(format!(
+            "{}\n{}\ntrue\n",
+            toplevel.join(".git").display(),
+            toplevel.display()
+        ));
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path().join(".git"))
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .assert()
+        .success()
+        .stdout("false\n");
+    Ok(())
+}
+
+#[test]
+fn layout_outside_a_repository() -> BoxResult<()> {
+    let dir = tempdir::TempDir::new("rit")?;
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(dir.path())
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .assert()
+        .code(128)
+        .stdout("")
+        .stderr(predicate::str::contains("fatal: not a git repository"));
+    Ok(())
+}
+
+#[test]
+fn core_abbrev_sets_short_length() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    let head = read_file(&repo, ".git/refs/heads/master")?;
+    git(&repo, &["config", "core.abbrev", "12"])?;
+
+    rev_parse(&repo, &["--short", "HEAD"])?
+        .success()
+        .stdout(format!("{}\n", &head[..12]));
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .env("CLICOLOR", "0")
+        .args(["log", "--format=%h"])
+        .assert()
+        .success()
+        .stdout(format!("{}\n", &head[..12]));
+    Ok(())
+}
+
+#[test]
+fn empty_reflog_is_an_error() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .args(["tag", "x"])
+        .assert()
+        .success();
+
+    rev_parse(&repo, &["x@{0}"])?
+        .failure()
+        .stderr(predicate::str::contains("log for 'x' is empty"));
+    rev_parse(&repo, &["x@{yesterday}"])?
+        .failure()
+        .stderr(predicate::str::contains("log for 'x' is empty"));
+    Ok(())
+}
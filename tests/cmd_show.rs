@@ -0,0 +1,122 @@
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use std::process::Command;
+
+mod helpers;
+use helpers::*;
+
+use rit::BoxResult;
+
+fn prepare_history(repo: &tempdir::TempDir) -> BoxResult<()> {
+    mkdir(repo, "dir")?;
+    prepare_commits(repo, vec!["1.txt", "dir/2.txt"])?;
+    write_file(repo, "1.txt", "changed\n", true)?;
+    commit(repo, "second")?;
+    Ok(())
+}
+
+fn show(repo: &tempdir::TempDir, object: &str) -> BoxResult<assert_cmd::assert::Assert> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    Ok(cmd
+        .current_dir(repo.path())
+        .env("CLICOLOR", "0")
+        .arg("show")
+        .arg(object)
+        .assert())
+}
+
+#[test]
+fn show_blobs_by_path() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_history(&repo)?;
+
+    show(&repo, "HEAD:1.txt")?.success().stdout("changed\n");
+    show(&repo, "HEAD~1:1.txt")?.success().stdout("1.txt");
+    show(&repo, "HEAD^:dir/2.txt")?
+        .success()
+        .stdout("dir/2.txt");
+    show(&repo, ":1.txt")?.success().stdout("changed\n");
+    Ok(())
+}
+
+#[test]
+fn show_trees() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_history(&repo)?;
+
+    show(&repo, "HEAD^{tree}")?
+        .success()
+        .stdout("tree HEAD^{tree}\n\n1.txt\ndir/\n");
+    show(&repo, "HEAD:dir")?
+        .success()
+        .stdout("tree HEAD:dir\n\n2.txt\n");
+    Ok(())
+}
+
+#[test]
+fn show_commit_with_patch() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_history(&repo)?;
+
+    show(&repo, "HEAD")?
+        .success()
+        .stdout(predicate::str::starts_with("commit "))
+        .stdout(predicate::str::contains(
+            "\n    second\n\ndiff --git a/1.txt b/1.txt\n",
+        ))
+        .stdout(predicate::str::contains("+changed\n"));
+    show(&repo, "HEAD^{blob}")?
+        .failure()
+        .stderr(predicate::str::contains(
+            "expected blob type, but the object dereferences to commit type",
+        ));
+    Ok(())
+}
+
+#[test]
+fn missing_paths_are_reported() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_history(&repo)?;
+
+    show(&repo, "HEAD:nope.txt")?
+        .failure()
+        .stderr(predicate::str::contains(
+            "error: path 'nope.txt' does not exist in 'HEAD'",
+        ));
+    show(&repo, ":nope.txt")?
+        .failure()
+        .stderr(predicate::str::contains(
+            "error: path 'nope.txt' does not exist in the index",
+        ));
+    Ok(())
+}
+
+#[test]
+fn show_conflict_stages() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["a"])?;
+    branch(&repo, "topic")?;
+    checkout(&repo, "topic")?;
+    write_file(&repo, "a", "theirs\n", true)?;
+    commit(&repo, "theirs")?;
+    checkout(&repo, "master")?;
+    write_file(&repo, "a", "ours\n", true)?;
+    commit(&repo, "ours")?;
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .env("GIT_AUTHOR_EMAIL", "author@example.com")
+        .env("GIT_AUTHOR_NAME", "A. U. Thor")
+        .args(["merge", "topic"])
+        .assert()
+        .code(1);
+
+    show(&repo, ":1:a")?.success().stdout("a");
+    show(&repo, ":2:a")?.success().stdout("ours\n");
+    show(&repo, ":3:a")?.success().stdout("theirs\n");
+    show(&repo, ":a")?
+        .failure()
+        .stderr(predicate::str::contains(
+            "path 'a' is in the index, but not at stage 0",
+        ));
+    Ok(())
+}
@@ -335,3 +335,54 @@ fn lists_untracked_dirs_that_contain_files() -> BoxResult<()> {
         );
     Ok(())
 }
+
+#[test]
+fn reports_unmerged_paths() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt", "2.txt", "3.txt"])?;
+    branch(&repo, "topic")?;
+    checkout(&repo, "topic")?;
+    write_file(&repo, "1.txt", "theirs", true)?;
+    git(&repo, &["rm", "-q", "2.txt"])?;
+    write_file(&repo, "3.txt", "theirs", true)?;
+    write_file(&repo, "4.txt", "theirs", true)?;
+    commit(&repo, "theirs")?;
+    checkout(&repo, "master")?;
+    write_file(&repo, "1.txt", "ours", true)?;
+    write_file(&repo, "2.txt", "ours", true)?;
+    git(&repo, &["rm", "-q", "3.txt"])?;
+    write_file(&repo, "4.txt", "ours", true)?;
+    commit(&repo, "ours")?;
+    Command::new("git")
+        .current_dir(repo.path())
+        .env("GIT_COMMITTER_NAME", "A. U. Thor")
+        .env("GIT_COMMITTER_EMAIL", "author@example.com")
+        .args(["merge", "-q", "topic"])
+        .assert()
+        .code(1);
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .args(["status", "--porcelain"])
+        .assert()
+        .success()
+        .stdout("UU 1.txt\nUD 2.txt\nDU 3.txt\nAA 4.txt\n");
+
+    add_file(&repo, "1.txt")?;
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .args(["status", "--porcelain"])
+        .assert()
+        .success()
+        .stdout("M  1.txt\nUD 2.txt\nDU 3.txt\nAA 4.txt\n");
+    let output = Command::new("git")
+        .current_dir(repo.path())
+        .args(["ls-files", "--stage", "1.txt", "4.txt"])
+        .output()?;
+    let stages: Vec<&str> = std::str::from_utf8(&output.stdout)?
+        .lines()
+        .map(|line| line.split(&[' ', '\t'][..]).nth(2).unwrap())
+        .collect();
+    assert_eq!(stages, vec!["0", "2", "3"]);
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use std::process::Command;
+
+mod helpers;
+use helpers::*;
+
+use rit::BoxResult;
+
+fn switch(repo: &tempdir::TempDir, args: &[&str]) -> BoxResult<assert_cmd::assert::Assert> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    Ok(cmd
+        .current_dir(repo.path())
+        .arg("switch")
+        .args(args)
+        .assert())
+}
+
+#[test]
+fn create_and_switch_back() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    let master = read_file(&repo, ".git/refs/heads/master")?;
+
+    switch(&repo, &["-c", "topic"])?
+        .success()
+        .stderr("Switched to a new branch 'topic'\n");
+    assert_eq!(read_file(&repo, ".git/HEAD")?, "ref: refs/heads/topic\n");
+    assert_eq!(read_file(&repo, ".git/refs/heads/topic")?, master);
+
+    write_file(&repo, "2.txt", "2", true)?;
+    commit(&repo, "on topic")?;
+    switch(&repo, &["-"])?
+        .success()
+        .stderr("Switched to branch 'master'\n");
+    assert!(!repo.path().join("2.txt").exists());
+    switch(&repo, &["master"])?
+        .success()
+        .stderr("Already on 'master'\n");
+
+    switch(&repo, &["-c", "topic"])?
+        .failure()
+        .stderr("fatal: A branch named 'topic' already exists.\n");
+    switch(&repo, &["-C", "topic", "master"])?
+        .success()
+        .stderr("Reset branch 'topic'\n");
+    assert_eq!(read_file(&repo, ".git/refs/heads/topic")?, master);
+    Ok(())
+}
+
+#[test]
+fn detach_requires_the_option() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    let first = read_file(&repo, ".git/refs/heads/master")?;
+    write_file(&repo, "2.txt", "2", true)?;
+    commit(&repo, "second")?;
+
+    switch(&repo, &["HEAD^"])?
+        .failure()
+        .stderr(predicate::str::ends_with(
+            "fatal: a branch is expected, got commit 'HEAD^'\n",
+        ));
+    switch(&repo, &["nope"])?
+        .failure()
+        .stderr("fatal: invalid reference: nope\n");
+
+    switch(&repo, &["--detach", "HEAD^"])?
+        .success()
+        .stderr(format!("HEAD is now at {} commit\n", &first[..7]));
+    assert_eq!(read_file(&repo, ".git/HEAD")?, first);
+    assert!(!repo.path().join("2.txt").exists());
+
+    switch(&repo, &["--detach", "master"])?
+        .success()
+        .stderr(predicate::str::starts_with(format!(
+            "Previous HEAD position was {} commit\nHEAD is now at ",
+            &first[..7]
+        )));
+    Ok(())
+}
+
+#[test]
+fn orphan_starts_a_new_history() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt", "a/2.txt"])?;
+
+    switch(&repo, &["--orphan", "fresh"])?
+        .success()
+        .stderr("Switched to a new branch 'fresh'\n");
+    assert_eq!(read_file(&repo, ".git/HEAD")?, "ref: refs/heads/fresh\n");
+    assert!(!repo.path().join("1.txt").exists());
+    assert!(!repo.path().join("a").exists());
+
+    write_file(&repo, "3.txt", "3", true)?;
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .env("GIT_AUTHOR_EMAIL", "author@example.com")
+        .env("GIT_AUTHOR_NAME", "A. U. Thor")
+        .args(["commit", "-m", "fresh start"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("[fresh (root-commit) "));
+    Ok(())
+}
+
+#[test]
+fn reports_tracking_status() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    switch(&repo, &["-c", "topic"])?.success();
+    write_file(&repo, "2.txt", "2", true)?;
+    commit(&repo, "on topic")?;
+    git(&repo, &["branch", "--set-upstream-to", "master"])?;
+
+    switch(&repo, &["master"])?.success();
+    switch(&repo, &["topic"])?.success().stdout(concat!(
+        "Your branch is ahead of 'master' by 1 commit.\n",
+        "  (use \"rit push\" to publish your local commits)\n",
+    ));
+    Ok(())
+}
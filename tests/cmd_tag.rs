@@ -0,0 +1,108 @@
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use std::process::Command;
+
+mod helpers;
+use helpers::*;
+
+use rit::BoxResult;
+
+fn rit(repo: &tempdir::TempDir, args: &[&str]) -> BoxResult<assert_cmd::assert::Assert> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    Ok(cmd
+        .current_dir(repo.path())
+        .env("CLICOLOR", "0")
+        .env("GIT_AUTHOR_NAME", "A. U. Thor")
+        .env("GIT_AUTHOR_EMAIL", "author@example.com")
+        .args(args)
+        .assert())
+}
+
+#[test]
+fn lightweight_tags_point_at_commits() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    let head = read_file(&repo, ".git/refs/heads/master")?;
+
+    rit(&repo, &["tag", "v1"])?.success();
+    assert_eq!(read_file(&repo, ".git/refs/tags/v1")?, head);
+
+    rit(&repo, &["tag", "v1"])?
+        .failure()
+        .stderr("fatal: tag 'v1' already exists\n");
+    rit(&repo, &["tag", "bad..name"])?
+        .failure()
+        .stderr(predicate::str::contains("not a valid tag name"));
+    Ok(())
+}
+
+#[test]
+fn annotated_tags_are_objects() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    let head = read_file(&repo, ".git/refs/heads/master")?;
+
+    rit(&repo, &["tag", "-a", "v1", "-m", "First release"])?.success();
+    let tag = read_file(&repo, ".git/refs/tags/v1")?;
+    assert_ne!(tag, head);
+
+    rit(&repo, &["show", "-s", "v1"])?
+        .success()
+        .stdout(predicate::str::starts_with(
+            "tag v1\nTagger: A. U. Thor <author@example.com>\nDate:   ",
+        ))
+        .stdout(predicate::str::contains("\n\nFirst release\n\ncommit "));
+    rit(&repo, &["rev-parse", "v1", "v1^{}", "v1^{commit}"])?
+        .success()
+        .stdout(format!("{}{}{}", tag, head, head));
+    rit(&repo, &["log", "--format=%s", "v1"])?
+        .success()
+        .stdout("commit\n");
+
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo.path())
+        .args(["cat-file", "-p", "v1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(format!(
+            "object {}type commit\ntag v1\ntagger A. U. Thor",
+            head
+        )));
+    Ok(())
+}
+
+#[test]
+fn list_and_delete() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+    let head = read_file(&repo, ".git/refs/heads/master")?;
+
+    for name in &["v1.1", "v1.0", "v2.0"] {
+        rit(&repo, &["tag", name])?.success();
+    }
+    rit(&repo, &["tag"])?.success().stdout("v1.0\nv1.1\nv2.0\n");
+    rit(&repo, &["tag", "-l", "v1.*"])?
+        .success()
+        .stdout("v1.0\nv1.1\n");
+
+    rit(&repo, &["tag", "-d", "v1.0"])?
+        .success()
+        .stdout(format!("Deleted tag 'v1.0' (was {})\n", &head[..7]));
+    rit(&repo, &["tag", "-d", "v1.0"])?
+        .code(1)
+        .stderr("error: tag 'v1.0' not found.\n");
+    rit(&repo, &["tag"])?.success().stdout("v1.1\nv2.0\n");
+    Ok(())
+}
+
+#[test]
+fn verify_requires_a_signature() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_commits(&repo, vec!["1.txt"])?;
+
+    rit(&repo, &["tag", "-m", "unsigned", "v1"])?.success();
+    rit(&repo, &["tag", "-v", "v1"])?
+        .code(1)
+        .stderr("error: no signature found\n");
+    Ok(())
+}
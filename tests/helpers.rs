@@ -1,3 +1,5 @@
+#![allow(dead_code)]
+
 use assert_cmd::prelude::*;
 use std::fs::File;
 use std::io::Write;
@@ -86,3 +88,33 @@ pub fn prepare_commits(repo: &TempDir, files: Vec<&str>) -> Result<(), std::io::
     }
     commit(repo, "commit")
 }
+
+pub fn git(repo: &TempDir, args: &[&str]) -> Result<(), std::io::Error> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo.path()).args(args).assert().success();
+    Ok(())
+}
+
+pub fn branch(repo: &TempDir, name: &str) -> Result<(), std::io::Error> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.current_dir(repo.path())
+        .arg("branch")
+        .arg(name)
+        .assert()
+        .success();
+    Ok(())
+}
+
+pub fn checkout(repo: &TempDir, revision: &str) -> Result<(), std::io::Error> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.current_dir(repo.path())
+        .arg("checkout")
+        .arg(revision)
+        .assert()
+        .success();
+    Ok(())
+}
+
+pub fn read_file(repo: &TempDir, path: &str) -> Result<String, std::io::Error> {
+    std::fs::read_to_string(repo.path().join(path))
+}
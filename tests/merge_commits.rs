@@ -0,0 +1,102 @@
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use std::process::Command;
+
+mod helpers;
+use helpers::*;
+
+use rit::BoxResult;
+
+fn git_output(repo: &tempdir::TempDir, args: &[&str]) -> BoxResult<String> {
+    let output = Command::new("git")
+        .env("GIT_AUTHOR_NAME", "A. U. Thor")
+        .env("GIT_AUTHOR_EMAIL", "author@example.com")
+        .env("GIT_COMMITTER_NAME", "A. U. Thor")
+        .env("GIT_COMMITTER_EMAIL", "author@example.com")
+        .current_dir(repo.path())
+        .args(args)
+        .output()?;
+    Ok(String::from_utf8(output.stdout)?.trim().to_owned())
+}
+
+/// Builds `master` as a merge of two single-file branches, using plumbing
+/// so that git never rewrites rit's index.
+fn prepare_merge(repo: &tempdir::TempDir) -> BoxResult<()> {
+    prepare_commits(repo, vec!["1.txt"])?;
+    branch(repo, "side")?;
+    checkout(repo, "side")?;
+    write_file(repo, "side.txt", "side", true)?;
+    commit(repo, "side")?;
+    checkout(repo, "master")?;
+    write_file(repo, "main.txt", "main", true)?;
+    commit(repo, "main")?;
+
+    let tree = git_output(repo, &["rev-parse", "master^{tree}"])?;
+    let merge = git_output(
+        repo,
+        &[
+            "commit-tree",
+            &tree,
+            "-p",
+            "master",
+            "-p",
+            "side",
+            "-m",
+            "merge",
+        ],
+    )?;
+    git(repo, &["update-ref", "refs/heads/master", &merge])?;
+    Ok(())
+}
+
+#[test]
+fn nth_parent_selects_merge_parents() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_merge(&repo)?;
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("checkout")
+        .arg("master^2")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("HEAD is now at"));
+    assert!(repo.path().join("side.txt").exists());
+    assert!(!repo.path().join("main.txt").exists());
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("checkout")
+        .arg("master^1")
+        .assert()
+        .success();
+    assert!(!repo.path().join("side.txt").exists());
+    assert!(repo.path().join("main.txt").exists());
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("checkout")
+        .arg("master^3")
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn gc_keeps_every_parent() -> BoxResult<()> {
+    let repo = prepare_repo()?;
+    prepare_merge(&repo)?;
+    // without the reflogs, the side branch is reachable only through the merge
+    git(&repo, &["branch", "-D", "side"])?;
+    std::fs::remove_dir_all(repo.path().join(".git/logs"))?;
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path()).arg("gc").assert().success();
+    let loose = std::fs::read_dir(repo.path().join(".git/objects"))?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name().len() == 2)
+        .count();
+    assert_eq!(loose, 0);
+    git(&repo, &["fsck", "--full", "--strict"])?;
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+mod helpers;
+use helpers::*;
+
+use rit::BoxResult;
+
+fn long_file(lines: usize, skip: usize) -> String {
+    (0..lines)
+        .filter(|&i| i != skip)
+        .map(|i| format!("line {} of a file long enough to delta\n", i))
+        .collect()
+}
+
+fn prepare_packed_repo() -> BoxResult<tempdir::TempDir> {
+    let repo = prepare_repo()?;
+    write_file(&repo, "a/1.txt", "one", true)?;
+    write_file(&repo, "long.txt", &long_file(100, 100), true)?;
+    commit(&repo, "first")?;
+    write_file(&repo, "long.txt", &long_file(110, 42), true)?;
+    commit(&repo, "second")?;
+    git(&repo, &["repack", "-a", "-d", "-f", "--window=50"])?;
+    git(&repo, &["prune-packed"])?;
+    Ok(repo)
+}
+
+#[test]
+fn status_is_clean_with_packed_objects() -> BoxResult<()> {
+    let repo = prepare_packed_repo()?;
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("status")
+        .arg("--porcelain")
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+#[test]
+fn diff_reads_deltified_blob() -> BoxResult<()> {
+    let repo = prepare_packed_repo()?;
+    write_file(&repo, "long.txt", &long_file(110, 43), false)?;
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(repo.path())
+        .arg("diff")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("+line 42 of a file"))
+        .stdout(predicates::str::contains("-line 43 of a file"));
+    Ok(())
+}